@@ -0,0 +1,696 @@
+//! Server-side filters select which packets an APRS-IS server sends
+//! to a client. A filter consists of space separated terms, e.g.
+//! `r/48.1/11.6/50 t/m -b/N0CALL*`. A packet passes if it matches any term,
+//! unless it matches a term that starts with '-'.
+//!
+//! Supported terms, see <http://www.aprs-is.net/javAPRSFilter.aspx>:
+//! - `r/lat/lon/dist`: range in km around a position
+//! - `p/aa/bb`: source callsign prefixes
+//! - `b/call1/call2`: source callsigns
+//! - `o/name1/name2`: object and item names
+//! - `t/poimqstunw` and `t/poimqstunw/call/dist`: packet types,
+//!   optionally within a range around the last position of `call`
+//! - `s/pri/alt/over`: symbols of the primary and alternate table,
+//!   the latter optionally with one of the overlays
+//! - `d/digi1/digi2`: digipeaters that have been used
+//! - `a/latN/lonW/latS/lonE`: area
+//! - `e/call1/call2`: entry stations, i.e. the station after the q construct
+//! - `g/call1/call2`: message addressees
+//! - `u/call1/call2`: destination callsigns
+//! - `q/con/I`: q constructs, e.g. `q/C` for qAC. With `I`, positions
+//!   of IGates that forwarded packets from RF pass as well.
+//! - `m/dist`: range in km around the last position of the client
+//! - `f/call/dist`: range in km around the last position of `call`
+//!
+//! Callsigns and names can contain the wildcard '*'.
+//! The last positions of stations come from the `StationContext`.
+
+use std::collections::{HashMap, HashSet};
+
+use super::AprsIsError;
+use AddresseeKind;
+use AprsData;
+use AprsPacket;
+use Callsign;
+use Latitude;
+use Longitude;
+use Via;
+
+const EARTH_RADIUS_KM: f64 = 6371.0;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum FilterKind {
+    Range {
+        latitude: f64,
+        longitude: f64,
+        distance_km: f64,
+    },
+    Prefix(Vec<String>),
+    Budlist(Vec<String>),
+    Object(Vec<String>),
+    Type {
+        types: Vec<u8>,
+        /// Only packets within this range around the last position of the callsign
+        range: Option<(String, f64)>,
+    },
+    Symbol {
+        primary: Vec<u8>,
+        alternate: Vec<u8>,
+        overlays: Vec<u8>,
+    },
+    Digipeater(Vec<String>),
+    Area {
+        north: f64,
+        west: f64,
+        south: f64,
+        east: f64,
+    },
+    EntryStation(Vec<String>),
+    Group(Vec<String>),
+    Unproto(Vec<String>),
+    QConstruct {
+        /// The last letters of the q constructs, e.g. `C` for qAC
+        constructs: Vec<u8>,
+        igates: bool,
+    },
+    MyRange(f64),
+    FriendRange {
+        callsign: String,
+        distance_km: f64,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FilterTerm {
+    /// The term excludes packets instead of including them
+    pub exclude: bool,
+    pub kind: FilterKind,
+}
+
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct Filter {
+    pub terms: Vec<FilterTerm>,
+}
+
+impl Filter {
+    pub fn parse(s: &str) -> Result<Self, AprsIsError> {
+        let terms = s
+            .split_whitespace()
+            .map(FilterTerm::parse)
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self { terms })
+    }
+
+    /// Whether the packet passes the filter.
+    /// A filter without any including terms doesn't pass anything.
+    pub fn matches(&self, packet: &AprsPacket, context: &StationContext) -> bool {
+        let mut included = false;
+
+        for term in &self.terms {
+            if term.kind.matches(packet, context) {
+                if term.exclude {
+                    return false;
+                }
+                included = true;
+            }
+        }
+
+        included
+    }
+}
+
+impl FilterTerm {
+    fn parse(s: &str) -> Result<Self, AprsIsError> {
+        let invalid = || AprsIsError::InvalidFilter(s.to_owned());
+
+        let (exclude, term) = match s.strip_prefix('-') {
+            Some(term) => (true, term),
+            None => (false, s),
+        };
+        let mut parts = term.split('/');
+        let kind = parts.next().ok_or_else(invalid)?;
+        let args: Vec<&str> = parts.collect();
+
+        let number = |i: usize| -> Result<f64, AprsIsError> {
+            args.get(i).and_then(|a| a.parse().ok()).ok_or_else(invalid)
+        };
+        let latitude = |i: usize| -> Result<f64, AprsIsError> {
+            Some(number(i)?)
+                .filter(|n| (-90.0..=90.0).contains(n))
+                .ok_or_else(invalid)
+        };
+        let longitude = |i: usize| -> Result<f64, AprsIsError> {
+            Some(number(i)?)
+                .filter(|n| (-180.0..=180.0).contains(n))
+                .ok_or_else(invalid)
+        };
+        let list = || -> Result<Vec<String>, AprsIsError> {
+            if args.is_empty() || args.iter().any(|a| a.is_empty()) {
+                return Err(invalid());
+            }
+
+            Ok(args.iter().map(|a| a.to_ascii_uppercase()).collect())
+        };
+
+        let kind = match (kind, args.len()) {
+            ("r", 3) => FilterKind::Range {
+                latitude: latitude(0)?,
+                longitude: longitude(1)?,
+                distance_km: number(2)?,
+            },
+            ("p", _) => FilterKind::Prefix(list()?),
+            ("b", _) => FilterKind::Budlist(list()?),
+            ("o", _) => FilterKind::Object(list()?),
+            ("t", 1) | ("t", 3) => {
+                let types = args[0].as_bytes().to_vec();
+                if types.is_empty() || !types.iter().all(|t| b"poimqstunw".contains(t)) {
+                    return Err(invalid());
+                }
+                let range = match args.get(1) {
+                    Some(call) => Some((call.to_ascii_uppercase(), number(2)?)),
+                    None => None,
+                };
+
+                FilterKind::Type { types, range }
+            }
+            ("s", 1..=3) => FilterKind::Symbol {
+                primary: args[0].as_bytes().to_vec(),
+                alternate: args
+                    .get(1)
+                    .map(|a| a.as_bytes().to_vec())
+                    .unwrap_or_default(),
+                overlays: args
+                    .get(2)
+                    .map(|a| a.as_bytes().to_vec())
+                    .unwrap_or_default(),
+            },
+            ("d", _) => FilterKind::Digipeater(list()?),
+            ("a", 4) => {
+                let (north, south) = (latitude(0)?, latitude(2)?);
+                if north < south {
+                    return Err(invalid());
+                }
+
+                // west can be larger than east if the area crosses the dateline
+                FilterKind::Area {
+                    north,
+                    west: longitude(1)?,
+                    south,
+                    east: longitude(3)?,
+                }
+            }
+            ("e", _) => FilterKind::EntryStation(list()?),
+            ("g", _) => FilterKind::Group(list()?),
+            ("u", _) => FilterKind::Unproto(list()?),
+            ("q", 1) | ("q", 2) => {
+                let igates = match args.get(1) {
+                    Some(&"I") => true,
+                    Some(_) => return Err(invalid()),
+                    None => false,
+                };
+
+                FilterKind::QConstruct {
+                    constructs: args[0].as_bytes().to_vec(),
+                    igates,
+                }
+            }
+            ("m", 1) => FilterKind::MyRange(number(0)?),
+            ("f", 2) => FilterKind::FriendRange {
+                callsign: args[0].to_ascii_uppercase(),
+                distance_km: number(1)?,
+            },
+            _ => return Err(invalid()),
+        };
+
+        Ok(Self { exclude, kind })
+    }
+}
+
+impl FilterKind {
+    pub fn matches(&self, packet: &AprsPacket, context: &StationContext) -> bool {
+        match self {
+            FilterKind::Range {
+                latitude,
+                longitude,
+                distance_km,
+            } => within(packet, Some((*latitude, *longitude)), *distance_km),
+            FilterKind::Prefix(prefixes) => {
+                let from = packet.from.to_string().to_ascii_uppercase();
+                prefixes.iter().any(|p| from.starts_with(p.as_str()))
+            }
+            FilterKind::Budlist(calls) => matches_any(calls, &packet.from.to_string()),
+            FilterKind::Object(names) => match object_name(packet) {
+                Some(name) => matches_any(names, &String::from_utf8_lossy(name)),
+                None => false,
+            },
+            FilterKind::Type { types, range } => {
+                let in_range = match range {
+                    Some((call, distance)) => {
+                        within(packet, context.position_by_name(call), *distance)
+                    }
+                    None => true,
+                };
+
+                in_range && types.iter().any(|t| is_type(packet, *t))
+            }
+            FilterKind::Symbol {
+                primary,
+                alternate,
+                overlays,
+            } => match symbol(packet) {
+                Some((b'/', code)) => primary.contains(&code),
+                Some((table, code)) => {
+                    alternate.contains(&code) && (overlays.is_empty() || overlays.contains(&table))
+                }
+                None => false,
+            },
+            FilterKind::Digipeater(calls) => packet.via.iter().any(|v| match v {
+                Via::Callsign(c, true) => matches_any(calls, &c.to_string()),
+                _ => false,
+            }),
+            FilterKind::Area {
+                north,
+                west,
+                south,
+                east,
+            } => match packet_position(packet) {
+                Some((lat, lon)) => {
+                    let within_longitude = if west <= east {
+                        lon >= *west && lon <= *east
+                    } else {
+                        // the area crosses the dateline
+                        lon >= *west || lon <= *east
+                    };
+
+                    lat <= *north && lat >= *south && within_longitude
+                }
+                None => false,
+            },
+            FilterKind::EntryStation(calls) => match entry_station(packet) {
+                Some(c) => matches_any(calls, &c.to_string()),
+                None => false,
+            },
+            FilterKind::Group(calls) => match &packet.data {
+                AprsData::Message(m) => matches_any(calls, &String::from_utf8_lossy(&m.addressee)),
+                _ => false,
+            },
+            FilterKind::Unproto(calls) => matches_any(calls, &packet.data.dest_field().to_string()),
+            FilterKind::QConstruct { constructs, igates } => {
+                let q = packet.via.iter().find_map(|v| match v {
+                    Via::QConstruct(q) => Some(q.as_textual().as_bytes()[2]),
+                    _ => None,
+                });
+                let igate =
+                    *igates && packet_position(packet).is_some() && context.is_igate(&packet.from);
+
+                q.map(|q| constructs.contains(&q)).unwrap_or(false) || igate
+            }
+            FilterKind::MyRange(distance) => {
+                let position = context
+                    .callsign
+                    .as_ref()
+                    .and_then(|c| context.position(c))
+                    .map(|(lat, lon)| (*lat, *lon));
+                within(packet, position, *distance)
+            }
+            FilterKind::FriendRange {
+                callsign,
+                distance_km,
+            } => within(packet, context.position_by_name(callsign), *distance_km),
+        }
+    }
+}
+
+/// What a server knows about other stations, for the filters that
+/// depend on the last position of a station.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StationContext {
+    /// The logged in station, for the `m/` filter
+    pub callsign: Option<Callsign>,
+
+    // by uppercase callsign
+    positions: HashMap<String, (f64, f64)>,
+    igates: HashSet<String>,
+}
+
+impl StationContext {
+    pub fn new(callsign: Callsign) -> Self {
+        Self {
+            callsign: Some(callsign),
+            ..Default::default()
+        }
+    }
+
+    /// Remembers the position of the source of the packet,
+    /// and the IGate that forwarded it from RF.
+    /// Positions of objects and items don't belong to their source.
+    pub fn update(&mut self, packet: &AprsPacket) {
+        if !matches!(packet.data, AprsData::Object(_) | AprsData::Item(_)) {
+            if let Some(position) = packet_position(packet) {
+                self.positions.insert(key(&packet.from), position);
+            }
+        }
+
+        let mut from_rf = false;
+        for v in &packet.via {
+            match v {
+                Via::QConstruct(q) => from_rf = matches!(q.as_textual(), "qAr" | "qAo" | "qAR"),
+                Via::Callsign(c, _) if from_rf => {
+                    self.igates.insert(key(c));
+                    break;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    pub fn set_position(&mut self, callsign: &Callsign, latitude: Latitude, longitude: Longitude) {
+        self.positions
+            .insert(key(callsign), (*latitude, *longitude));
+    }
+
+    /// The last known position of the station
+    pub fn position(&self, callsign: &Callsign) -> Option<(Latitude, Longitude)> {
+        let (lat, lon) = self.position_by_name(&key(callsign))?;
+
+        Some((Latitude::new(lat)?, Longitude::new(lon)?))
+    }
+
+    pub fn is_igate(&self, callsign: &Callsign) -> bool {
+        self.igates.contains(&key(callsign))
+    }
+
+    fn position_by_name(&self, callsign: &str) -> Option<(f64, f64)> {
+        self.positions.get(callsign).copied()
+    }
+}
+
+fn key(callsign: &Callsign) -> String {
+    callsign.to_string().to_ascii_uppercase()
+}
+
+fn within(packet: &AprsPacket, center: Option<(f64, f64)>, distance_km: f64) -> bool {
+    match (packet_position(packet), center) {
+        (Some(position), Some(center)) => distance(position, center) <= distance_km,
+        _ => false,
+    }
+}
+
+// great circle distance in km
+fn distance((lat1, lon1): (f64, f64), (lat2, lon2): (f64, f64)) -> f64 {
+    let (lat1, lon1, lat2, lon2) = (
+        lat1.to_radians(),
+        lon1.to_radians(),
+        lat2.to_radians(),
+        lon2.to_radians(),
+    );
+
+    let a = ((lat2 - lat1) / 2.0).sin().powi(2)
+        + lat1.cos() * lat2.cos() * ((lon2 - lon1) / 2.0).sin().powi(2);
+
+    2.0 * EARTH_RADIUS_KM * a.sqrt().asin()
+}
+
+fn packet_position(packet: &AprsPacket) -> Option<(f64, f64)> {
+    let (lat, lon) = match &packet.data {
+        AprsData::Position(p) => (p.position.latitude, p.position.longitude),
+        AprsData::MicE(m) => (m.latitude, m.longitude),
+        AprsData::Object(o) => (o.position.latitude, o.position.longitude),
+        AprsData::Item(i) => (i.position.latitude, i.position.longitude),
+        AprsData::RawGps(r) => {
+            let p = r.position()?;
+            (p.latitude, p.longitude)
+        }
+        AprsData::Grid(g) => (g.locator.latitude(), g.locator.longitude()),
+        AprsData::ThirdParty(t) => return packet_position(&t.packet),
+        _ => return None,
+    };
+
+    Some((*lat, *lon))
+}
+
+fn symbol(packet: &AprsPacket) -> Option<(u8, u8)> {
+    let position = match &packet.data {
+        AprsData::Position(p) => &p.position,
+        AprsData::Object(o) => &o.position,
+        AprsData::Item(i) => &i.position,
+        AprsData::MicE(m) => return Some((m.symbol_table, m.symbol_code)),
+        _ => return None,
+    };
+
+    Some((position.symbol_table as u8, position.symbol_code as u8))
+}
+
+fn object_name(packet: &AprsPacket) -> Option<&[u8]> {
+    match &packet.data {
+        AprsData::Object(o) => Some(&o.name),
+        AprsData::Item(i) => Some(&i.name),
+        _ => None,
+    }
+}
+
+fn is_type(packet: &AprsPacket, t: u8) -> bool {
+    match (t, &packet.data) {
+        (b'p', AprsData::Position(_)) | (b'p', AprsData::MicE(_)) => true,
+        (b'p', AprsData::RawGps(r)) => r.position().is_some(),
+        (b'o', AprsData::Object(_)) => true,
+        (b'i', AprsData::Item(_)) => true,
+        (b'm', AprsData::Message(_)) => true,
+        (b'q', AprsData::Query(_)) => true,
+        (b's', AprsData::Status(_)) => true,
+        (b't', AprsData::Telemetry(_)) => true,
+        (b'u', AprsData::UserDefined(_)) => true,
+        (b'n', AprsData::Message(m)) => matches!(m.addressee_kind(), AddresseeKind::Nws { .. }),
+        (b'w', AprsData::Weather(_)) => true,
+        (b'w', AprsData::Position(p)) => p.weather.is_some(),
+        (b'w', AprsData::Object(o)) => o.weather.is_some(),
+        (b'w', AprsData::Item(i)) => i.weather.is_some(),
+        _ => false,
+    }
+}
+
+// the station that injected the packet into APRS-IS follows the q construct
+fn entry_station(packet: &AprsPacket) -> Option<&Callsign> {
+    let q = packet
+        .via
+        .iter()
+        .position(|v| matches!(v, Via::QConstruct(_)))?;
+
+    packet.via.get(q + 1)?.callsign().map(|(c, _)| c)
+}
+
+fn matches_any(patterns: &[String], s: &str) -> bool {
+    let s = s.to_ascii_uppercase();
+    patterns
+        .iter()
+        .any(|p| wildcard_match(p.as_bytes(), s.as_bytes()))
+}
+
+// '*' matches any number of characters.
+// On a mismatch, only the last '*' is retried with one more character,
+// which takes O(n·m) time instead of backtracking into every '*'.
+fn wildcard_match(pattern: &[u8], s: &[u8]) -> bool {
+    let (mut p, mut i) = (0, 0);
+    // the position after the last '*' and where its match ends
+    let mut star = None;
+
+    while i < s.len() {
+        match pattern.get(p) {
+            Some(b'*') => {
+                p += 1;
+                star = Some((p, i));
+            }
+            Some(c) if *c == s[i] => {
+                p += 1;
+                i += 1;
+            }
+            _ => match star {
+                Some((star_p, star_i)) => {
+                    p = star_p;
+                    i = star_i + 1;
+                    star = Some((star_p, star_i + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == b'*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn packet(s: &str) -> AprsPacket {
+        AprsPacket::decode_textual(s.as_bytes()).unwrap()
+    }
+
+    fn matches(filter: &str, s: &str) -> bool {
+        Filter::parse(filter)
+            .unwrap()
+            .matches(&packet(s), &StationContext::default())
+    }
+
+    #[test]
+    fn parse() {
+        let filter = Filter::parse("r/33.25/-96.5/50 -b/n0call* t/poi/VE9/10").unwrap();
+        assert_eq!(
+            filter.terms,
+            vec![
+                FilterTerm {
+                    exclude: false,
+                    kind: FilterKind::Range {
+                        latitude: 33.25,
+                        longitude: -96.5,
+                        distance_km: 50.0
+                    }
+                },
+                FilterTerm {
+                    exclude: true,
+                    kind: FilterKind::Budlist(vec!["N0CALL*".to_owned()])
+                },
+                FilterTerm {
+                    exclude: false,
+                    kind: FilterKind::Type {
+                        types: b"poi".to_vec(),
+                        range: Some(("VE9".to_owned(), 10.0))
+                    }
+                },
+            ]
+        );
+
+        for invalid in [
+            "r/33/-96",
+            "x/abc",
+            "t/xyz",
+            "b/",
+            "q/C/X",
+            "m/far",
+            "a/1/2/3",
+            "r/91/0/10",
+            "r/0/-181/10",
+            "r/NaN/0/10",
+            "a/48/-73/50/-71",
+            "a/50/190/48/-71",
+        ] {
+            assert!(
+                matches!(Filter::parse(invalid), Err(AprsIsError::InvalidFilter(_))),
+                "{}",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn range_and_area() {
+        let p = "N0CALL>APRS:!4903.50N/07201.75W-";
+
+        assert!(matches("r/49/-72/10", p));
+        assert!(!matches("r/49/-73/10", p));
+        assert!(matches("a/50/-73/48/-71", p));
+        assert!(!matches("a/50/-71/48/-70", p));
+        assert!(!matches("r/49/-72/10", "N0CALL>APRS:>Hello"));
+
+        // areas crossing the dateline
+        let fiji = "N0CALL>APRS:!1808.00S/17826.00E-";
+        let samoa = "N0CALL>APRS:!1350.00S/17145.00W-";
+        assert!(matches("a/-10/170/-20/-170", fiji));
+        assert!(matches("a/-10/170/-20/-170", samoa));
+        assert!(!matches("a/-10/170/-20/-170", p));
+    }
+
+    #[test]
+    fn callsigns() {
+        let p = "N0CALL-9>APRS,WIDE1-1*,WIDE2-1,qAR,IGATE-1:>Hello";
+
+        assert!(matches("p/N0", p));
+        assert!(!matches("p/N1", p));
+        assert!(matches("b/n0call-9", p));
+        assert!(matches("b/N0*", p));
+        assert!(!matches("b/N0CALL", p));
+        assert!(matches("d/WIDE1*", p));
+        assert!(!matches("d/WIDE2*", p));
+        assert!(matches("e/IGATE*", p));
+        assert!(matches("u/APRS", p));
+        assert!(matches("q/RC", p));
+        assert!(!matches("q/C", p));
+    }
+
+    #[test]
+    fn types_and_symbols() {
+        let object = "N0CALL>APRS:;LEADER   *092345z4903.50N/07201.75W>";
+        let message = "N0CALL>APRS::NWS-WARN :Tornado";
+        let weather = "N0CALL>APRS:_10090556c220s004g005t077r000p000P000h50b09900wRSW";
+
+        assert!(matches("t/o", object));
+        assert!(matches("o/LEAD*", object));
+        assert!(!matches("t/p", object));
+        assert!(matches("t/m", message));
+        assert!(matches("t/n", message));
+        assert!(matches("g/NWS-*", message));
+        assert!(matches("t/w", weather));
+
+        assert!(matches("s/>", object));
+        assert!(!matches("s//>", object));
+        assert!(matches("s//#/T", "N0CALL>APRS:!4903.50NT07201.75W#"));
+        assert!(!matches("s//#/T", "N0CALL>APRS:!4903.50N\\07201.75W#"));
+    }
+
+    #[test]
+    fn exclusions() {
+        let p = "N0CALL>APRS:!4903.50N/07201.75W-";
+
+        assert!(!matches("t/p -b/N0CALL", p));
+        assert!(matches("t/p -b/N1CALL", p));
+        assert!(!matches("-b/N1CALL", p));
+    }
+
+    #[test]
+    fn station_context() {
+        let me = Callsign::new_no_ssid("ME");
+        let mut context = StationContext::new(me.clone());
+        context.set_position(
+            &me,
+            Latitude::new(49.0).unwrap(),
+            Longitude::new(-72.0).unwrap(),
+        );
+        context.update(&packet("FRIEND>APRS,qAR,IGATE:!4000.00N/07000.00W-"));
+
+        let near_me = packet("N0CALL>APRS:!4903.50N/07201.75W-");
+        let near_friend = packet("N0CALL>APRS:!4001.00N/07000.00W-");
+
+        let filter = Filter::parse("m/10").unwrap();
+        assert!(filter.matches(&near_me, &context));
+        assert!(!filter.matches(&near_friend, &context));
+
+        let filter = Filter::parse("f/friend/10").unwrap();
+        assert!(!filter.matches(&near_me, &context));
+        assert!(filter.matches(&near_friend, &context));
+
+        let filter = Filter::parse("t/p/FRIEND/10").unwrap();
+        assert!(filter.matches(&near_friend, &context));
+
+        let filter = Filter::parse("q/C/I").unwrap();
+        assert!(context.is_igate(&Callsign::new_no_ssid("IGATE")));
+        assert!(filter.matches(&packet("IGATE>APRS:!4903.50N/07201.75W-"), &context));
+        assert!(!filter.matches(&near_me, &context));
+    }
+
+    #[test]
+    fn distances() {
+        // one degree of latitude
+        assert_relative_eq!(distance((0.0, 0.0), (1.0, 0.0)), 111.19, epsilon = 0.01);
+        assert!(wildcard_match(b"A*C", b"ABBC"));
+        assert!(!wildcard_match(b"A*C", b"ABBD"));
+        assert!(wildcard_match(b"*", b""));
+        assert!(wildcard_match(b"A*B*C", b"AXBYBC"));
+        assert!(!wildcard_match(b"A*B*C", b"AXBYBD"));
+
+        // this would backtrack into every star
+        let pattern = "*A".repeat(20);
+        let s = format!("{}B", "A".repeat(40));
+        assert!(!wildcard_match(pattern.as_bytes(), s.as_bytes()));
+    }
+}
@@ -0,0 +1,179 @@
+//! Clients log in to APRS-IS with a single line:
+//! `user CALL pass PASSCODE vers SOFTWARE VERSION filter FILTER`
+//!
+//! The passcode is a hash of the callsign. A passcode of -1 logs in
+//! as a receive-only client.
+
+use std::io::Write;
+
+use Callsign;
+use EncodeError;
+
+/// A login line, sent by the client right after connecting
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Login {
+    pub callsign: Callsign,
+    pub passcode: i32,
+    pub software: String,
+    pub version: String,
+    /// A server-side filter, e.g. `r/33/-97/200`
+    pub filter: Option<String>,
+}
+
+impl Login {
+    /// Creates a login with the passcode of `callsign`
+    pub fn new(
+        callsign: Callsign,
+        software: impl Into<String>,
+        version: impl Into<String>,
+    ) -> Self {
+        let passcode = i32::from(passcode(&callsign));
+
+        Self {
+            callsign,
+            passcode,
+            software: software.into(),
+            version: version.into(),
+            filter: None,
+        }
+    }
+
+    /// Creates a login that can't send packets
+    pub fn new_receive_only(
+        callsign: Callsign,
+        software: impl Into<String>,
+        version: impl Into<String>,
+    ) -> Self {
+        Self {
+            passcode: -1,
+            ..Self::new(callsign, software, version)
+        }
+    }
+
+    pub fn with_filter(mut self, filter: impl Into<String>) -> Self {
+        self.filter = Some(filter.into());
+        self
+    }
+
+    /// Whether the passcode matches the callsign
+    pub fn is_verified(&self) -> bool {
+        self.passcode == i32::from(passcode(&self.callsign))
+    }
+
+    /// Decodes a login line without the line terminator,
+    /// e.g. on the server side.
+    pub fn decode(b: &[u8]) -> Option<Self> {
+        let s = std::str::from_utf8(b).ok()?;
+        let mut words = s.split_whitespace();
+
+        if words.next()? != "user" {
+            return None;
+        }
+        let callsign = Callsign::new(words.next()?)?;
+
+        let mut passcode = None;
+        let mut software = None;
+        let mut filter = None;
+        while let Some(word) = words.next() {
+            match word {
+                "pass" => passcode = Some(words.next()?.parse().ok()?),
+                "vers" => software = Some((words.next()?, words.next()?)),
+                "filter" => {
+                    // the filter is the remainder of the line
+                    filter = Some(words.by_ref().collect::<Vec<_>>().join(" "));
+                }
+                _ => {}
+            }
+        }
+        let (software, version) = software.unwrap_or(("", ""));
+
+        Some(Self {
+            callsign,
+            passcode: passcode.unwrap_or(-1),
+            software: software.to_owned(),
+            version: version.to_owned(),
+            filter,
+        })
+    }
+
+    /// Encodes the login line without the line terminator
+    pub fn encode<W: Write>(&self, buf: &mut W) -> Result<(), EncodeError> {
+        write!(
+            buf,
+            "user {} pass {} vers {} {}",
+            self.callsign, self.passcode, self.software, self.version
+        )?;
+
+        if let Some(filter) = &self.filter {
+            write!(buf, " filter {}", filter)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The APRS-IS passcode of a callsign. The SSID is ignored.
+pub fn passcode(callsign: &Callsign) -> u16 {
+    let call = callsign.call().to_ascii_uppercase();
+
+    let mut hash: u16 = 0x73e2;
+    for pair in call.as_bytes().chunks(2) {
+        hash ^= u16::from(pair[0]) << 8;
+        if let Some(&c) = pair.get(1) {
+            hash ^= u16::from(c);
+        }
+    }
+
+    hash & 0x7fff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn passcodes() {
+        assert_eq!(passcode(&Callsign::new_no_ssid("N0CALL")), 13023);
+        assert_eq!(passcode(&Callsign::new_with_ssid("n0call", "10")), 13023);
+        assert_eq!(passcode(&Callsign::new_no_ssid("VE9")), 7335);
+    }
+
+    #[test]
+    fn encode_login() {
+        let login = Login::new(Callsign::new_no_ssid("N0CALL"), "aprs-parser", "0.4.2")
+            .with_filter("r/33/-97/200 t/m");
+
+        let mut buf = vec![];
+        login.encode(&mut buf).unwrap();
+        assert_eq!(
+            buf,
+            &b"user N0CALL pass 13023 vers aprs-parser 0.4.2 filter r/33/-97/200 t/m"[..]
+        );
+        assert!(login.is_verified());
+
+        let login = Login::new_receive_only(Callsign::new_with_ssid("N0CALL", "1"), "test", "1.0");
+        let mut buf = vec![];
+        login.encode(&mut buf).unwrap();
+        assert_eq!(buf, &b"user N0CALL-1 pass -1 vers test 1.0"[..]);
+        assert!(!login.is_verified());
+    }
+
+    #[test]
+    fn decode_login() {
+        let login =
+            Login::decode(b"user N0CALL pass 13023 vers aprs-parser 0.4.2 filter r/33/-97/200 t/m")
+                .unwrap();
+        assert_eq!(
+            login,
+            Login::new(Callsign::new_no_ssid("N0CALL"), "aprs-parser", "0.4.2")
+                .with_filter("r/33/-97/200 t/m")
+        );
+
+        let login = Login::decode(b"user N0CALL").unwrap();
+        assert_eq!(login.passcode, -1);
+        assert_eq!(login.filter, None);
+
+        assert_eq!(Login::decode(b"# comment"), None);
+        assert_eq!(Login::decode(b"user N0CALL pass abc"), None);
+    }
+}
@@ -0,0 +1,64 @@
+//! Support for the [APRS-IS] protocol, which connects APRS stations
+//! over the internet. After connecting, a client sends a `Login`
+//! and then exchanges packets with the server, one per line.
+//! Lines starting with '#' are `ServerMessage`s.
+//! `Filter` applies server-side filters locally.
+//!
+//! [APRS-IS]: http://www.aprs-is.net/
+//!
+//! # Usage
+//!
+//! ```rust,no_run
+//! extern crate aprs_parser;
+//!
+//! use std::net::TcpStream;
+//!
+//! use aprs_parser::aprs_is::{AprsIsLine, AprsIsReader, AprsIsWriter, Login};
+//! use aprs_parser::Callsign;
+//!
+//! fn main() {
+//!     let stream = TcpStream::connect("rotate.aprs2.net:14580").unwrap();
+//!     let mut writer = AprsIsWriter::new(stream.try_clone().unwrap());
+//!
+//!     let login = Login::new_receive_only(Callsign::new_no_ssid("N0CALL"), "example", "1.0")
+//!         .with_filter("r/48.1/11.6/50");
+//!     writer.login(&login).unwrap();
+//!
+//!     // the reader stops after an I/O error
+//!     for line in AprsIsReader::new(stream) {
+//!         match line {
+//!             Ok(AprsIsLine::Packet(packet)) => println!("{:?}", packet),
+//!             Ok(AprsIsLine::Server(_)) => {}
+//!             Err(e) => eprintln!("{}", e),
+//!         }
+//!     }
+//! }
+//! ```
+
+mod filter;
+mod login;
+mod server;
+mod stream;
+
+pub use self::filter::{Filter, FilterKind, FilterTerm, StationContext};
+pub use self::login::{passcode, Login};
+pub use self::server::ServerMessage;
+pub use self::stream::{AprsIsLine, AprsIsReader, AprsIsWriter, MAX_LINE_LEN};
+
+use DecodeError;
+use EncodeError;
+
+#[derive(Debug, thiserror::Error)]
+pub enum AprsIsError {
+    #[error("Invalid line {line:?}: {error}")]
+    Decode { line: Vec<u8>, error: DecodeError },
+    #[error(transparent)]
+    Encode(#[from] EncodeError),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    /// A line longer than the limit of APRS-IS, with its first bytes
+    #[error("Line too long, starting with {0:?}")]
+    LineTooLong(Vec<u8>),
+    #[error("Invalid filter: {0}")]
+    InvalidFilter(String),
+}
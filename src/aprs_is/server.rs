@@ -0,0 +1,130 @@
+//! Lines starting with '#' are comments from the server,
+//! e.g. its banner, keepalives and the response to the login.
+//!
+//! Examples:
+//! - "# aprsc 2.1.10-gd72a17c"
+//! - "# logresp N0CALL verified, server T2TEST"
+
+use std::io::Write;
+
+use Callsign;
+use EncodeError;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ServerMessage {
+    /// The response to the login line
+    LogResp {
+        callsign: Callsign,
+        verified: bool,
+        server: Option<String>,
+    },
+    /// Any other comment, without the leading "# "
+    Comment(Vec<u8>),
+}
+
+impl ServerMessage {
+    /// Decodes a line without the line terminator.
+    /// Returns `None` if the line isn't a comment.
+    pub fn decode(b: &[u8]) -> Option<Self> {
+        let comment = b.strip_prefix(b"#")?;
+        let comment = comment.strip_prefix(b" ").unwrap_or(comment);
+
+        Some(Self::decode_logresp(comment).unwrap_or_else(|| Self::Comment(comment.to_vec())))
+    }
+
+    fn decode_logresp(b: &[u8]) -> Option<Self> {
+        let s = std::str::from_utf8(b).ok()?;
+        let mut words = s.split_whitespace();
+
+        if words.next()? != "logresp" {
+            return None;
+        }
+        let callsign = Callsign::new(words.next()?)?;
+        let verified = match words.next()?.trim_end_matches(',') {
+            "verified" => true,
+            "unverified" => false,
+            _ => return None,
+        };
+        let server = match words.next() {
+            Some("server") => Some(words.next()?.to_owned()),
+            _ => None,
+        };
+
+        Some(Self::LogResp {
+            callsign,
+            verified,
+            server,
+        })
+    }
+
+    pub fn encode<W: Write>(&self, buf: &mut W) -> Result<(), EncodeError> {
+        match self {
+            Self::LogResp {
+                callsign,
+                verified,
+                server,
+            } => {
+                let status = if *verified { "verified" } else { "unverified" };
+                write!(buf, "# logresp {} {}", callsign, status)?;
+                if let Some(server) = server {
+                    write!(buf, ", server {}", server)?;
+                }
+            }
+            Self::Comment(comment) => {
+                buf.write_all(b"# ")?;
+                buf.write_all(comment)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_logresp() {
+        let line = &b"# logresp N0CALL-1 verified, server T2TEST"[..];
+        let message = ServerMessage::decode(line).unwrap();
+        assert_eq!(
+            message,
+            ServerMessage::LogResp {
+                callsign: Callsign::new_with_ssid("N0CALL", "1"),
+                verified: true,
+                server: Some("T2TEST".to_owned()),
+            }
+        );
+
+        let mut buf = vec![];
+        message.encode(&mut buf).unwrap();
+        assert_eq!(buf, line);
+
+        assert_eq!(
+            ServerMessage::decode(b"# logresp N0CALL unverified"),
+            Some(ServerMessage::LogResp {
+                callsign: Callsign::new_no_ssid("N0CALL"),
+                verified: false,
+                server: None,
+            })
+        );
+    }
+
+    #[test]
+    fn decode_comment() {
+        assert_eq!(
+            ServerMessage::decode(b"# aprsc 2.1.10-gd72a17c"),
+            Some(ServerMessage::Comment(b"aprsc 2.1.10-gd72a17c".to_vec()))
+        );
+        assert_eq!(
+            ServerMessage::decode(b"#keepalive"),
+            Some(ServerMessage::Comment(b"keepalive".to_vec()))
+        );
+        assert_eq!(
+            ServerMessage::decode(b"# logresp nonsense"),
+            Some(ServerMessage::Comment(b"logresp nonsense".to_vec()))
+        );
+        assert_eq!(ServerMessage::decode(b"N0CALL>APRS:>Hello"), None);
+    }
+}
@@ -0,0 +1,356 @@
+//! APRS-IS is line oriented: every packet and server message
+//! is terminated by "\r\n".
+
+use std::io::{BufRead, BufReader, Read, Write};
+
+use super::{AprsIsError, Login, ServerMessage};
+use AprsPacket;
+
+/// The maximum length of a line without the line terminator
+pub const MAX_LINE_LEN: usize = 512;
+
+/// A line received from an APRS-IS server
+// almost all lines are packets, boxing them wouldn't save anything
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Debug, PartialEq)]
+pub enum AprsIsLine {
+    Packet(AprsPacket),
+    Server(ServerMessage),
+}
+
+impl AprsIsLine {
+    /// Decodes a line without the line terminator
+    pub fn decode(b: &[u8]) -> Result<Self, AprsIsError> {
+        if let Some(message) = ServerMessage::decode(b) {
+            return Ok(Self::Server(message));
+        }
+
+        AprsPacket::decode_textual(b)
+            .map(Self::Packet)
+            .map_err(|error| AprsIsError::Decode {
+                line: b.to_vec(),
+                error,
+            })
+    }
+}
+
+/// Reads lines from an APRS-IS connection.
+/// A line that can't be decoded is returned as `AprsIsError::Decode`,
+/// a line longer than `MAX_LINE_LEN` as `AprsIsError::LineTooLong`.
+/// Reading can continue with the next line.
+/// Iterating stops after an `AprsIsError::Io`.
+pub struct AprsIsReader<R> {
+    inner: BufReader<R>,
+    buf: Vec<u8>,
+    failed: bool,
+}
+
+impl<R: Read> AprsIsReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner: BufReader::new(inner),
+            buf: vec![],
+            failed: false,
+        }
+    }
+
+    /// Returns `Ok(None)` once the connection is closed.
+    /// Empty lines are skipped.
+    pub fn read_line(&mut self) -> Result<Option<AprsIsLine>, AprsIsError> {
+        // room for the line terminator
+        let limit = MAX_LINE_LEN as u64 + 2;
+
+        loop {
+            self.buf.clear();
+            let read = (&mut self.inner)
+                .take(limit)
+                .read_until(b'\n', &mut self.buf)?;
+            if read == 0 {
+                return Ok(None);
+            }
+
+            if read as u64 == limit && self.buf.last() != Some(&b'\n') {
+                self.skip_line()?;
+                self.buf.truncate(MAX_LINE_LEN);
+                return Err(AprsIsError::LineTooLong(self.buf.clone()));
+            }
+
+            let mut line = &self.buf[..];
+            while let Some((b'\r' | b'\n', rest)) = line.split_last() {
+                line = rest;
+            }
+
+            if line.len() > MAX_LINE_LEN {
+                return Err(AprsIsError::LineTooLong(line[..MAX_LINE_LEN].to_vec()));
+            }
+
+            if !line.is_empty() {
+                return AprsIsLine::decode(line).map(Some);
+            }
+        }
+    }
+
+    // Discards the rest of a line that is too long without buffering it
+    fn skip_line(&mut self) -> Result<(), AprsIsError> {
+        loop {
+            let (used, done) = {
+                let available = self.inner.fill_buf()?;
+                match available.iter().position(|&c| c == b'\n') {
+                    Some(i) => (i + 1, true),
+                    None => (available.len(), available.is_empty()),
+                }
+            };
+            self.inner.consume(used);
+
+            if done {
+                return Ok(());
+            }
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner.into_inner()
+    }
+}
+
+impl<R: Read> Iterator for AprsIsReader<R> {
+    type Item = Result<AprsIsLine, AprsIsError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        let result = self.read_line();
+        // the connection is most likely broken
+        self.failed = matches!(result, Err(AprsIsError::Io(_)));
+        result.transpose()
+    }
+}
+
+/// Writes lines to an APRS-IS connection
+pub struct AprsIsWriter<W> {
+    inner: W,
+}
+
+impl<W: Write> AprsIsWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self { inner }
+    }
+
+    pub fn login(&mut self, login: &Login) -> Result<(), AprsIsError> {
+        let mut line = vec![];
+        login.encode(&mut line)?;
+        self.write_line(&line)
+    }
+
+    pub fn send_packet(&mut self, packet: &AprsPacket) -> Result<(), AprsIsError> {
+        let mut line = vec![];
+        packet.encode_textual(&mut line)?;
+        self.write_line(&line)
+    }
+
+    /// Sends a comment, e.g. as a keepalive.
+    /// Servers use this to send `ServerMessage`s.
+    pub fn send_server_message(&mut self, message: &ServerMessage) -> Result<(), AprsIsError> {
+        let mut line = vec![];
+        message.encode(&mut line)?;
+        self.write_line(&line)
+    }
+
+    // lines are written at once, so they aren't split if the connection is shared
+    fn write_line(&mut self, line: &[u8]) -> Result<(), AprsIsError> {
+        let mut buf = Vec::with_capacity(line.len() + 2);
+        buf.extend_from_slice(line);
+        buf.extend_from_slice(b"\r\n");
+
+        self.inner.write_all(&buf)?;
+        self.inner.flush()?;
+
+        Ok(())
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
+    use AprsData;
+    use Callsign;
+    use DecodeError;
+
+    #[test]
+    fn read_lines() {
+        let input = &b"# aprsc 2.1.10\r\nN0CALL>APRS:>Hello\r\n\r\ninvalid\nN0CALL>APRS:>World"[..];
+        let mut reader = AprsIsReader::new(input);
+
+        assert_eq!(
+            reader.read_line().unwrap(),
+            Some(AprsIsLine::Server(ServerMessage::Comment(
+                b"aprsc 2.1.10".to_vec()
+            )))
+        );
+        assert!(matches!(
+            reader.read_line(),
+            Ok(Some(AprsIsLine::Packet(AprsPacket {
+                data: AprsData::Status(_),
+                ..
+            })))
+        ));
+        match reader.read_line() {
+            Err(AprsIsError::Decode { line, error }) => {
+                assert_eq!(line, b"invalid");
+                assert_eq!(error, DecodeError::InvalidPacket(b"invalid".to_vec()));
+            }
+            r => panic!("Unexpected result: {:?}", r),
+        }
+
+        // the last line doesn't need a terminator
+        assert_eq!(reader.count(), 1);
+    }
+
+    #[test]
+    fn read_long_lines() {
+        let mut input = b"N0CALL>APRS:>".to_vec();
+        input.extend_from_slice(&[b'x'; 1000]);
+        input.extend_from_slice(b"\r\nN0CALL>APRS:>");
+        input.extend_from_slice(&[b'x'; MAX_LINE_LEN - 13]);
+        input.extend_from_slice(b"\r\nN0CALL>APRS:>");
+        input.extend_from_slice(&[b'x'; MAX_LINE_LEN - 12]);
+        input.extend_from_slice(b"\nN0CALL>APRS:>Hello");
+        let mut reader = AprsIsReader::new(&input[..]);
+
+        match reader.read_line() {
+            Err(AprsIsError::LineTooLong(start)) => assert_eq!(start, &input[..MAX_LINE_LEN]),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+        // the longest allowed line
+        assert!(matches!(
+            reader.read_line(),
+            Ok(Some(AprsIsLine::Packet(_)))
+        ));
+        assert!(matches!(
+            reader.read_line(),
+            Err(AprsIsError::LineTooLong(_))
+        ));
+        assert!(matches!(
+            reader.read_line(),
+            Ok(Some(AprsIsLine::Packet(_)))
+        ));
+        assert!(matches!(reader.read_line(), Ok(None)));
+    }
+
+    struct BrokenConnection;
+
+    impl Read for BrokenConnection {
+        fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+            Err(std::io::ErrorKind::ConnectionReset.into())
+        }
+    }
+
+    #[test]
+    fn stop_after_io_error() {
+        let mut reader = AprsIsReader::new(BrokenConnection);
+        assert!(matches!(reader.next(), Some(Err(AprsIsError::Io(_)))));
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn write_lines() {
+        let mut writer = AprsIsWriter::new(vec![]);
+        writer
+            .login(&Login::new_receive_only(
+                Callsign::new_no_ssid("N0CALL"),
+                "test",
+                "1.0",
+            ))
+            .unwrap();
+        writer
+            .send_packet(&AprsPacket::decode_textual(b"N0CALL>APRS:>Hello").unwrap())
+            .unwrap();
+
+        assert_eq!(
+            writer.into_inner(),
+            &b"user N0CALL pass -1 vers test 1.0\r\nN0CALL>APRS:>Hello\r\n"[..]
+        );
+    }
+
+    // A stand-in for an APRS-IS server: it greets the client, checks the login
+    // and echoes every packet it receives back to the client.
+    fn serve(stream: TcpStream) {
+        let mut writer = AprsIsWriter::new(stream.try_clone().unwrap());
+        let mut reader = BufReader::new(stream);
+
+        writer
+            .send_server_message(&ServerMessage::Comment(b"stand-in 1.0".to_vec()))
+            .unwrap();
+
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        let login = Login::decode(line.trim_end().as_bytes()).unwrap();
+        writer
+            .send_server_message(&ServerMessage::LogResp {
+                callsign: login.callsign.clone(),
+                verified: login.is_verified(),
+                server: Some("STANDIN".to_owned()),
+            })
+            .unwrap();
+
+        let mut reader = AprsIsReader::new(reader.into_inner());
+        while let Some(line) = reader.read_line().unwrap() {
+            if let AprsIsLine::Packet(p) = line {
+                writer.send_packet(&p).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn tcp_session() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = thread::spawn(move || serve(listener.accept().unwrap().0));
+
+        let stream = TcpStream::connect(address).unwrap();
+        let mut reader = AprsIsReader::new(stream.try_clone().unwrap());
+        let mut writer = AprsIsWriter::new(stream);
+
+        writer
+            .login(&Login::new(Callsign::new_no_ssid("N0CALL"), "test", "1.0").with_filter("t/s"))
+            .unwrap();
+
+        assert_eq!(
+            reader.read_line().unwrap(),
+            Some(AprsIsLine::Server(ServerMessage::Comment(
+                b"stand-in 1.0".to_vec()
+            )))
+        );
+        assert_eq!(
+            reader.read_line().unwrap(),
+            Some(AprsIsLine::Server(ServerMessage::LogResp {
+                callsign: Callsign::new_no_ssid("N0CALL"),
+                verified: true,
+                server: Some("STANDIN".to_owned()),
+            }))
+        );
+
+        let packet = AprsPacket::decode_textual(b"N0CALL>APRS,TCPIP*:>Hello").unwrap();
+        writer.send_packet(&packet).unwrap();
+        assert_eq!(
+            reader.read_line().unwrap(),
+            Some(AprsIsLine::Packet(packet))
+        );
+
+        // closing the connection stops the server
+        writer
+            .into_inner()
+            .shutdown(std::net::Shutdown::Write)
+            .unwrap();
+        server.join().unwrap();
+        assert_eq!(reader.read_line().unwrap(), None);
+    }
+}
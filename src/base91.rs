@@ -10,11 +10,11 @@ pub(crate) fn encode_ascii<W: Write>(
     let mut val = val.round();
     let mut digit_buf = vec![];
 
-    debug_assert!(!val.is_nan() && val > 0.0 && !val.is_infinite());
+    debug_assert!(!val.is_nan() && val >= 0.0 && !val.is_infinite());
 
-    while val > 1.0 {
+    while val >= 1.0 {
         let x = val % 91.0;
-        val /= 91.0;
+        val = (val - x) / 91.0;
 
         digit_buf.push(digit_to_ascii(x as u8));
     }
@@ -86,6 +86,20 @@ mod tests {
         assert_eq!(expected, buf);
     }
 
+    #[test]
+    fn encode_small_values() {
+        for (val, expected) in [
+            (0.0, &b"!!"[..]),
+            (1.0, b"!\""),
+            (91.0, b"\"!"),
+            (8280.0, b"{{"),
+        ] {
+            let mut buf = vec![];
+            encode_ascii(val, &mut buf, 2).unwrap();
+            assert_eq!(expected, buf);
+        }
+    }
+
     #[test]
     fn decode_works() {
         let ascii = &b"<*e7"[..];
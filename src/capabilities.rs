@@ -0,0 +1,168 @@
+//! A Station Capabilities report starts with the '<' APRS Data Type Identifier.
+//! It is a comma separated list of tokens, which may have a value.
+//! Stations usually send it in response to an `?IGATE?` query.
+//!
+//! Example:
+//! - "<IGATE,MSG_CNT=43,LOC_CNT=14"
+
+use std::io::Write;
+
+use Callsign;
+use DecodeError;
+use EncodeError;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Capability {
+    /// The key as it was sent, including any spaces after the comma.
+    /// Empty tokens are kept with an empty key.
+    pub key: Vec<u8>,
+    pub value: Option<Vec<u8>>,
+}
+
+impl Capability {
+    // the key without the spaces that may follow the comma
+    fn trimmed_key(&self) -> &[u8] {
+        let start = self.key.iter().take_while(|&&c| c == b' ').count();
+        &self.key[start..]
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AprsCapabilities {
+    pub to: Callsign,
+
+    pub capabilities: Vec<Capability>,
+}
+
+impl AprsCapabilities {
+    /// Returns `true` if the station has a capability with the given key.
+    /// Spaces before the key are ignored.
+    pub fn has(&self, key: &[u8]) -> bool {
+        self.capabilities.iter().any(|c| c.trimmed_key() == key)
+    }
+
+    /// Returns the value of the first capability with the given key.
+    /// Spaces before the key are ignored.
+    pub fn value(&self, key: &[u8]) -> Option<&[u8]> {
+        self.capabilities
+            .iter()
+            .find(|c| c.trimmed_key() == key)
+            .and_then(|c| c.value.as_deref())
+    }
+
+    /// The tokens are kept exactly as they were sent,
+    /// so that the report is re-encoded unchanged.
+    pub fn decode(b: &[u8], to: Callsign) -> Result<Self, DecodeError> {
+        if b.is_empty() {
+            return Ok(Self {
+                to,
+                capabilities: vec![],
+            });
+        }
+
+        let capabilities = b
+            .split(|&c| c == b',')
+            .map(|token| {
+                let mut splitter = token.splitn(2, |&c| c == b'=');
+                Capability {
+                    key: splitter.next().unwrap_or(&[]).to_vec(),
+                    value: splitter.next().map(|v| v.to_vec()),
+                }
+            })
+            .collect();
+
+        Ok(Self { to, capabilities })
+    }
+
+    pub fn encode<W: Write>(&self, buf: &mut W) -> Result<(), EncodeError> {
+        buf.write_all(b"<")?;
+
+        for (i, capability) in self.capabilities.iter().enumerate() {
+            if i > 0 {
+                buf.write_all(b",")?;
+            }
+
+            buf.write_all(&capability.key)?;
+            if let Some(value) = &capability.value {
+                buf.write_all(b"=")?;
+                buf.write_all(value)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn default_callsign() -> Callsign {
+        Callsign::new_no_ssid("VE9")
+    }
+
+    #[test]
+    fn decode_capabilities() {
+        let result =
+            AprsCapabilities::decode(b"IGATE, MSG_CNT=43,LOC_CNT=14", default_callsign()).unwrap();
+
+        assert_eq!(
+            result.capabilities,
+            vec![
+                Capability {
+                    key: b"IGATE".to_vec(),
+                    value: None,
+                },
+                Capability {
+                    key: b" MSG_CNT".to_vec(),
+                    value: Some(b"43".to_vec()),
+                },
+                Capability {
+                    key: b"LOC_CNT".to_vec(),
+                    value: Some(b"14".to_vec()),
+                },
+            ]
+        );
+        assert!(result.has(b"IGATE"));
+        assert_eq!(result.value(b"MSG_CNT"), Some(&b"43"[..]));
+        assert_eq!(result.value(b"IGATE"), None);
+    }
+
+    #[test]
+    fn encode_capabilities() {
+        let capabilities = AprsCapabilities {
+            to: default_callsign(),
+            capabilities: vec![
+                Capability {
+                    key: b"IGATE".to_vec(),
+                    value: None,
+                },
+                Capability {
+                    key: b"MSG_CNT".to_vec(),
+                    value: Some(b"43".to_vec()),
+                },
+            ],
+        };
+
+        let mut buf = vec![];
+        capabilities.encode(&mut buf).unwrap();
+        assert_eq!(buf, b"<IGATE,MSG_CNT=43");
+    }
+
+    #[test]
+    fn decode_and_reencode() {
+        for original in [
+            &b""[..],
+            b"IGATE",
+            b"IGATE, MSG_CNT=43,LOC_CNT=14",
+            b"IGATE,,MSG_CNT=",
+            b",=,  ",
+        ] {
+            let capabilities = AprsCapabilities::decode(original, default_callsign()).unwrap();
+
+            let mut buf = vec![];
+            capabilities.encode(&mut buf).unwrap();
+            assert_eq!(&buf[1..], original);
+        }
+    }
+}
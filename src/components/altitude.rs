@@ -0,0 +1,108 @@
+//! The altitude can be included anywhere in the comment of a position
+//! report as `/A=aaaaaa`, with the altitude in feet.
+//! Negative altitudes use the first digit for the sign, e.g. `/A=-00012`.
+
+use std::io::Write;
+
+use crate::{bytes::parse_bytes, EncodeError};
+
+const MIN_ALTITUDE: i32 = -99_999;
+const MAX_ALTITUDE: i32 = 999_999;
+
+/// Looks for the first valid altitude in `comment`.
+/// If there is one, it is removed from the comment.
+/// Also returns the index the altitude was located at.
+pub(crate) fn extract(comment: &mut Vec<u8>) -> Option<(i32, usize)> {
+    let (index, altitude) = comment
+        .windows(9)
+        .enumerate()
+        .filter(|(_, w)| w.starts_with(b"/A="))
+        .find_map(|(i, w)| decode(&w[3..]).map(|a| (i, a)))?;
+
+    comment.drain(index..(index + 9));
+
+    Some((altitude, index))
+}
+
+fn decode(b: &[u8]) -> Option<i32> {
+    let digits = match b.first()? {
+        b'-' => &b[1..],
+        _ => b,
+    };
+    if !digits.iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+
+    parse_bytes(b)
+}
+
+pub(crate) fn encode<W: Write>(buf: &mut W, altitude: i32) -> Result<(), EncodeError> {
+    if !(MIN_ALTITUDE..=MAX_ALTITUDE).contains(&altitude) {
+        return Err(EncodeError::InvalidAltitude(altitude));
+    }
+
+    write!(buf, "/A={:06}", altitude)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extract_altitude() {
+        let mut comment = b"Hello/A=001234 world".to_vec();
+
+        assert_eq!(extract(&mut comment), Some((1234, 5)));
+        assert_eq!(comment, b"Hello world");
+    }
+
+    #[test]
+    fn extract_negative_altitude() {
+        let mut comment = b"/A=-00012".to_vec();
+
+        assert_eq!(extract(&mut comment), Some((-12, 0)));
+        assert!(comment.is_empty());
+    }
+
+    #[test]
+    fn extract_first_valid_altitude() {
+        let mut comment = b"/A=12/A=000100/A=000200".to_vec();
+
+        assert_eq!(extract(&mut comment), Some((100, 5)));
+        assert_eq!(comment, b"/A=12/A=000200");
+    }
+
+    #[test]
+    fn extract_invalid_altitude() {
+        for o in [
+            &b"Hello"[..],
+            b"/A=12345",
+            b"/A=+12345",
+            b"/A= 12345",
+            b"/A=--1234",
+        ] {
+            let mut comment = o.to_vec();
+            assert_eq!(extract(&mut comment), None);
+            assert_eq!(comment, o);
+        }
+    }
+
+    #[test]
+    fn encode_altitude() {
+        let mut buf = vec![];
+        encode(&mut buf, 1234).unwrap();
+        encode(&mut buf, -12).unwrap();
+        assert_eq!(buf, b"/A=001234/A=-00012");
+
+        assert!(matches!(
+            encode(&mut vec![], 1_000_000),
+            Err(EncodeError::InvalidAltitude(1_000_000))
+        ));
+        assert!(matches!(
+            encode(&mut vec![], -100_000),
+            Err(EncodeError::InvalidAltitude(-100_000))
+        ));
+    }
+}
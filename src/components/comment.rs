@@ -0,0 +1,181 @@
+//! The comment of positions, objects, items and Mic-E reports can contain
+//! an altitude, base91 telemetry and a DAO. They are removed from the comment
+//! when decoding and written back to the same place when encoding,
+//! so that packets are re-encoded unchanged.
+
+use std::io::Write;
+
+use crate::{CommentTelemetry, Dao, EncodeError, Position};
+
+use super::altitude;
+
+/// Where the tokens that were removed from a comment were located,
+/// counted from the end of the comment so that they stay in place
+/// if the comment is replaced.
+/// `None` is the default place, which is also used for new packets:
+/// the altitude is written before the comment, the DAO after
+/// the comment and the telemetry.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct CommentOffsets {
+    /// The number of comment bytes after the `/A=` altitude
+    pub altitude: Option<usize>,
+    /// The number of bytes after the `!DAO!`, including the altitude
+    /// and the telemetry
+    pub dao: Option<usize>,
+}
+
+/// The tokens that were found in a comment
+pub(crate) struct CommentTokens {
+    pub altitude: Option<i32>,
+    pub telemetry: Option<CommentTelemetry>,
+    /// The DAO of Mic-E reports, which isn't applied to their coordinates
+    pub dao: Option<Dao>,
+    pub offsets: CommentOffsets,
+}
+
+impl CommentTokens {
+    /// Removes the DAO, the telemetry and the altitude from `comment`.
+    /// The DAO is applied to `position`. Mic-E reports don't pass one,
+    /// their DAO is returned with its steps instead.
+    pub(crate) fn extract(comment: &mut Vec<u8>, position: Option<&mut Position>) -> Self {
+        let (dao, dao_offset) = match position {
+            Some(p) => (None, p.extract_dao(comment)),
+            None => match Dao::extract(comment) {
+                Some((dao, _, _, after)) => (Some(dao), Some(after)),
+                None => (None, None),
+            },
+        };
+        let dao_offset = dao_offset.filter(|after| *after != 0);
+        let telemetry = CommentTelemetry::extract(comment);
+        let (altitude, offset) = match altitude::extract(comment) {
+            Some((altitude, 0)) => (Some(altitude), None),
+            Some((altitude, index)) => (Some(altitude), Some(comment.len() - index)),
+            None => (None, None),
+        };
+
+        Self {
+            altitude,
+            telemetry,
+            dao,
+            offsets: CommentOffsets {
+                altitude: offset,
+                dao: dao_offset,
+            },
+        }
+    }
+}
+
+/// Writes `comment` with the altitude, the telemetry
+/// and the encoded `dao` token put back in
+pub(crate) fn encode<W: Write>(
+    buf: &mut W,
+    comment: &[u8],
+    altitude: Option<i32>,
+    telemetry: Option<&CommentTelemetry>,
+    dao: &[u8],
+    offsets: &CommentOffsets,
+) -> Result<(), EncodeError> {
+    let mut encoded = vec![];
+
+    // the comment could have been changed since it was decoded
+    let after = offsets.altitude.unwrap_or(comment.len()).min(comment.len());
+    let (before, after) = comment.split_at(comment.len() - after);
+
+    encoded.extend_from_slice(before);
+    if let Some(altitude) = altitude {
+        altitude::encode(&mut encoded, altitude)?;
+    }
+    encoded.extend_from_slice(after);
+
+    if let Some(telemetry) = telemetry {
+        telemetry.encode(&mut encoded)?;
+    }
+
+    if !dao.is_empty() {
+        let after = offsets.dao.unwrap_or(0).min(encoded.len());
+        let mut index = encoded.len() - after;
+        // a token directly after a word wouldn't be decoded as a DAO
+        if index > 0 && encoded[index - 1].is_ascii_alphabetic() {
+            encoded.insert(index, b' ');
+            index += 1;
+        }
+        encoded.splice(index..index, dao.iter().copied());
+    }
+
+    buf.write_all(&encoded)?;
+
+    Ok(())
+}
+
+/// The length of the comment as `encode` writes it,
+/// e.g. to check it against the limits of the spec
+pub(crate) fn encoded_len(
+    comment: &[u8],
+    altitude: Option<i32>,
+    telemetry: Option<&CommentTelemetry>,
+    dao: &[u8],
+    offsets: &CommentOffsets,
+) -> usize {
+    let mut encoded = vec![];
+    match encode(&mut encoded, comment, altitude, telemetry, dao, offsets) {
+        Ok(()) => encoded.len(),
+        // encoding fails anyway, so only the comment itself is measured
+        Err(_) => comment.len(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reinsert_altitude() {
+        for original in [
+            &b"/A=001000Hello"[..],
+            b"Hello/A=001000",
+            b"Hello /A=001000 world|!\"&7|",
+            b"Hello",
+        ] {
+            let mut comment = original.to_vec();
+            let tokens = CommentTokens::extract(&mut comment, None);
+
+            let mut buf = vec![];
+            encode(
+                &mut buf,
+                &comment,
+                tokens.altitude,
+                tokens.telemetry.as_ref(),
+                &[],
+                &tokens.offsets,
+            )
+            .unwrap();
+            assert_eq!(buf, original);
+        }
+    }
+
+    #[test]
+    fn default_offsets() {
+        let mut comment = b"/A=001000Hello".to_vec();
+        let tokens = CommentTokens::extract(&mut comment, None);
+        assert_eq!(tokens.offsets, CommentOffsets::default());
+        assert_eq!(comment, b"Hello");
+
+        let mut comment = b"Hello/A=001000 world".to_vec();
+        let tokens = CommentTokens::extract(&mut comment, None);
+        assert_eq!(tokens.offsets.altitude, Some(6));
+
+        // the offset is clamped if the comment got shorter
+        let mut buf = vec![];
+        encode(&mut buf, b"Hi", Some(1000), None, &[], &tokens.offsets).unwrap();
+        assert_eq!(buf, b"/A=001000Hi");
+
+        let mut comment = b"Hello/A=001000".to_vec();
+        let tokens = CommentTokens::extract(&mut comment, None);
+        assert_eq!(tokens.offsets.altitude, Some(0));
+
+        // the altitude stays at the end of a new comment
+        let mut buf = vec![];
+        encode(&mut buf, b"Changed", Some(1000), None, &[], &tokens.offsets).unwrap();
+        assert_eq!(buf, b"Changed/A=001000");
+    }
+}
@@ -0,0 +1,206 @@
+use std::io::Write;
+
+use crate::{base91, EncodeError};
+
+/// Base91 encoded telemetry, which can be appended to the comment of
+/// position reports, Mic-E reports, objects and items.
+///
+/// It is enclosed in `|` characters and consists of a sequence number,
+/// one to five analog values and optionally the eight digital values,
+/// each encoded as two base91 digits: `|ss1122334455dd|`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CommentTelemetry {
+    pub sequence: u16,
+    /// Up to five analog values ranging from 0 to 8280
+    pub analog: Vec<u16>,
+    /// The eight digital values, B1 first.
+    /// Can only be present if all five analog values are.
+    pub digital: Option<[bool; 8]>,
+}
+
+const MAX_VALUE: u16 = 91 * 91 - 1;
+
+impl CommentTelemetry {
+    /// Looks for telemetry at the end of `comment`.
+    /// If there is any, it is removed from the comment.
+    pub(crate) fn extract(comment: &mut Vec<u8>) -> Option<Self> {
+        let (last, rest) = comment.split_last()?;
+        if *last != b'|' {
+            return None;
+        }
+
+        let start = rest.iter().rposition(|c| *c == b'|')?;
+        let telemetry = Self::decode(&rest[(start + 1)..])?;
+
+        comment.truncate(start);
+
+        Some(telemetry)
+    }
+
+    fn decode(b: &[u8]) -> Option<Self> {
+        if b.len() % 2 != 0 || b.len() < 4 || b.len() > 14 {
+            return None;
+        }
+
+        let mut values = b.chunks(2).map(decode_value).collect::<Option<Vec<_>>>()?;
+
+        let sequence = values.remove(0);
+        let digital = if values.len() == 6 {
+            let bits = values.pop()?;
+            if bits > 255 {
+                return None;
+            }
+
+            let mut digital = [false; 8];
+            for (i, bit) in digital.iter_mut().enumerate() {
+                *bit = bits & (1 << i) != 0;
+            }
+            Some(digital)
+        } else {
+            None
+        };
+
+        Some(Self {
+            sequence,
+            analog: values,
+            digital,
+        })
+    }
+
+    pub(crate) fn encode<W: Write>(&self, buf: &mut W) -> Result<(), EncodeError> {
+        let valid_len = match self.digital {
+            Some(_) => self.analog.len() == 5,
+            None => (1..=5).contains(&self.analog.len()),
+        };
+        let valid_values = self
+            .analog
+            .iter()
+            .chain(std::iter::once(&self.sequence))
+            .all(|v| *v <= MAX_VALUE);
+
+        if !valid_len || !valid_values {
+            return Err(EncodeError::InvalidCommentTelemetry);
+        }
+
+        buf.write_all(b"|")?;
+        base91::encode_ascii(self.sequence.into(), buf, 2)?;
+        for value in &self.analog {
+            base91::encode_ascii((*value).into(), buf, 2)?;
+        }
+        if let Some(digital) = &self.digital {
+            let bits = digital
+                .iter()
+                .enumerate()
+                .fold(0u16, |acc, (i, bit)| acc | (u16::from(*bit) << i));
+            base91::encode_ascii(bits.into(), buf, 2)?;
+        }
+        buf.write_all(b"|")?;
+
+        Ok(())
+    }
+}
+
+fn decode_value(b: &[u8]) -> Option<u16> {
+    // only '!' to '{' are valid base91 digits
+    if !b.iter().all(|c| (b'!'..=b'{').contains(c)) {
+        return None;
+    }
+
+    base91::decode_ascii(b).map(|v| v as u16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extract_telemetry() {
+        let mut comment = b"Hello|ss1122334455#a|".to_vec();
+
+        assert_eq!(
+            CommentTelemetry::extract(&mut comment),
+            Some(CommentTelemetry {
+                sequence: 7544,
+                analog: vec![1472, 1564, 1656, 1748, 1840],
+                digital: Some([false, true, true, false, true, true, true, true]),
+            })
+        );
+        assert_eq!(comment, b"Hello");
+    }
+
+    #[test]
+    fn extract_short_telemetry() {
+        let mut comment = b"|!\"&7|".to_vec();
+
+        assert_eq!(
+            CommentTelemetry::extract(&mut comment),
+            Some(CommentTelemetry {
+                sequence: 1,
+                analog: vec![477],
+                digital: None,
+            })
+        );
+        assert!(comment.is_empty());
+    }
+
+    #[test]
+    fn extract_invalid_telemetry() {
+        let originals = vec![
+            &b"Hello"[..],
+            b"Hello|",
+            b"|!!|",
+            b"|!!!|",
+            b"|!!!!!!!!!!!!!!!!|",
+            b"|!! !|",
+            b"Hello|ss1122334455#a| world",
+        ];
+
+        for o in originals {
+            let mut comment = o.to_vec();
+            assert_eq!(CommentTelemetry::extract(&mut comment), None);
+            assert_eq!(comment, o);
+        }
+    }
+
+    #[test]
+    fn encode_roundtrip() {
+        let originals = vec![&b"|ss1122334455#a|"[..], b"|!\"&7|", b"|{{!!!!|"];
+
+        for o in originals {
+            let mut comment = o.to_vec();
+            let telemetry = CommentTelemetry::extract(&mut comment).unwrap();
+
+            let mut buf = vec![];
+            telemetry.encode(&mut buf).unwrap();
+            assert_eq!(o, buf);
+        }
+    }
+
+    #[test]
+    fn encode_invalid() {
+        let invalids = vec![
+            CommentTelemetry {
+                sequence: 1,
+                analog: vec![],
+                digital: None,
+            },
+            CommentTelemetry {
+                sequence: 1,
+                analog: vec![1, 2, 3],
+                digital: Some([true; 8]),
+            },
+            CommentTelemetry {
+                sequence: 1,
+                analog: vec![8281],
+                digital: None,
+            },
+        ];
+
+        for t in invalids {
+            assert!(matches!(
+                t.encode(&mut vec![]),
+                Err(EncodeError::InvalidCommentTelemetry)
+            ));
+        }
+    }
+}
@@ -0,0 +1,292 @@
+//! The `!DAO!` token specifies the datum of a position and can add extra
+//! precision to it. It is usually placed at the end of the comment or
+//! separated from the rest of it by a space. A token directly after a letter
+//! is part of a word and not a DAO.
+//!
+//! - `!Wxy!`: human readable, `x` and `y` are the third decimal of the
+//!   latitude and longitude minutes
+//! - `!wxy!`: base91, `x` and `y` add 1/91 of a hundredth of a minute
+//! - `!W  !`: only specifies the datum
+//!
+//! `W` (WGS84) is the only datum in use.
+
+use std::io::Write;
+
+use crate::{base91, EncodeError};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DaoPrecision {
+    /// The token only specifies the datum
+    DatumOnly,
+    /// Adds a third decimal to the minutes
+    ThousandthMinute,
+    /// Adds 1/91 of a hundredth of a minute
+    Base91,
+}
+
+impl DaoPrecision {
+    // the number of steps a hundredth of a minute is divided into
+    fn steps(&self) -> Option<u32> {
+        match self {
+            DaoPrecision::DatumOnly => None,
+            DaoPrecision::ThousandthMinute => Some(10),
+            DaoPrecision::Base91 => Some(91),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Dao {
+    /// The datum, `W` for WGS84. Always uppercase.
+    pub datum: char,
+    pub precision: DaoPrecision,
+    /// The latitude and longitude steps of a DAO that wasn't applied to the
+    /// coordinates, e.g. of Mic-E reports. They are encoded as they are.
+    /// `None` if the steps are part of the coordinates.
+    pub steps: Option<(u32, u32)>,
+}
+
+impl Dao {
+    /// Looks for the last `!DAO!` token in `comment` that ends the comment
+    /// or is followed by a space or telemetry, and doesn't follow a letter.
+    /// If there is one, it is removed from the comment.
+    /// Also returns the latitude and longitude offsets in degrees,
+    /// which increase the absolute values of the coordinates,
+    /// and the number of bytes that followed the token.
+    pub(crate) fn extract(comment: &mut Vec<u8>) -> Option<(Self, f64, f64, usize)> {
+        let (index, (dao, lat_offset, lon_offset)) = comment
+            .windows(5)
+            .enumerate()
+            .rev()
+            .filter(|(i, _)| matches!(comment.get(i + 5), None | Some(b' ') | Some(b'|')))
+            .filter(|(i, _)| *i == 0 || !comment[i - 1].is_ascii_alphabetic())
+            .find_map(|(i, w)| Self::decode(w).map(|d| (i, d)))?;
+
+        comment.drain(index..(index + 5));
+
+        Some((dao, lat_offset, lon_offset, comment.len() - index))
+    }
+
+    fn decode(b: &[u8]) -> Option<(Self, f64, f64)> {
+        if b.len() != 5 || b[0] != b'!' || b[4] != b'!' || !b"Ww".contains(&b[1]) {
+            return None;
+        }
+
+        let datum = b[1].to_ascii_uppercase() as char;
+        let (precision, lat, lon) = if &b[2..4] == b"  " {
+            (DaoPrecision::DatumOnly, 0, 0)
+        } else if b[1].is_ascii_uppercase() {
+            let lat = (b[2] as char).to_digit(10)?;
+            let lon = (b[3] as char).to_digit(10)?;
+            (DaoPrecision::ThousandthMinute, lat, lon)
+        } else {
+            if !b[2..4].iter().all(|c| (b'!'..=b'{').contains(c)) {
+                return None;
+            }
+            let lat = base91::digit_from_ascii(b[2])?.into();
+            let lon = base91::digit_from_ascii(b[3])?.into();
+            (DaoPrecision::Base91, lat, lon)
+        };
+
+        let offset = |steps| match precision.steps() {
+            Some(n) => f64::from(steps) / f64::from(n) / 6000.0,
+            None => 0.0,
+        };
+
+        let steps = match precision {
+            DaoPrecision::DatumOnly => None,
+            _ => Some((lat, lon)),
+        };
+
+        Some((
+            Self {
+                datum,
+                precision,
+                steps,
+            },
+            offset(lat),
+            offset(lon),
+        ))
+    }
+
+    /// Splits `value` into a value with a precision of a hundredth of a minute
+    /// and the number of DAO steps that need to be added to it.
+    pub(crate) fn split(&self, value: f64) -> (f64, u32) {
+        let steps = match self.precision.steps() {
+            Some(n) => n,
+            None => return (value, 0),
+        };
+
+        let total = (value.abs() * 6000.0 * f64::from(steps)).round() as u64;
+        let base = (total / u64::from(steps)) as f64 / 6000.0;
+        let rest = (total % u64::from(steps)) as u32;
+
+        (base.copysign(value), rest)
+    }
+
+    /// Encodes the token with the steps `lat` and `lon`,
+    /// unless the DAO has `steps` of its own
+    pub(crate) fn encode<W: Write>(
+        &self,
+        buf: &mut W,
+        lat: u32,
+        lon: u32,
+    ) -> Result<(), EncodeError> {
+        if !self.datum.eq_ignore_ascii_case(&'W') {
+            return Err(EncodeError::InvalidDatum(self.datum));
+        }
+
+        let (lat, lon) = self.steps.unwrap_or((lat, lon));
+
+        let datum = self.datum.to_ascii_uppercase();
+        match self.precision {
+            DaoPrecision::DatumOnly => write!(buf, "!{}  !", datum)?,
+            DaoPrecision::ThousandthMinute => write!(buf, "!{}{}{}!", datum, lat, lon)?,
+            DaoPrecision::Base91 => {
+                write!(buf, "!{}", datum.to_ascii_lowercase())?;
+                buf.write_all(&[
+                    base91::digit_to_ascii(lat as u8),
+                    base91::digit_to_ascii(lon as u8),
+                ])?;
+                write!(buf, "!")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extract_human_readable() {
+        let mut comment = b"/A=003054 !W09! id213D17F2".to_vec();
+
+        let (dao, lat, lon, after) = Dao::extract(&mut comment).unwrap();
+        assert_eq!(
+            dao,
+            Dao {
+                datum: 'W',
+                precision: DaoPrecision::ThousandthMinute,
+                steps: Some((0, 9)),
+            }
+        );
+        assert_relative_eq!(lat, 0.0);
+        assert_relative_eq!(lon, 0.009 / 60.0);
+        assert_eq!(comment, b"/A=003054  id213D17F2");
+        assert_eq!(after, 11);
+    }
+
+    #[test]
+    fn extract_base91() {
+        let mut comment = b"Hello !wTD!".to_vec();
+
+        let (dao, lat, lon, after) = Dao::extract(&mut comment).unwrap();
+        assert_eq!(
+            dao,
+            Dao {
+                datum: 'W',
+                precision: DaoPrecision::Base91,
+                steps: Some((51, 35)),
+            }
+        );
+        assert_relative_eq!(lat, 51.0 / 91.0 / 6000.0);
+        assert_relative_eq!(lon, 35.0 / 91.0 / 6000.0);
+        assert_eq!(comment, b"Hello ");
+        assert_eq!(after, 0);
+    }
+
+    #[test]
+    fn extract_datum_only() {
+        let mut comment = b"!W  !".to_vec();
+
+        let (dao, lat, lon, _) = Dao::extract(&mut comment).unwrap();
+        assert_eq!(dao.precision, DaoPrecision::DatumOnly);
+        assert_relative_eq!(lat, 0.0);
+        assert_relative_eq!(lon, 0.0);
+        assert!(comment.is_empty());
+    }
+
+    #[test]
+    fn extract_last_token() {
+        let mut comment = b"!W12! !W34!".to_vec();
+
+        let (_, lat, _, _) = Dao::extract(&mut comment).unwrap();
+        assert_relative_eq!(lat, 0.003 / 60.0);
+        assert_eq!(comment, b"!W12! ");
+    }
+
+    #[test]
+    fn extract_invalid() {
+        for o in [
+            &b"Hello!"[..],
+            b"!!!!!",
+            b"!W1a!",
+            b"!1W2!",
+            b"!w1 !",
+            b"!W 1!",
+            // only WGS84 is in use
+            b"!A12!",
+            b"!a12!",
+            // in the middle of a word
+            b"wow!w12!there",
+            b"wow!w12!",
+        ] {
+            let mut comment = o.to_vec();
+            assert_eq!(Dao::extract(&mut comment), None);
+            assert_eq!(comment, o);
+        }
+    }
+
+    #[test]
+    fn split_and_encode() {
+        let dao = Dao {
+            datum: 'W',
+            precision: DaoPrecision::ThousandthMinute,
+            steps: None,
+        };
+        let (base, steps) = dao.split(-(12.0 + 24.499 / 60.0));
+        assert_relative_eq!(base, -(12.0 + 24.49 / 60.0));
+        assert_eq!(steps, 9);
+
+        let mut buf = vec![];
+        dao.encode(&mut buf, 0, steps).unwrap();
+        assert_eq!(buf, b"!W09!");
+
+        let dao = Dao {
+            datum: 'w',
+            precision: DaoPrecision::Base91,
+            steps: None,
+        };
+        let mut buf = vec![];
+        dao.encode(&mut buf, 51, 35).unwrap();
+        assert_eq!(buf, b"!wTD!");
+
+        // steps that weren't applied are kept
+        let dao = Dao {
+            steps: Some((1, 2)),
+            ..dao
+        };
+        let mut buf = vec![];
+        dao.encode(&mut buf, 51, 35).unwrap();
+        assert_eq!(buf, b"!w\"#!");
+    }
+
+    #[test]
+    fn encode_invalid_datum() {
+        for datum in ['1', 'A'] {
+            let dao = Dao {
+                datum,
+                precision: DaoPrecision::DatumOnly,
+                steps: None,
+            };
+
+            assert!(matches!(
+                dao.encode(&mut vec![], 0, 0),
+                Err(EncodeError::InvalidDatum(d)) if d == datum
+            ));
+        }
+    }
+}
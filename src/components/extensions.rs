@@ -71,7 +71,7 @@ impl Extension {
                 direction_degrees,
                 speed_knots,
             } => {
-                write!(buf, "{:3}/{:3}", direction_degrees, speed_knots)?;
+                write!(buf, "{:03}/{:03}", direction_degrees, speed_knots)?;
             }
             Extension::PowerHeightGainDirectivity {
                 power_watts,
@@ -101,7 +101,7 @@ impl Extension {
                 )?;
             }
             Extension::RadioRange { radio_range_miles } => {
-                write!(buf, "RNG{:4}", radio_range_miles)?;
+                write!(buf, "RNG{:04}", radio_range_miles)?;
             }
             Extension::DFStrengthHeightGainDirectivity {
                 s_points,
@@ -135,7 +135,7 @@ impl Extension {
                 object_type: r#type,
                 color,
             } => {
-                write!(buf, "T{:2}/C{:2}", r#type, color)?;
+                write!(buf, "T{:02}/C{:02}", r#type, color)?;
             }
         }
 
@@ -256,7 +256,7 @@ impl Extension {
                         direction_degrees: parse_bytes(&bytes[..3]).ok_or_else(|| {
                             DecodeError::InvalidExtensionDirectionSpeed(b.to_vec())
                         })?,
-                        speed_knots: parse_bytes(&bytes[5..]).ok_or_else(|| {
+                        speed_knots: parse_bytes(&bytes[4..]).ok_or_else(|| {
                             DecodeError::InvalidExtensionDirectionSpeed(b.to_vec())
                         })?,
                     })
@@ -288,6 +288,17 @@ mod test {
         ));
     }
 
+    #[test]
+    fn test_encode_course_speed() {
+        for original in [&b"322/103"[..], b"080/043", b"RNG0050", b"T01/C05"] {
+            let ext = Extension::decode(original).unwrap();
+
+            let mut buf = vec![];
+            ext.encode(&mut buf).unwrap();
+            assert_eq!(original, &buf[..]);
+        }
+    }
+
     #[test]
     fn test_parse_phg() {
         let cse_speed = b"PHG5132";
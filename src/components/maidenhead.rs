@@ -0,0 +1,277 @@
+//! A Maidenhead locator identifies a box on the map, e.g. `IO91SX`.
+//! Each pair of characters subdivides the box of the previous pair:
+//! - fields: 20° of longitude by 10° of latitude, `A` to `R`
+//! - squares: 2° by 1°, `0` to `9`
+//! - subsquares: 5' by 2.5', `A` to `X`
+//! - extended squares: 30" by 15", `0` to `9`
+
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::io::Write;
+use std::ops::RangeInclusive;
+
+use AprsCst;
+use EncodeError;
+use Latitude;
+use Longitude;
+use Position;
+use Precision;
+
+// (number of divisions, first character) of each pair
+const PAIRS: [(u8, u8); 4] = [(18, b'A'), (10, b'0'), (24, b'A'), (10, b'0')];
+
+/// Locators are compared and hashed ignoring case, `io91sx` equals `IO91SX`.
+/// They are encoded with the case they were created with.
+#[derive(Clone, Debug)]
+pub struct MaidenheadLocator(Vec<u8>);
+
+impl MaidenheadLocator {
+    /// Creates a locator from 4, 6 or 8 characters. Letters may be lowercase.
+    /// Returns `None` if the locator is invalid.
+    pub fn new(locator: &str) -> Option<Self> {
+        Self::decode(locator.as_bytes())
+    }
+
+    /// Returns the locator of length `len` (4, 6 or 8) that contains the given coordinates.
+    pub fn from_lat_lon(latitude: Latitude, longitude: Longitude, len: usize) -> Option<Self> {
+        if !matches!(len, 4 | 6 | 8) {
+            return None;
+        }
+
+        // scale to fractions of the whole globe
+        let mut lon = ((*longitude + 180.0) / 360.0).clamp(0.0, 1.0 - f64::EPSILON);
+        let mut lat = ((*latitude + 90.0) / 180.0).clamp(0.0, 1.0 - f64::EPSILON);
+
+        let mut locator = Vec::with_capacity(len);
+        for (divisions, first) in PAIRS.iter().take(len / 2) {
+            let divisions = f64::from(*divisions);
+            let lon_index = (lon * divisions).floor();
+            let lat_index = (lat * divisions).floor();
+            locator.push(first + lon_index as u8);
+            locator.push(first + lat_index as u8);
+
+            lon = lon * divisions - lon_index;
+            lat = lat * divisions - lat_index;
+        }
+
+        Some(Self(locator))
+    }
+
+    /// Returns the locator of length `len` (4, 6 or 8) that contains the position.
+    pub fn from_position(position: &Position, len: usize) -> Option<Self> {
+        Self::from_lat_lon(position.latitude, position.longitude, len)
+    }
+
+    /// Converts the locator into a position at the center of its box.
+    /// The precision is the finest one that covers the height of the box.
+    pub fn to_position(&self, symbol_table: char, symbol_code: char) -> Position {
+        let precision = match self.0.len() {
+            4 => Precision::OneDegree,
+            6 => Precision::TenMinute,
+            _ => Precision::OneMinute,
+        };
+
+        Position {
+            latitude: self.latitude(),
+            longitude: self.longitude(),
+            precision,
+            symbol_table,
+            symbol_code,
+            cst: AprsCst::Uncompressed,
+            dao: None,
+        }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// The latitude of the center of the box.
+    pub fn latitude(&self) -> Latitude {
+        let (south, height) = self.south_and_height();
+        Latitude::new(south + height / 2.0).unwrap_or_default()
+    }
+
+    /// The longitude of the center of the box.
+    pub fn longitude(&self) -> Longitude {
+        let (west, width) = self.west_and_width();
+        Longitude::new(west + width / 2.0).unwrap_or_default()
+    }
+
+    /// Returns the range of latitudes the locator covers.
+    pub fn latitude_bounding(&self) -> RangeInclusive<f64> {
+        let (south, height) = self.south_and_height();
+        south..=(south + height)
+    }
+
+    /// Returns the range of longitudes the locator covers.
+    pub fn longitude_bounding(&self) -> RangeInclusive<f64> {
+        let (west, width) = self.west_and_width();
+        west..=(west + width)
+    }
+
+    fn south_and_height(&self) -> (f64, f64) {
+        let (start, size) = self.start_and_size(1);
+        (start * 180.0 - 90.0, size * 180.0)
+    }
+
+    fn west_and_width(&self) -> (f64, f64) {
+        let (start, size) = self.start_and_size(0);
+        (start * 360.0 - 180.0, size * 360.0)
+    }
+
+    // Walks the characters at `offset`, `offset + 2`, ...
+    // Returns the start and size of the box as a fraction of the whole range.
+    fn start_and_size(&self, offset: usize) -> (f64, f64) {
+        let mut start = 0.0;
+        let mut size = 1.0;
+
+        for (c, (divisions, first)) in self.0.iter().skip(offset).step_by(2).zip(PAIRS.iter()) {
+            size /= f64::from(*divisions);
+            start += f64::from(c.to_ascii_uppercase() - first) * size;
+        }
+
+        (start, size)
+    }
+
+    pub(crate) fn decode(b: &[u8]) -> Option<Self> {
+        if !matches!(b.len(), 4 | 6 | 8) {
+            return None;
+        }
+
+        let valid = b
+            .chunks(2)
+            .zip(PAIRS.iter())
+            .all(|(pair, (divisions, first))| {
+                pair.iter().all(|c| {
+                    let c = c.to_ascii_uppercase();
+                    c >= *first && c - first < *divisions
+                })
+            });
+
+        if valid {
+            Some(Self(b.to_vec()))
+        } else {
+            None
+        }
+    }
+
+    pub(crate) fn encode<W: Write>(&self, buf: &mut W) -> Result<(), EncodeError> {
+        buf.write_all(&self.0)?;
+
+        Ok(())
+    }
+}
+
+impl PartialEq for MaidenheadLocator {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.eq_ignore_ascii_case(&other.0)
+    }
+}
+
+impl Eq for MaidenheadLocator {}
+
+impl Hash for MaidenheadLocator {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.to_ascii_uppercase().hash(state);
+    }
+}
+
+impl fmt::Display for MaidenheadLocator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // only valid ASCII is accepted
+        write!(f, "{}", String::from_utf8_lossy(&self.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_locators() {
+        assert!(MaidenheadLocator::new("IO91").is_some());
+        assert!(MaidenheadLocator::new("io91sx").is_some());
+        assert!(MaidenheadLocator::new("IO91SX54").is_some());
+
+        for invalid in [
+            "IO9",
+            "IO91S",
+            "SO91",
+            "IOA1",
+            "IO91SY",
+            "IO91SX5A",
+            "IO91SX5400",
+        ] {
+            assert_eq!(MaidenheadLocator::new(invalid), None, "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn ignore_case() {
+        use std::collections::HashSet;
+
+        let lower = MaidenheadLocator::new("io91sx").unwrap();
+        let upper = MaidenheadLocator::new("IO91SX").unwrap();
+        assert_eq!(lower, upper);
+        assert_ne!(lower, MaidenheadLocator::new("IO91SW").unwrap());
+
+        let set: HashSet<_> = vec![lower.clone(), upper].into_iter().collect();
+        assert_eq!(set.len(), 1);
+
+        // the case is kept for encoding
+        assert_eq!(lower.as_bytes(), b"io91sx");
+    }
+
+    #[test]
+    fn locator_bounding() {
+        let locator = MaidenheadLocator::new("IO91sx").unwrap();
+
+        let lat = locator.latitude_bounding();
+        let lon = locator.longitude_bounding();
+        assert_relative_eq!(*lat.start(), 51.0 + 57.5 / 60.0, epsilon = 1e-9);
+        assert_relative_eq!(*lat.end(), 52.0, epsilon = 1e-9);
+        assert_relative_eq!(*lon.start(), -(30.0 / 60.0), epsilon = 1e-9);
+        assert_relative_eq!(*lon.end(), -(25.0 / 60.0), epsilon = 1e-9);
+
+        assert_relative_eq!(*locator.latitude(), 51.0 + 58.75 / 60.0, epsilon = 1e-9);
+        assert_relative_eq!(*locator.longitude(), -(27.5 / 60.0), epsilon = 1e-9);
+    }
+
+    #[test]
+    fn from_lat_lon() {
+        let latitude = Latitude::new(51.9792).unwrap();
+        let longitude = Longitude::new(-0.4554).unwrap();
+
+        for (len, expected) in [(4, "IO91"), (6, "IO91SX"), (8, "IO91SX55")] {
+            assert_eq!(
+                MaidenheadLocator::from_lat_lon(latitude, longitude, len),
+                MaidenheadLocator::new(expected)
+            );
+        }
+        assert_eq!(
+            MaidenheadLocator::from_lat_lon(latitude, longitude, 5),
+            None
+        );
+
+        // the edges of the map belong to the last box
+        let corner = MaidenheadLocator::from_lat_lon(
+            Latitude::new(90.0).unwrap(),
+            Longitude::new(180.0).unwrap(),
+            4,
+        );
+        assert_eq!(corner, MaidenheadLocator::new("RR99"));
+    }
+
+    #[test]
+    fn position_roundtrip() {
+        let locator = MaidenheadLocator::new("JN58td").unwrap();
+        let position = locator.to_position('/', '-');
+
+        assert_eq!(position.precision, Precision::TenMinute);
+        assert_eq!(
+            MaidenheadLocator::from_position(&position, 6),
+            MaidenheadLocator::new("JN58TD")
+        );
+    }
+}
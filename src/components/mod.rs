@@ -1,4 +1,10 @@
+pub mod altitude;
+pub mod comment;
+pub mod comment_telemetry;
+pub mod dao;
 pub mod extensions;
 pub mod lonlat;
+pub mod maidenhead;
 pub mod position;
 pub mod timestamp;
+pub mod weather;
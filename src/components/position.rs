@@ -3,9 +3,14 @@ use std::{
     ops::RangeInclusive,
 };
 
+use crate::diagnostic::{SpanResult, SpannedError};
 use crate::{AprsCompressedCs, AprsCompressionType, DecodeError, EncodeError};
 
+use super::dao::Dao;
 use super::lonlat::{Latitude, Longitude, Precision};
+use super::weather::{Weather, WeatherFormat};
+
+const MPH_PER_KNOT: f64 = 1.150_779;
 
 #[derive(PartialEq, Debug, Clone)]
 pub enum AprsCst {
@@ -25,6 +30,11 @@ pub struct Position {
     pub symbol_table: char,
     pub symbol_code: char,
     pub cst: AprsCst,
+    /// The datum and extra precision from a `!DAO!` token in the comment.
+    /// It is applied to uncompressed coordinates, compressed positions keep
+    /// its steps. When encoding, the token is only emitted for positions
+    /// with a precision of `Precision::HundredthMinute`.
+    pub dao: Option<Dao>,
 }
 
 impl Position {
@@ -38,10 +48,121 @@ impl Position {
         self.precision.range(self.longitude.value())
     }
 
+    /// Encodes the position, compressed or uncompressed depending on `cst`
+    pub(crate) fn encode<W: Write>(&self, buf: &mut W) -> Result<(), EncodeError> {
+        if matches!(self.cst, AprsCst::Uncompressed) {
+            self.encode_uncompressed(buf)
+        } else {
+            self.encode_compressed(buf)
+        }
+    }
+
+    /// Weather stations (symbol code `_`) append their weather data to the position.
+    /// Returns the weather data and the remaining bytes if `b` contains any.
+    /// Compressed positions have the wind direction and speed in place of
+    /// the course and speed.
+    pub(crate) fn decode_weather<'a>(&self, b: &'a [u8]) -> Option<(Weather, &'a [u8])> {
+        if self.symbol_code != '_' {
+            return None;
+        }
+
+        let (mut weather, rest) = Weather::decode(b, self.weather_format())?;
+        if let AprsCst::CompressedSome {
+            cs: AprsCompressedCs::CourseSpeed(cs),
+            ..
+        } = &self.cst
+        {
+            weather.wind_direction = Some(cs.course_degrees());
+            weather.wind_speed = Some((cs.speed_knots() * MPH_PER_KNOT).round() as u16);
+        }
+
+        Some((weather, rest))
+    }
+
+    pub(crate) fn weather_format(&self) -> WeatherFormat {
+        if matches!(self.cst, AprsCst::Uncompressed) {
+            WeatherFormat::Uncompressed
+        } else {
+            WeatherFormat::Compressed
+        }
+    }
+
+    /// Removes the last `!DAO!` token from `comment` and applies it.
+    /// Compressed positions are already more precise than the DAO,
+    /// so it isn't applied to them and keeps its steps instead.
+    /// Returns the number of bytes that followed the token.
+    pub(crate) fn extract_dao(&mut self, comment: &mut Vec<u8>) -> Option<usize> {
+        if self.precision != Precision::HundredthMinute {
+            return None;
+        }
+
+        let (mut dao, lat_offset, lon_offset, after) = Dao::extract(comment)?;
+
+        if matches!(self.cst, AprsCst::Uncompressed) {
+            // the steps are computed from the coordinates when encoding
+            dao.steps = None;
+
+            let lat = self.latitude.value();
+            let lon = self.longitude.value();
+
+            if let (Some(lat), Some(lon)) = (
+                Latitude::new(lat + lat_offset.copysign(lat)),
+                Longitude::new(lon + lon_offset.copysign(lon)),
+            ) {
+                self.latitude = lat;
+                self.longitude = lon;
+            }
+        }
+
+        self.dao = Some(dao);
+
+        Some(after)
+    }
+
+    // The DAO is only encoded for positions with full precision
+    fn encoded_dao(&self) -> Option<&Dao> {
+        self.dao
+            .as_ref()
+            .filter(|_| self.precision == Precision::HundredthMinute)
+    }
+
+    /// Encodes the `!DAO!` token, if there is one
+    pub(crate) fn encode_dao<W: Write>(&self, buf: &mut W) -> Result<(), EncodeError> {
+        if let Some(dao) = self.encoded_dao() {
+            let (_, lat) = dao.split(self.latitude.value());
+            let (_, lon) = dao.split(self.longitude.value());
+            dao.encode(buf, lat, lon)?;
+        }
+
+        Ok(())
+    }
+
+    /// The encoded `!DAO!` token, empty if there is none
+    pub(crate) fn dao_token(&self) -> Result<Vec<u8>, EncodeError> {
+        let mut token = vec![];
+        self.encode_dao(&mut token)?;
+
+        Ok(token)
+    }
+
     pub(crate) fn encode_uncompressed<W: Write>(&self, buf: &mut W) -> Result<(), EncodeError> {
-        self.latitude.encode_uncompressed(buf, self.precision)?;
+        // the DAO contains the digits after the hundredths of a minute,
+        // so they must not be rounded into the position
+        let (latitude, longitude) = match self.encoded_dao() {
+            Some(dao) if dao.steps.is_none() => {
+                let (lat, _) = dao.split(self.latitude.value());
+                let (lon, _) = dao.split(self.longitude.value());
+                (
+                    Latitude::new(lat).ok_or(EncodeError::InvalidLatitude(lat))?,
+                    Longitude::new(lon).ok_or(EncodeError::InvalidLongitude(lon))?,
+                )
+            }
+            _ => (self.latitude, self.longitude),
+        };
+
+        latitude.encode_uncompressed(buf, self.precision)?;
         write!(buf, "{}", self.symbol_table)?;
-        self.longitude.encode_uncompressed(buf)?;
+        longitude.encode_uncompressed(buf)?;
         write!(buf, "{}", self.symbol_code)?;
         Ok(())
     }
@@ -67,18 +188,24 @@ impl Position {
     ///
     /// all position representations interleave the symbol table and code
     /// so we stuff it all in here
-    pub(crate) fn decode(b: &[u8]) -> Result<(Option<&[u8]>, Self), DecodeError> {
+    pub(crate) fn decode(b: &[u8]) -> Result<(Option<&[u8]>, Self), SpannedError> {
         // make sure we're not empty
         if b.is_empty() {
-            return Err(DecodeError::InvalidPosition(b.to_vec()));
+            return Err(SpannedError::new(
+                DecodeError::InvalidPosition(vec![]),
+                0..0,
+            ));
         }
         let is_uncompressed_position = (*b.first().unwrap_or(&0) as char).is_numeric();
         if is_uncompressed_position {
             if b.len() < 19 {
-                return Err(DecodeError::InvalidPosition(b.to_vec()));
+                return Err(SpannedError::new(
+                    DecodeError::InvalidPosition(b.to_vec()),
+                    0..b.len(),
+                ));
             }
-            let (latitude, precision) = Latitude::parse_uncompressed(&b[0..8])?;
-            let longitude = Longitude::parse_uncompressed(&b[9..18], precision)?;
+            let (latitude, precision) = Latitude::parse_uncompressed(&b[0..8]).span(0..8)?;
+            let longitude = Longitude::parse_uncompressed(&b[9..18], precision).span(9..18)?;
 
             let symbol_table = b[8] as char;
             let symbol_code = b[18] as char;
@@ -92,11 +219,15 @@ impl Position {
                     symbol_code,
                     symbol_table,
                     cst: AprsCst::Uncompressed,
+                    dao: None,
                 },
             ))
         } else {
             if b.len() < 13 {
-                return Err(DecodeError::InvalidPosition(b.to_vec()));
+                return Err(SpannedError::new(
+                    DecodeError::InvalidPosition(b.to_vec()),
+                    0..b.len(),
+                ));
             }
             let symbol_table = b[0] as char;
             let comp_lat = &b[1..5];
@@ -107,8 +238,8 @@ impl Position {
 
             b.take(12);
 
-            let latitude = Latitude::parse_compressed(comp_lat)?;
-            let longitude = Longitude::parse_compressed(comp_lon)?;
+            let latitude = Latitude::parse_compressed(comp_lat).span(1..5)?;
+            let longitude = Longitude::parse_compressed(comp_lon).span(5..9)?;
 
             // From the APRS spec - if the c value is a space,
             // the csT doesn't matter
@@ -117,9 +248,11 @@ impl Position {
                 _ => {
                     let t = comp_type
                         .checked_sub(33)
-                        .ok_or_else(|| DecodeError::InvalidPosition(b.to_owned()))?
+                        .ok_or_else(|| DecodeError::InvalidPosition(b.to_owned()))
+                        .span(12..13)?
                         .into();
-                    let cs = AprsCompressedCs::parse(course_speed[0], course_speed[1], t)?;
+                    let cs = AprsCompressedCs::parse(course_speed[0], course_speed[1], t)
+                        .span(10..12)?;
                     AprsCst::CompressedSome { cs, t }
                 }
             };
@@ -132,6 +265,7 @@ impl Position {
                     symbol_code,
                     symbol_table,
                     cst,
+                    dao: None,
                 },
             ))
         }
@@ -40,6 +40,9 @@ pub enum Timestamp {
     DDHHMM(u8, u8, u8),
     /// Hour, Minute and Second in UTC
     HHMMSS(u8, u8, u8),
+    /// Month, Day of month, Hour and Minute in UTC.
+    /// Only used in positionless weather reports.
+    MDHM(u8, u8, u8, u8),
     /// Unsupported timestamp format
     Unsupported(Vec<u8>),
 }
@@ -63,10 +66,35 @@ impl Timestamp {
         }
     }
 
+    /// Month, Day of month, Hour and Minute in UTC
+    pub fn new_mdhm(mo: u8, d: u8, h: u8, m: u8) -> Option<Self> {
+        if mo <= 99 && d <= 99 && h <= 99 && m <= 99 {
+            Some(Self::MDHM(mo, d, h, m))
+        } else {
+            None
+        }
+    }
+
+    /// Decodes the 8 digit MDHM timestamp used in positionless weather reports
+    pub(crate) fn decode_mdhm(b: &[u8]) -> Result<Self, DecodeError> {
+        if b.len() != 8 {
+            return Err(DecodeError::InvalidTimestamp(b.to_owned()));
+        }
+
+        let mut values = [0; 4];
+        for (i, v) in values.iter_mut().enumerate() {
+            *v = parse_bytes(&b[(i * 2)..(i * 2 + 2)])
+                .ok_or_else(|| DecodeError::InvalidTimestamp(b.to_owned()))?;
+        }
+
+        Ok(Timestamp::MDHM(values[0], values[1], values[2], values[3]))
+    }
+
     pub fn encode<W: Write>(&self, buf: &mut W) -> Result<(), EncodeError> {
         match self {
             Self::DDHHMM(d, h, m) => write!(buf, "{:02}{:02}{:02}z", d, h, m)?,
             Self::HHMMSS(h, m, s) => write!(buf, "{:02}{:02}{:02}h", h, m, s)?,
+            Self::MDHM(mo, d, h, m) => write!(buf, "{:02}{:02}{:02}{:02}", mo, d, h, m)?,
             Self::Unsupported(s) => buf.write_all(s)?,
         };
 
@@ -175,6 +203,26 @@ mod tests {
         assert_eq!(b"654321h"[..], buf);
     }
 
+    #[test]
+    fn parse_mdhm() {
+        assert_eq!(
+            Timestamp::decode_mdhm(&b"10090556"[..]),
+            Ok(Timestamp::MDHM(10, 9, 5, 56))
+        );
+
+        assert_eq!(
+            Timestamp::decode_mdhm(&b"1009055z"[..]),
+            Err(DecodeError::InvalidTimestamp(b"1009055z".to_vec()))
+        );
+    }
+
+    #[test]
+    fn encode_mdhm() {
+        let mut buf = vec![];
+        Timestamp::MDHM(10, 9, 5, 56).encode(&mut buf).unwrap();
+        assert_eq!(b"10090556"[..], buf);
+    }
+
     #[test]
     fn encode_local_time() {
         let mut buf = vec![];
@@ -0,0 +1,410 @@
+use std::io::Write;
+
+use crate::{bytes::parse_bytes, EncodeError};
+
+/// Weather data, as found in positionless weather reports and after the `_`
+/// symbol in position, object and item reports.
+///
+/// Every field is optional. Values that are reported as missing (`...` or
+/// spaces) are decoded as `None`. The wind, gust and temperature fields are
+/// mandatory in the APRS spec, so they are always encoded (as `...` if `None`).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Weather {
+    /// Wind direction in degrees.
+    /// Compressed positions store it in their course/speed bytes,
+    /// so it isn't encoded from here for them.
+    pub wind_direction: Option<u16>,
+    /// Sustained one-minute wind speed in mph.
+    /// Like the direction, it is part of the course/speed of compressed positions.
+    pub wind_speed: Option<u16>,
+    /// Peak wind speed in the last 5 minutes in mph
+    pub wind_gust: Option<u16>,
+    /// Temperature in degrees Fahrenheit
+    pub temperature: Option<i16>,
+    /// Rainfall in the last hour in hundredths of an inch
+    pub rain_last_hour: Option<u16>,
+    /// Rainfall in the last 24 hours in hundredths of an inch
+    pub rain_last_24_hours: Option<u16>,
+    /// Rainfall since midnight in hundredths of an inch
+    pub rain_since_midnight: Option<u16>,
+    /// Relative humidity in percent
+    pub humidity: Option<u8>,
+    /// Barometric pressure in tenths of millibars
+    pub barometric_pressure: Option<u32>,
+    /// Luminosity in watts per square meter
+    pub luminosity: Option<u16>,
+    /// Snowfall in the last 24 hours in inches
+    pub snow: Option<u16>,
+    /// Raw rain counter
+    pub raw_rain_counter: Option<u16>,
+}
+
+/// Where the weather data is located, which determines how wind
+/// direction and speed are represented.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum WeatherFormat {
+    /// `cDDDsSSS` after the timestamp of a positionless weather report
+    Positionless,
+    /// `DDD/SSS` in place of the course/speed extension
+    Uncompressed,
+    /// Wind direction and speed are stored in the compressed `cs` bytes
+    Compressed,
+}
+
+impl Weather {
+    /// Decodes weather data from the start of `b`.
+    /// Returns the weather data and the remaining (comment) bytes.
+    pub(crate) fn decode(b: &[u8], format: WeatherFormat) -> Option<(Self, &[u8])> {
+        let mut weather = Self::default();
+
+        let mut b = match format {
+            WeatherFormat::Positionless => {
+                let wind = b.get(..8)?;
+                if wind[0] != b'c' || wind[4] != b's' {
+                    return None;
+                }
+
+                weather.wind_direction = parse_value(&wind[1..4])?;
+                weather.wind_speed = parse_value(&wind[5..8])?;
+
+                &b[8..]
+            }
+            WeatherFormat::Uncompressed => {
+                let wind = b.get(..7)?;
+                if wind[3] != b'/' {
+                    return None;
+                }
+
+                weather.wind_direction = parse_value(&wind[0..3])?;
+                weather.wind_speed = parse_value(&wind[4..7])?;
+
+                &b[7..]
+            }
+            WeatherFormat::Compressed => b,
+        };
+
+        let mut found_any = format != WeatherFormat::Compressed;
+
+        // parse fields until we hit something we don't understand,
+        // everything after that is the comment
+        while let Some(rest) = weather.decode_field(b) {
+            b = rest;
+            found_any = true;
+        }
+
+        if !found_any {
+            return None;
+        }
+
+        Some((weather, b))
+    }
+
+    // Decodes a single field at the start of `b`.
+    // Returns the remaining bytes, or `None` if there is no valid field.
+    fn decode_field<'a>(&mut self, b: &'a [u8]) -> Option<&'a [u8]> {
+        let len = match b.first()? {
+            b'h' => 2,
+            b'b' => 5,
+            _ => 3,
+        };
+        let value = b.get(1..=len)?;
+
+        match b[0] {
+            b'g' => self.wind_gust = parse_value(value)?,
+            b't' => self.temperature = parse_value(value)?,
+            b'r' => self.rain_last_hour = parse_value(value)?,
+            b'p' => self.rain_last_24_hours = parse_value(value)?,
+            b'P' => self.rain_since_midnight = parse_value(value)?,
+            b'h' => {
+                // 00 represents 100%
+                self.humidity = parse_value::<u8>(value)?.map(|h| if h == 0 { 100 } else { h })
+            }
+            b'b' => self.barometric_pressure = parse_value(value)?,
+            b'L' => self.luminosity = parse_value(value)?,
+            b'l' => self.luminosity = parse_value::<u16>(value)?.map(|l| l + 1000),
+            b's' => self.snow = parse_value(value)?,
+            b'#' => self.raw_rain_counter = parse_value(value)?,
+            _ => return None,
+        }
+
+        Some(&b[(len + 1)..])
+    }
+
+    /// Fails with `EncodeError::InvalidWeatherValue` if a value
+    /// doesn't fit into its fixed-width field
+    pub(crate) fn encode<W: Write>(
+        &self,
+        buf: &mut W,
+        format: WeatherFormat,
+    ) -> Result<(), EncodeError> {
+        match format {
+            WeatherFormat::Positionless => {
+                write!(buf, "c")?;
+                encode_value(buf, 'c', self.wind_direction, 3)?;
+                write!(buf, "s")?;
+                encode_value(buf, 's', self.wind_speed, 3)?;
+            }
+            WeatherFormat::Uncompressed => {
+                encode_value(buf, 'c', self.wind_direction, 3)?;
+                write!(buf, "/")?;
+                encode_value(buf, 's', self.wind_speed, 3)?;
+            }
+            // wind direction and speed are encoded in the `cs` bytes of the position
+            WeatherFormat::Compressed => {}
+        }
+
+        write!(buf, "g")?;
+        encode_value(buf, 'g', self.wind_gust, 3)?;
+        write!(buf, "t")?;
+        encode_value(buf, 't', self.temperature, 3)?;
+
+        encode_field(buf, 'r', self.rain_last_hour, 3)?;
+        encode_field(buf, 'p', self.rain_last_24_hours, 3)?;
+        encode_field(buf, 'P', self.rain_since_midnight, 3)?;
+        match self.humidity {
+            // 00 represents 100%
+            Some(100) => write!(buf, "h00")?,
+            Some(h @ 1..=99) => write!(buf, "h{:02}", h)?,
+            Some(_) => return Err(EncodeError::InvalidWeatherValue('h')),
+            None => {}
+        }
+        encode_field(buf, 'b', self.barometric_pressure, 5)?;
+        match self.luminosity {
+            Some(l) if l >= 1000 => encode_field(buf, 'l', Some(l - 1000), 3)?,
+            l => encode_field(buf, 'L', l, 3)?,
+        }
+        encode_field(buf, 's', self.snow, 3)?;
+        encode_field(buf, '#', self.raw_rain_counter, 3)?;
+
+        Ok(())
+    }
+}
+
+// Returns `Some(None)` if the value is marked as missing,
+// and `None` if the value is invalid
+fn parse_value<T: std::str::FromStr>(b: &[u8]) -> Option<Option<T>> {
+    if b.iter().all(|c| *c == b'.' || *c == b' ') {
+        return Some(None);
+    }
+
+    // only digits and a leading '-' are allowed, `parse` would also accept '+'
+    let digits = b.strip_prefix(b"-").unwrap_or(b);
+    if !digits.iter().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    parse_bytes(b).map(Some)
+}
+
+// Writes `value` with `width` digits, or dots if it is missing.
+// `field` is the letter of the field, for the error.
+fn encode_value<W: Write, T: Into<i64>>(
+    buf: &mut W,
+    field: char,
+    value: Option<T>,
+    width: usize,
+) -> Result<(), EncodeError> {
+    match value {
+        Some(v) => {
+            let v = v.into();
+            let max = 10_i64.pow(width as u32) - 1;
+            // one digit is taken by the '-'
+            let min = -(10_i64.pow(width as u32 - 1) - 1);
+            if v < min || v > max {
+                return Err(EncodeError::InvalidWeatherValue(field));
+            }
+
+            write!(buf, "{:0width$}", v, width = width)?
+        }
+        None => {
+            for _ in 0..width {
+                buf.write_all(b".")?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Writes an optional field with its letter, if it is present
+fn encode_field<W: Write, T: Into<i64>>(
+    buf: &mut W,
+    field: char,
+    value: Option<T>,
+    width: usize,
+) -> Result<(), EncodeError> {
+    if value.is_some() {
+        write!(buf, "{}", field)?;
+        encode_value(buf, field, value, width)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_uncompressed() {
+        let (weather, comment) = Weather::decode(
+            b"220/004g005t-07r001p010P100h00b10132L456s002#123wRSW",
+            WeatherFormat::Uncompressed,
+        )
+        .unwrap();
+
+        assert_eq!(
+            Weather {
+                wind_direction: Some(220),
+                wind_speed: Some(4),
+                wind_gust: Some(5),
+                temperature: Some(-7),
+                rain_last_hour: Some(1),
+                rain_last_24_hours: Some(10),
+                rain_since_midnight: Some(100),
+                humidity: Some(100),
+                barometric_pressure: Some(10132),
+                luminosity: Some(456),
+                snow: Some(2),
+                raw_rain_counter: Some(123),
+            },
+            weather
+        );
+        assert_eq!(b"wRSW", comment);
+    }
+
+    #[test]
+    fn decode_missing_values() {
+        let (weather, comment) =
+            Weather::decode(b".../   g...t077l010", WeatherFormat::Uncompressed).unwrap();
+
+        assert_eq!(
+            Weather {
+                temperature: Some(77),
+                luminosity: Some(1010),
+                ..Default::default()
+            },
+            weather
+        );
+        assert_eq!(b"", comment);
+    }
+
+    #[test]
+    fn decode_positionless() {
+        let (weather, comment) =
+            Weather::decode(b"c220s004g005t077h50", WeatherFormat::Positionless).unwrap();
+
+        assert_eq!(Some(220), weather.wind_direction);
+        assert_eq!(Some(4), weather.wind_speed);
+        assert_eq!(Some(50), weather.humidity);
+        assert_eq!(None, weather.snow);
+        assert_eq!(b"", comment);
+    }
+
+    #[test]
+    fn decode_invalid_value_ends_fields() {
+        let (weather, comment) =
+            Weather::decode(b"g005t077hello world", WeatherFormat::Compressed).unwrap();
+
+        assert_eq!(Some(5), weather.wind_gust);
+        assert_eq!(Some(77), weather.temperature);
+        assert_eq!(b"hello world", comment);
+    }
+
+    #[test]
+    fn decode_compressed_without_fields() {
+        assert_eq!(None, Weather::decode(b"Hello", WeatherFormat::Compressed));
+    }
+
+    #[test]
+    fn encode_roundtrip() {
+        let formats = vec![
+            (
+                &b"c220s004g005t077r000p000P000h50b09900"[..],
+                WeatherFormat::Positionless,
+            ),
+            (
+                &b"220/004g005t-07L456s002#123"[..],
+                WeatherFormat::Uncompressed,
+            ),
+            (&b".../...g...t...l010"[..], WeatherFormat::Uncompressed),
+            (&b"g005t077r000"[..], WeatherFormat::Compressed),
+        ];
+
+        for (bytes, format) in formats {
+            let (weather, _) = Weather::decode(bytes, format).unwrap();
+
+            let mut buf = vec![];
+            weather.encode(&mut buf, format).unwrap();
+            assert_eq!(bytes, &buf[..], "{}", buf.escape_ascii());
+        }
+    }
+
+    #[test]
+    fn decode_plus_sign() {
+        let (weather, comment) = Weather::decode(b"g005t+77", WeatherFormat::Compressed).unwrap();
+
+        assert_eq!(None, weather.temperature);
+        assert_eq!(b"t+77", comment);
+    }
+
+    #[test]
+    fn encode_out_of_range() {
+        let invalid = vec![
+            (
+                Weather {
+                    humidity: Some(150),
+                    ..Default::default()
+                },
+                'h',
+            ),
+            (
+                Weather {
+                    humidity: Some(0),
+                    ..Default::default()
+                },
+                'h',
+            ),
+            (
+                Weather {
+                    luminosity: Some(2000),
+                    ..Default::default()
+                },
+                'l',
+            ),
+            (
+                Weather {
+                    temperature: Some(-100),
+                    ..Default::default()
+                },
+                't',
+            ),
+            (
+                Weather {
+                    wind_direction: Some(1000),
+                    ..Default::default()
+                },
+                'c',
+            ),
+        ];
+
+        for (weather, field) in invalid {
+            let result = weather.encode(&mut vec![], WeatherFormat::Uncompressed);
+            assert!(
+                matches!(result, Err(EncodeError::InvalidWeatherValue(f)) if f == field),
+                "{:?}",
+                weather
+            );
+        }
+
+        let weather = Weather {
+            temperature: Some(-99),
+            humidity: Some(100),
+            luminosity: Some(1999),
+            ..Default::default()
+        };
+        let mut buf = vec![];
+        weather.encode(&mut buf, WeatherFormat::Compressed).unwrap();
+        assert_eq!(b"g...t-99h00l999", &buf[..]);
+    }
+}
@@ -0,0 +1,302 @@
+//! Many stations violate APRS101 slightly, e.g. with lowercase callsigns
+//! or overlong comments. By default such packets are decoded as usual,
+//! but `AprsPacket::decode_textual_with_options` reports every violation
+//! as a `DecodeWarning`, or rejects the packet in strict mode.
+//! This includes data that couldn't be decoded, but doesn't fail
+//! the packet, like weather reports that are kept as `AprsData::Invalid`.
+
+use std::ops::Range;
+
+use AprsPacket;
+use DecodeError;
+use Field;
+use SpecViolation;
+
+use crate::diagnostic::{self, SpannedError};
+
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct DecodeOptions {
+    /// Reject packets that would have warnings with `DecodeError::NonCompliant`
+    pub strict: bool,
+}
+
+impl DecodeOptions {
+    pub fn strict() -> Self {
+        Self { strict: true }
+    }
+
+    pub fn lenient() -> Self {
+        Self { strict: false }
+    }
+}
+
+/// Something that lenient decoding accepted, but strict decoding rejects
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum DecodeWarning {
+    /// The packet violates the spec, see `AprsPacket::validate`.
+    /// The span is the header or the information field.
+    #[error("{violation}")]
+    NonCompliant {
+        violation: SpecViolation,
+        span: Range<usize>,
+    },
+    /// A part of the information field couldn't be decoded
+    /// and was kept as `AprsData::Invalid` or `AprsData::Unknown`,
+    /// e.g. weather data or a '[' without the closing ']'
+    #[error("{error}")]
+    Undecodable {
+        error: DecodeError,
+        span: Range<usize>,
+        field: Field,
+    },
+}
+
+impl DecodeWarning {
+    /// The offending bytes within the packet
+    pub fn span(&self) -> Range<usize> {
+        match self {
+            Self::NonCompliant { span, .. } | Self::Undecodable { span, .. } => span.clone(),
+        }
+    }
+
+    pub fn field(&self) -> Field {
+        match self {
+            Self::NonCompliant { violation, .. } => violation.field(),
+            Self::Undecodable { field, .. } => *field,
+        }
+    }
+
+    pub fn expected(&self) -> &'static str {
+        match self {
+            Self::NonCompliant { violation, .. } => violation.expected(),
+            Self::Undecodable { error, .. } => diagnostic::describe(error).1,
+        }
+    }
+}
+
+impl From<SpannedError> for DecodeWarning {
+    fn from(e: SpannedError) -> Self {
+        Self::Undecodable {
+            error: e.error,
+            span: e.span,
+            field: e.field,
+        }
+    }
+}
+
+/// A packet decoded with `AprsPacket::decode_textual_with_options`
+#[derive(PartialEq, Debug, Clone)]
+pub struct DecodedPacket {
+    pub packet: AprsPacket,
+    pub warnings: Vec<DecodeWarning>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use AprsData;
+    use Callsign;
+    use Diagnostic;
+    use Timestamp;
+
+    fn violations(warnings: Vec<DecodeWarning>) -> Vec<SpecViolation> {
+        warnings
+            .into_iter()
+            .map(|w| match w {
+                DecodeWarning::NonCompliant { violation, .. } => violation,
+                w => panic!("Unexpected warning: {:?}", w),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn lenient() {
+        let decoded = AprsPacket::decode_textual_with_options(
+            b"n0call>APRS:@322575z4903.50N/07201.75W-Test 001234",
+            &DecodeOptions::lenient(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            decoded.warnings,
+            vec![
+                DecodeWarning::NonCompliant {
+                    violation: SpecViolation::LowercaseCallsign(Callsign::new_no_ssid("n0call")),
+                    span: 0..11,
+                },
+                DecodeWarning::NonCompliant {
+                    violation: SpecViolation::TimestampOutOfRange(Timestamp::DDHHMM(32, 25, 75)),
+                    span: 12..50,
+                },
+            ]
+        );
+        assert_eq!(
+            Ok(decoded.packet),
+            AprsPacket::decode_textual(b"n0call>APRS:@322575z4903.50N/07201.75W-Test 001234")
+        );
+    }
+
+    #[test]
+    fn strict() {
+        let options = DecodeOptions::strict();
+
+        assert_eq!(
+            AprsPacket::decode_textual_with_options(
+                b"N0CALL>APRS:;MY OBJECT*092345z4903.50N/07201.75W>",
+                &options
+            ),
+            Err(DecodeError::NonCompliant(vec![
+                DecodeWarning::NonCompliant {
+                    violation: SpecViolation::SpaceInObjectName(b"MY OBJECT".to_vec()),
+                    span: 12..49,
+                }
+            ]))
+        );
+
+        let decoded = AprsPacket::decode_textual_with_options(
+            b"N0CALL>APRS:;OBJECT   *092345z4903.50N/07201.75W>",
+            &options,
+        )
+        .unwrap();
+        assert!(decoded.warnings.is_empty());
+    }
+
+    #[test]
+    fn aprs_is_path() {
+        for input in [
+            &b"N0CALL>APRS,TCPIP*,qAC,T2FINLAND:!4903.50N/07201.75W-"[..],
+            b"N0CALL>APRS,qAS,AE5PL-RELAY:>hi",
+        ] {
+            let decoded =
+                AprsPacket::decode_textual_with_options(input, &DecodeOptions::strict()).unwrap();
+            assert!(decoded.warnings.is_empty());
+        }
+
+        // the digipeaters before the q construct are still checked
+        assert!(AprsPacket::decode_textual_with_options(
+            b"N0CALL>APRS,DIGIPEATER,qAR,T2FINLAND:>hi",
+            &DecodeOptions::strict()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn undecodable() {
+        let input = b"N0CALL>APRS:_1009055";
+        let decoded =
+            AprsPacket::decode_textual_with_options(input, &DecodeOptions::lenient()).unwrap();
+
+        assert!(matches!(decoded.packet.data, AprsData::Invalid { .. }));
+        assert_eq!(decoded.warnings.len(), 1);
+        assert_eq!(decoded.warnings[0].span(), 13..20);
+        assert_eq!(decoded.warnings[0].field(), Field::Timestamp);
+        assert!(matches!(
+            AprsPacket::decode_textual_with_options(input, &DecodeOptions::strict()),
+            Err(DecodeError::NonCompliant(_))
+        ));
+
+        let input = b"N0CALL>APRS:[JO62QN";
+        let decoded =
+            AprsPacket::decode_textual_with_options(input, &DecodeOptions::lenient()).unwrap();
+        assert!(matches!(decoded.packet.data, AprsData::Unknown(_)));
+        assert_eq!(
+            decoded.warnings,
+            vec![DecodeWarning::Undecodable {
+                error: DecodeError::InvalidMaidenhead(b"JO62QN".to_vec()),
+                span: 13..19,
+                field: Field::Maidenhead,
+            }]
+        );
+        assert!(AprsPacket::decode_textual_with_options(input, &DecodeOptions::strict()).is_err());
+    }
+
+    #[test]
+    fn undecodable_third_party() {
+        // the invalid weather report is inside the third-party packet
+        let input = b"N0CALL>APRS:}N1CALL>APRS:_1009";
+        let decoded =
+            AprsPacket::decode_textual_with_options(input, &DecodeOptions::lenient()).unwrap();
+
+        assert!(matches!(decoded.packet.data, AprsData::ThirdParty(_)));
+        assert_eq!(decoded.warnings.len(), 1);
+        assert_eq!(decoded.warnings[0].span(), 26..30);
+    }
+
+    #[test]
+    fn relaxed_fields() {
+        for input in [
+            &b"N0CALL>APRS::DEST     :Hello{abc!"[..],
+            b"N0CALL>APRS:$GPGGA,123519,4807.038,N,01131.000,E,1,08,0.9,545.4,M,46.9,M,,*00",
+        ] {
+            let decoded =
+                AprsPacket::decode_textual_with_options(input, &DecodeOptions::lenient()).unwrap();
+            assert_eq!(decoded.warnings.len(), 1, "{:?}", decoded.warnings);
+            assert_eq!(decoded.warnings[0].span(), 12..input.len());
+
+            assert!(
+                AprsPacket::decode_textual_with_options(input, &DecodeOptions::strict()).is_err()
+            );
+        }
+    }
+
+    #[test]
+    fn diagnostic() {
+        let input = b"N0CALL>APRS,wide1-1*:>Hello";
+        let warnings =
+            match AprsPacket::decode_textual_with_options(input, &DecodeOptions::strict()) {
+                Err(DecodeError::NonCompliant(warnings)) => warnings,
+                r => panic!("Unexpected result: {:?}", r),
+            };
+
+        let d = Diagnostic::from(warnings[0].clone());
+        assert_eq!(d.span, 0..20);
+        assert_eq!(d.field, Field::Header);
+        assert_eq!(d.expected, "an uppercase callsign");
+        assert_eq!(d.error, DecodeError::NonCompliant(warnings));
+    }
+
+    #[test]
+    fn lengths() {
+        let comment = "x".repeat(44);
+        let position = format!("N0CALL>APRS:!4903.50N/07201.75W-{}", comment);
+        let message = format!("N0CALL>APRS::DEST     :{}", "x".repeat(68));
+        let status = format!("N0CALL>APRS:>092345z{}", "x".repeat(56));
+
+        let warnings = |s: &str| {
+            violations(
+                AprsPacket::decode_textual_with_options(s.as_bytes(), &DecodeOptions::default())
+                    .unwrap()
+                    .warnings,
+            )
+        };
+
+        assert_eq!(
+            warnings(&position),
+            vec![SpecViolation::CommentTooLong { len: 44, max: 43 }]
+        );
+        assert_eq!(
+            warnings(&message),
+            vec![SpecViolation::MessageTooLong { len: 68, max: 67 }]
+        );
+        assert_eq!(
+            warnings(&status),
+            vec![SpecViolation::CommentTooLong { len: 56, max: 55 }]
+        );
+    }
+
+    #[test]
+    fn noncompliant_status_timestamp() {
+        let decoded = AprsPacket::decode_textual_with_options(
+            b"N0CALL>APRS:>120503hFatal error",
+            &DecodeOptions::lenient(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            violations(decoded.warnings),
+            vec![SpecViolation::NoncompliantTimestamp(Timestamp::HHMMSS(
+                12, 5, 3
+            ))]
+        );
+    }
+}
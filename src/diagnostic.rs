@@ -0,0 +1,400 @@
+//! Diagnostics point at the bytes of a packet that couldn't be decoded,
+//! e.g. to debug the configuration of a tracker:
+//!
+//! ```text
+//! invalid latitude at bytes 13..21
+//! N0CALL>APRS:!49O3.50N/07201.75W-
+//!              ^^^^^^^^ expected a latitude as DDMM.hhN or DDMM.hhS
+//! ```
+
+use std::fmt::{Display, Formatter};
+use std::ops::Range;
+
+use DecodeError;
+use DecodeWarning;
+
+/// The part of a packet that was being decoded
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Field {
+    Header,
+    Source,
+    Destination,
+    Via,
+    Timestamp,
+    Position,
+    Latitude,
+    Longitude,
+    CourseSpeed,
+    Extension,
+    Comment,
+    Message,
+    MicE,
+    Object,
+    Item,
+    /// The name of an object or item
+    Name,
+    Weather,
+    Telemetry,
+    Maidenhead,
+    UserDefined,
+    Nmea,
+}
+
+impl Display for Field {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Field::Header => "header",
+            Field::Source => "source callsign",
+            Field::Destination => "destination callsign",
+            Field::Via => "via path",
+            Field::Timestamp => "timestamp",
+            Field::Position => "position",
+            Field::Latitude => "latitude",
+            Field::Longitude => "longitude",
+            Field::CourseSpeed => "compressed course/speed",
+            Field::Extension => "extension",
+            Field::Comment => "comment",
+            Field::Message => "message",
+            Field::MicE => "Mic-E data",
+            Field::Object => "object",
+            Field::Item => "item",
+            Field::Name => "name",
+            Field::Weather => "weather data",
+            Field::Telemetry => "telemetry",
+            Field::Maidenhead => "Maidenhead locator",
+            Field::UserDefined => "user-defined data",
+            Field::Nmea => "NMEA sentence",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// The offending bytes within the packet.
+    /// Empty if something is missing, e.g. at the end of the packet.
+    pub span: Range<usize>,
+    pub field: Field,
+    pub expected: &'static str,
+    pub error: DecodeError,
+}
+
+impl Diagnostic {
+    pub(crate) fn new(error: SpannedError) -> Self {
+        let (_, expected) = describe(&error.error);
+
+        Self {
+            span: error.span,
+            field: error.field,
+            expected,
+            error: error.error,
+        }
+    }
+
+    /// Renders the packet with the span underlined,
+    /// followed by what was expected.
+    /// Non-printable bytes are shown as '.'.
+    pub fn render(&self, input: &[u8]) -> String {
+        let line: String = input
+            .iter()
+            .map(|&c| {
+                if c.is_ascii_graphic() || c == b' ' {
+                    c as char
+                } else {
+                    '.'
+                }
+            })
+            .collect();
+        let width = self.span.len().max(1);
+
+        format!(
+            "{}\n{}\n{}{} expected {}\n",
+            self,
+            line,
+            " ".repeat(self.span.start),
+            "^".repeat(width),
+            self.expected
+        )
+    }
+}
+
+/// Strict decoding rejects packets with warnings,
+/// which can be shown like errors
+impl From<DecodeWarning> for Diagnostic {
+    fn from(warning: DecodeWarning) -> Self {
+        let span = warning.span();
+        let field = warning.field();
+        let expected = warning.expected();
+        let error = match warning {
+            DecodeWarning::Undecodable { error, .. } => error,
+            w @ DecodeWarning::NonCompliant { .. } => DecodeError::NonCompliant(vec![w]),
+        };
+
+        Self {
+            span,
+            field,
+            expected,
+            error,
+        }
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "invalid {} at bytes {}..{}",
+            self.field, self.span.start, self.span.end
+        )
+    }
+}
+
+impl std::error::Error for Diagnostic {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// A decode error with the bytes it is about.
+/// Decoders return spans relative to their own input,
+/// callers move them with `offset` when they pass on a part of theirs.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct SpannedError {
+    pub error: DecodeError,
+    pub span: Range<usize>,
+    pub field: Field,
+}
+
+impl SpannedError {
+    /// The field is the one the error usually belongs to
+    pub(crate) fn new(error: DecodeError, span: Range<usize>) -> Self {
+        let (field, _) = describe(&error);
+
+        Self { error, span, field }
+    }
+
+    pub(crate) fn with_field(self, field: Field) -> Self {
+        Self { field, ..self }
+    }
+
+    pub(crate) fn offset(self, offset: usize) -> Self {
+        Self {
+            span: (self.span.start + offset)..(self.span.end + offset),
+            ..self
+        }
+    }
+}
+
+impl From<SpannedError> for DecodeError {
+    fn from(e: SpannedError) -> Self {
+        e.error
+    }
+}
+
+pub(crate) trait SpanResult<T> {
+    /// Attaches `span` to an error
+    fn span(self, span: Range<usize>) -> Result<T, SpannedError>;
+}
+
+impl<T> SpanResult<T> for Result<T, DecodeError> {
+    fn span(self, span: Range<usize>) -> Result<T, SpannedError> {
+        self.map_err(|e| SpannedError::new(e, span))
+    }
+}
+
+pub(crate) trait OffsetResult<T> {
+    /// Moves the span of an error by `offset`
+    fn offset(self, offset: usize) -> Result<T, SpannedError>;
+}
+
+impl<T> OffsetResult<T> for Result<T, SpannedError> {
+    fn offset(self, offset: usize) -> Result<T, SpannedError> {
+        self.map_err(|e| e.offset(offset))
+    }
+}
+
+/// The field an error usually belongs to and what was expected instead
+pub(crate) fn describe(error: &DecodeError) -> (Field, &'static str) {
+    match error {
+        DecodeError::InvalidCallsign(_) => (Field::Header, "a callsign with an optional SSID"),
+        DecodeError::InvalidVia(_) => (
+            Field::Via,
+            "a callsign with an optional SSID and '*', or a q construct",
+        ),
+        DecodeError::InvalidTimestamp(_) => (
+            Field::Timestamp,
+            "a timestamp as DDHHMMz, DDHHMM/, HHMMSSh or MMDDHHMM",
+        ),
+        DecodeError::UnsupportedPositionFormat(_) | DecodeError::InvalidPosition(_) => (
+            Field::Position,
+            "an uncompressed or compressed position with a symbol",
+        ),
+        DecodeError::InvalidLatitude(_) => (Field::Latitude, "a latitude as DDMM.hhN or DDMM.hhS"),
+        DecodeError::InvalidLongitude(_) => {
+            (Field::Longitude, "a longitude as DDDMM.hhE or DDDMM.hhW")
+        }
+        DecodeError::InvalidPacket(_) => (Field::Header, "a header as SOURCE>DEST,PATH:"),
+        DecodeError::InvalidMessageDestination(_) => (
+            Field::Message,
+            "a 9 character addressee padded with spaces, followed by ':'",
+        ),
+        DecodeError::InvalidMessageId(_) => {
+            (Field::Message, "a message ID of 1 to 5 letters or digits")
+        }
+        DecodeError::InvalidCs(_) => (
+            Field::CourseSpeed,
+            "a base-91 course/speed, radio range or altitude",
+        ),
+        DecodeError::InvalidMicEDestination(_) => (
+            Field::Destination,
+            "6 Mic-E latitude digits with an optional SSID",
+        ),
+        DecodeError::InvalidMicEInformation(_) => {
+            (Field::MicE, "Mic-E longitude, speed, course and symbol")
+        }
+        DecodeError::InvalidObjectName(_) => (Field::Object, "a 9 character object name"),
+        DecodeError::InvalidObjectLiveness(_) => (Field::Object, "'*' (live) or ' ' (killed)"),
+        DecodeError::InvalidObject => (
+            Field::Object,
+            "a 9 character name, liveness, timestamp and position",
+        ),
+        DecodeError::InvalidItem => (
+            Field::Item,
+            "a 3 to 9 character name, liveness and position",
+        ),
+        DecodeError::InvalidItemName(_) => (
+            Field::Item,
+            "a 3 to 9 character item name without '!' or ' '",
+        ),
+        DecodeError::InvalidItemLiveness(_) => (Field::Item, "'!' (live) or ' ' (killed)"),
+        DecodeError::InvalidExtension(_) => (Field::Extension, "a 7 character data extension"),
+        DecodeError::InvalidExtensionRange(_) => (Field::Extension, "a radio range as RNGrrrr"),
+        DecodeError::InvalidExtensionDirectionSpeed(_) => {
+            (Field::Extension, "a course and speed as ddd/sss")
+        }
+        DecodeError::InvalidExtensionPhg(_) => (Field::Extension, "PHGphgd"),
+        DecodeError::InvalidExtensionDfs(_) => (Field::Extension, "DFSshgd"),
+        DecodeError::InvalidExtensionArea(_) => (Field::Extension, "an area object as Tyy/Cxx"),
+        DecodeError::InvalidWeather(_) => (
+            Field::Weather,
+            "weather data as a letter followed by digits, e.g. t077",
+        ),
+        DecodeError::InvalidTelemetry(_) => {
+            (Field::Telemetry, "T#sss,aaa,aaa,aaa,aaa,aaa,bbbbbbbb")
+        }
+        DecodeError::InvalidMaidenhead(_) => (
+            Field::Maidenhead,
+            "a 4, 6 or 8 character Maidenhead locator",
+        ),
+        DecodeError::InvalidUserDefined(_) => {
+            (Field::UserDefined, "a user ID and a packet type character")
+        }
+        DecodeError::InvalidNmea(_) => (Field::Nmea, "a comma separated NMEA sentence"),
+        DecodeError::NonCompliant(warnings) => warnings
+            .first()
+            .map(|w| (w.field(), w.expected()))
+            .unwrap_or((Field::Header, "a packet that complies with the APRS spec")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use AprsPacket;
+
+    fn diagnose(input: &[u8]) -> Diagnostic {
+        AprsPacket::decode_textual_diagnostic(input).unwrap_err()
+    }
+
+    #[test]
+    fn header_spans() {
+        let d = diagnose(b"N0CALL APRS>Hello");
+        assert_eq!(d.span, 17..17);
+        assert_eq!(d.field, Field::Header);
+
+        let d = diagnose(b"N0CALL APRS:>Hello");
+        assert_eq!(d.span, 0..11);
+        assert_eq!(d.field, Field::Header);
+
+        let d = diagnose(b"N0\xffCALL>APRS:>Hello");
+        assert_eq!(d.span, 0..7);
+        assert_eq!(d.field, Field::Source);
+
+        let d = diagnose(b"N0CALL>AP-:>Hello");
+        assert_eq!(d.span, 7..10);
+        assert_eq!(d.field, Field::Destination);
+
+        let d = diagnose(b"N0CALL>APRS,WIDE1-1,-1:>Hello");
+        assert_eq!(d.span, 20..22);
+        assert_eq!(d.field, Field::Via);
+    }
+
+    #[test]
+    fn body_spans() {
+        let d = diagnose(b"N0CALL>APRS:!49O3.50N/07201.75W-");
+        assert_eq!(d.span, 13..21);
+        assert_eq!(d.field, Field::Latitude);
+        assert_eq!(d.error, DecodeError::InvalidLatitude(b"49O3.50N".to_vec()));
+
+        let d = diagnose(b"N0CALL>APRS:@092345x4903.50N/07201.75W-");
+        assert_eq!(d.span, 13..20);
+        assert_eq!(d.field, Field::Timestamp);
+
+        let d = diagnose(b"N0CALL>APRS:;LEADER   #092345z4903.50N/07201.75W>");
+        assert_eq!(d.span, 22..23);
+        assert_eq!(d.field, Field::Object);
+    }
+
+    #[test]
+    fn nested_spans() {
+        let d = diagnose(b"N0CALL>APRS::DEST:Hello");
+        assert_eq!(d.span, 13..17);
+        assert_eq!(d.field, Field::Message);
+
+        let d = diagnose(b"N0CALL>APRS:)AID#2#4903.50N/07201.75W>");
+        assert_eq!(d.span, 16..17);
+        assert_eq!(d.field, Field::Item);
+
+        let d = diagnose(b"N0CALL>APRS:=/5L!!< e7>7P[");
+        assert_eq!(d.span, 18..22);
+        assert_eq!(d.field, Field::Longitude);
+
+        let d = diagnose(b"N0CALL>APRS:;LEADER   *092345z49O3.50N/07201.75W>");
+        assert_eq!(d.span, 30..38);
+        assert_eq!(d.field, Field::Latitude);
+    }
+
+    #[test]
+    fn mic_e_spans() {
+        let d = diagnose(b"N0CALL>HELLO0:`(_fn\"Oj/");
+        assert_eq!(d.span, 7..13);
+        assert_eq!(d.field, Field::Destination);
+
+        let d = diagnose(b"N0CALL>T2SP0W:`(_f");
+        assert_eq!(d.span, 15..18);
+        assert_eq!(d.field, Field::MicE);
+    }
+
+    #[test]
+    fn render() {
+        let input = b"N0CALL>APRS:!49O3.50N/07201.75W-\x1c";
+        let d = diagnose(input);
+
+        assert_eq!(
+            d.render(input),
+            "invalid latitude at bytes 13..21\n\
+             N0CALL>APRS:!49O3.50N/07201.75W-.\n             \
+             ^^^^^^^^ expected a latitude as DDMM.hhN or DDMM.hhS\n"
+        );
+
+        let input = b"N0CALL";
+        let d = diagnose(input);
+        assert_eq!(
+            d.render(input),
+            "invalid header at bytes 6..6\n\
+             N0CALL\n      \
+             ^ expected a header as SOURCE>DEST,PATH:\n"
+        );
+    }
+}
@@ -1,7 +1,9 @@
 use Callsign;
+use DecodeWarning;
+use SpecViolation;
 
 use crate::Extension;
-#[derive(Debug, Eq, PartialEq, thiserror::Error)]
+#[derive(Debug, Clone, Eq, PartialEq, thiserror::Error)]
 pub enum DecodeError {
     #[error("Invalid Callsign: {0:?}")]
     InvalidCallsign(Vec<u8>),
@@ -56,6 +58,19 @@ pub enum DecodeError {
 
     #[error("Invalid Extension Area value: {0:?}")]
     InvalidExtensionArea(Vec<u8>),
+
+    #[error("Invalid Weather data: {0:?}")]
+    InvalidWeather(Vec<u8>),
+    #[error("Invalid Telemetry data: {0:?}")]
+    InvalidTelemetry(Vec<u8>),
+    #[error("Invalid Maidenhead locator: {0:?}")]
+    InvalidMaidenhead(Vec<u8>),
+    #[error("Invalid User-Defined packet: {0:?}")]
+    InvalidUserDefined(Vec<u8>),
+    #[error("Invalid NMEA sentence: {0:?}")]
+    InvalidNmea(Vec<u8>),
+    #[error("Packet doesn't comply with the APRS spec: {0:?}")]
+    NonCompliant(Vec<DecodeWarning>),
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -70,11 +85,26 @@ pub enum EncodeError {
     InvalidData,
     #[error("Invalid Message Addressee: {0:?}")]
     InvalidMessageAddressee(Vec<u8>),
+    #[error("Invalid Message ID: {0:?}")]
+    InvalidMessageId(Vec<u8>),
     #[error("Compressed altitude requires the nmea source to be gga")]
     NonGgaAltitude,
+    #[error("Telemetry definition exceeds the limits of the APRS spec")]
+    InvalidTelemetryDefinition,
+    #[error("Comment telemetry exceeds the limits of the APRS spec")]
+    InvalidCommentTelemetry,
+    #[error("Invalid Altitude: {0}")]
+    InvalidAltitude(i32),
+    /// The letter of the weather field whose value doesn't fit
+    #[error("Weather value doesn't fit into the field {0:?}")]
+    InvalidWeatherValue(char),
+    #[error("Invalid DAO datum: {0:?}")]
+    InvalidDatum(char),
     #[error(transparent)]
     Write(#[from] std::io::Error),
 
     #[error("Invalid Extension value: {0:?}")]
     InvalidExtension(Extension),
+    #[error("Packet doesn't comply with the APRS spec: {0:?}")]
+    NonCompliant(Vec<SpecViolation>),
 }
@@ -0,0 +1,80 @@
+//! A Maidenhead Locator Beacon starts with the '[' APRS Data Type Identifier.
+//! The 4 or 6 character locator is terminated by a ']', followed by an optional comment.
+//!
+//! Example:
+//! - "[IO91SX] 35 miles NNW of London"
+
+use std::io::Write;
+
+use Callsign;
+use DecodeError;
+use EncodeError;
+use MaidenheadLocator;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AprsGrid {
+    pub to: Callsign,
+
+    pub locator: MaidenheadLocator,
+    pub comment: Vec<u8>,
+}
+
+impl AprsGrid {
+    pub fn decode(b: &[u8], to: Callsign) -> Result<Self, DecodeError> {
+        let end = b
+            .iter()
+            .position(|&c| c == b']')
+            .ok_or_else(|| DecodeError::InvalidMaidenhead(b.to_vec()))?;
+        let locator = MaidenheadLocator::decode(&b[..end])
+            .ok_or_else(|| DecodeError::InvalidMaidenhead(b[..end].to_vec()))?;
+
+        Ok(Self {
+            to,
+            locator,
+            comment: b[(end + 1)..].to_vec(),
+        })
+    }
+
+    pub fn encode<W: Write>(&self, buf: &mut W) -> Result<(), EncodeError> {
+        buf.write_all(b"[")?;
+        self.locator.encode(buf)?;
+        buf.write_all(b"]")?;
+        buf.write_all(&self.comment)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn default_callsign() -> Callsign {
+        Callsign::new_no_ssid("VE9")
+    }
+
+    #[test]
+    fn decode_grid() {
+        let result =
+            AprsGrid::decode(b"IO91SX] 35 miles NNW of London", default_callsign()).unwrap();
+
+        assert_eq!(result.locator, MaidenheadLocator::new("IO91SX").unwrap());
+        assert_eq!(result.comment, b" 35 miles NNW of London");
+
+        let mut buf = vec![];
+        result.encode(&mut buf).unwrap();
+        assert_eq!(buf, b"[IO91SX] 35 miles NNW of London");
+    }
+
+    #[test]
+    fn decode_invalid_grid() {
+        assert_eq!(
+            AprsGrid::decode(b"IO91SX", default_callsign()),
+            Err(DecodeError::InvalidMaidenhead(b"IO91SX".to_vec()))
+        );
+        assert_eq!(
+            AprsGrid::decode(b"IO9]", default_callsign()),
+            Err(DecodeError::InvalidMaidenhead(b"IO9".to_vec()))
+        );
+    }
+}
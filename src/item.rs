@@ -9,7 +9,14 @@
 
 use std::io::Write;
 
-use crate::{AprsCst, Callsign, DecodeError, EncodeError, Extension, Position};
+use crate::{
+    AprsCst, Callsign, CommentOffsets, CommentTelemetry, DecodeError, EncodeError, Extension,
+    Position, SpecViolation, Weather,
+};
+
+use crate::components::comment::{self, CommentTokens};
+use crate::diagnostic::{OffsetResult, SpannedError};
+use crate::validate;
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct AprsItem {
@@ -18,54 +25,29 @@ pub struct AprsItem {
     pub live: bool,
     pub position: Position,
     pub extension: Option<Extension>,
+    pub weather: Option<Weather>,
+    pub altitude: Option<i32>,
     pub comment: Vec<u8>,
+    pub telemetry: Option<CommentTelemetry>,
+    pub comment_offsets: CommentOffsets,
 }
 
 impl AprsItem {
     pub fn decode(b: &[u8], to: Callsign) -> Result<Self, DecodeError> {
-        // items are odd, name is 3..9 in length, any char except '!' or ' '
-        // as that ends name parsing
-        let mut name = b
-            .get(..3)
-            .ok_or_else(|| DecodeError::InvalidItemName(b.to_vec()))?
-            .to_vec();
-
-        let mut packet_offset_index = 3;
-
-        for index in packet_offset_index..9.min(b.len()) {
-            // dev note: we should use .is_some_and() here but MSRV is 1.70 for that feature
-            if let Some(c) = b.get(index) {
-                if [b' ', b'!'].contains(c) {
-                    // we found the end of the name
-                    packet_offset_index = index;
-                    break;
-                }
-            }
-        }
-        // add these several bytes to name
-        name.extend(
-            b.get(3..packet_offset_index)
-                .ok_or_else(|| DecodeError::InvalidItemName(b.to_vec()))?,
-        );
-
-        if name.contains(&b'!') || name.contains(&b' ') {
-            // check that the name is not bad right away
-            return Err(DecodeError::InvalidItemName(b.to_vec()));
-        }
-
-        let live = match *b.get(packet_offset_index).ok_or(DecodeError::InvalidItem)? as char {
-            '!' => Ok(true),
-            ' ' => Ok(false),
-            others => Err(DecodeError::InvalidItemLiveness(others)),
-        }?;
+        Self::decode_spanned(b, to).map_err(DecodeError::from)
+    }
 
-        let (remaining_buffer, position) = Position::decode(
-            b.get(packet_offset_index + 1..)
-                .ok_or_else(|| DecodeError::InvalidTimestamp(b.to_vec()))?,
-        )?;
+    pub(crate) fn decode_spanned(b: &[u8], to: Callsign) -> Result<Self, SpannedError> {
+        let (name, live, rest) = decode_header(b)?;
+        let (remaining_buffer, mut position) =
+            Position::decode(rest).offset(b.len() - rest.len())?;
 
         // decide where the comment comes from
-        let (extension, comment) = if matches!(position.cst, AprsCst::Uncompressed) {
+        let weather = remaining_buffer.and_then(|b| position.decode_weather(b));
+        let (weather, extension, mut comment) = if let Some((weather, comment)) = weather {
+            // weather stations report wind direction/speed in place of an extension
+            (Some(weather), None, comment.to_vec())
+        } else if matches!(position.cst, AprsCst::Uncompressed) {
             // opportunistically decode extensions if we can
             if let Some(comment_bytes) = remaining_buffer {
                 if let Some(ext) = comment_bytes
@@ -73,39 +55,73 @@ impl AprsItem {
                     .and_then(|ext| Extension::decode(ext).ok())
                 {
                     (
+                        None,
                         Some(ext),
                         comment_bytes.get(7..).unwrap_or_default().to_vec(),
                     )
                 } else {
-                    (None, comment_bytes.to_vec())
+                    (None, None, comment_bytes.to_vec())
                 }
             } else {
-                (None, vec![])
+                (None, None, vec![])
             }
         } else {
-            (None, remaining_buffer.unwrap_or_default().to_vec())
+            (None, None, remaining_buffer.unwrap_or_default().to_vec())
         };
 
+        let tokens = CommentTokens::extract(&mut comment, Some(&mut position));
+
         Ok(Self {
             to,
-            name,
+            name: name.to_vec(),
             live,
             position,
+            altitude: tokens.altitude,
             comment,
             extension,
+            weather,
+            telemetry: tokens.telemetry,
+            comment_offsets: tokens.offsets,
         })
     }
 
+    /// Returns all violations of the APRS spec
+    pub fn validate(&self) -> Vec<SpecViolation> {
+        let mut violations = vec![];
+        if !(validate::MIN_ITEM_NAME_LEN..=validate::MAX_NAME_LEN).contains(&self.name.len()) {
+            violations.push(SpecViolation::InvalidNameLength(self.name.clone()));
+        }
+        if self.name.contains(&b'!') || self.name.contains(&b' ') {
+            violations.push(SpecViolation::InvalidItemName(self.name.clone()));
+        }
+        if self.weather.is_none() {
+            let max = validate::max_comment_len(self.extension.is_some());
+            let len = comment::encoded_len(
+                &self.comment,
+                self.altitude,
+                self.telemetry.as_ref(),
+                &self.position.dao_token().unwrap_or_default(),
+                &self.comment_offsets,
+            );
+            validate::check_comment(len, max, &mut violations);
+        }
+
+        violations
+    }
+
     pub fn encode<W: Write>(&self, buf: &mut W) -> Result<(), EncodeError> {
         let mut name = self.name.clone();
         name.truncate(9);
         write!(buf, ")")?;
-        buf.write_all(&self.name)?;
+        buf.write_all(&name)?;
 
         write!(buf, "{}", if self.live { '!' } else { ' ' })?;
 
         // if we have extensions, we have to do an uncompressed encoding to support it
-        if let Some(ext) = &self.extension {
+        if let Some(weather) = &self.weather {
+            self.position.encode(buf)?;
+            weather.encode(buf, self.position.weather_format())?;
+        } else if let Some(ext) = &self.extension {
             self.position.encode_uncompressed(buf)?;
             ext.encode(buf)?;
         } else if matches!(self.position.cst, AprsCst::Uncompressed) {
@@ -114,12 +130,72 @@ impl AprsItem {
             self.position.encode_compressed(buf)?;
         }
 
-        buf.write_all(&self.comment)?;
+        comment::encode(
+            buf,
+            &self.comment,
+            self.altitude,
+            self.telemetry.as_ref(),
+            &self.position.dao_token()?,
+            &self.comment_offsets,
+        )?;
 
         Ok(())
     }
 }
 
+/// Splits the name and liveness from the position and comment
+pub(crate) fn decode_header(b: &[u8]) -> Result<(&[u8], bool, &[u8]), SpannedError> {
+    // items are odd, name is 3..9 in length, any char except '!' or ' '
+    // as that ends name parsing
+    if b.len() < 3 {
+        return Err(SpannedError::new(
+            DecodeError::InvalidItemName(b.to_vec()),
+            0..b.len(),
+        ));
+    }
+
+    let mut packet_offset_index = 3;
+
+    for index in packet_offset_index..9.min(b.len()) {
+        // dev note: we should use .is_some_and() here but MSRV is 1.70 for that feature
+        if let Some(c) = b.get(index) {
+            if [b' ', b'!'].contains(c) {
+                // we found the end of the name
+                packet_offset_index = index;
+                break;
+            }
+        }
+    }
+    let name = &b[..packet_offset_index];
+
+    if name.contains(&b'!') || name.contains(&b' ') {
+        // check that the name is not bad right away
+        return Err(SpannedError::new(
+            DecodeError::InvalidItemName(b.to_vec()),
+            0..name.len(),
+        ));
+    }
+
+    let live = match b.get(packet_offset_index) {
+        Some(b'!') => true,
+        Some(b' ') => false,
+        Some(&c) => {
+            return Err(SpannedError::new(
+                DecodeError::InvalidItemLiveness(c as char),
+                packet_offset_index..(packet_offset_index + 1),
+            ))
+        }
+        None => {
+            return Err(SpannedError::new(
+                DecodeError::InvalidItem,
+                packet_offset_index..packet_offset_index,
+            ))
+        }
+    };
+
+    Ok((name, live, &b[(packet_offset_index + 1)..]))
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -209,6 +285,24 @@ mod tests {
         }
     }
 
+    #[test]
+    fn parse_compressed_weather_item() {
+        let packet =
+            AprsPacket::decode_textual(br"N0CALL>APRS:)WX1!/5L!!<*e7_7P[g005t077h50").unwrap();
+
+        if let AprsData::Item(o) = packet.data {
+            let weather = o.weather.unwrap();
+            // wind direction and speed are part of the compressed position
+            assert_eq!(weather.wind_direction, Some(88));
+            assert_eq!(weather.wind_speed, Some(42));
+            assert_eq!(weather.wind_gust, Some(5));
+            assert_eq!(weather.humidity, Some(50));
+            assert!(o.comment.is_empty());
+        } else {
+            panic!("Unexpected data type");
+        }
+    }
+
     #[test]
     fn decode_recode_uncompressed() {
         let textual_repr = b"N8DEU-7>APZWX,WIDE2-2:)AID 4903.50N/07201.75WAPHG5132";
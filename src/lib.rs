@@ -8,7 +8,7 @@
 //! ```rust
 //! extern crate aprs_parser;
 //!
-//! use aprs_parser::{AprsCst, AprsData, AprsPacket, AprsPosition, Callsign, Latitude, Longitude, Precision, Timestamp, Via, QConstruct, Position};
+//! use aprs_parser::{AprsCst, CommentOffsets, AprsData, AprsPacket, AprsPosition, Callsign, Extension, Latitude, Longitude, Precision, Timestamp, Via, QConstruct, Position};
 //!
 //! fn main() {
 //!     let result = AprsPacket::decode_textual(
@@ -42,8 +42,17 @@
 //!                             symbol_table: '\\',
 //!                             symbol_code: '^',
 //!                             cst: AprsCst::Uncompressed,
+//!                             dao: None,
 //!                         },
-//!                         comment: b"322/103/A=003054".to_vec(),
+//!                         extension: Some(Extension::DirectionSpeed {
+//!                             direction_degrees: 322,
+//!                             speed_knots: 103,
+//!                         }),
+//!                         weather: None,
+//!                         altitude: Some(3054),
+//!                         comment: vec![],
+//!                         telemetry: None,
+//!                         comment_offsets: CommentOffsets::default(),
 //!                     }
 //!                 )
 //!             }
@@ -61,44 +70,83 @@ extern crate thiserror;
 #[macro_use]
 extern crate approx;
 
+pub mod aprs_is;
 mod base91;
 mod bytes;
 mod callsign;
+mod capabilities;
 mod compressed_cs;
 pub mod compression_type;
+mod decode_options;
+mod diagnostic;
 mod error;
+mod grid;
 
 mod item;
 mod message;
 pub mod mic_e;
 mod object;
 mod packet;
+mod packet_ref;
 mod position;
+mod query;
+mod raw_gps;
+mod raw_packet;
 mod status;
+mod telemetry;
+mod telemetry_registry;
+mod third_party;
+mod unknown;
+mod user_defined;
+mod weather;
 
 mod components;
 mod utils;
+mod validate;
 mod via;
 
 pub use callsign::Callsign;
 
+pub use capabilities::{AprsCapabilities, Capability};
 pub use components::{
+    comment::CommentOffsets,
+    comment_telemetry::CommentTelemetry,
+    dao::{Dao, DaoPrecision},
     extensions::*,
     lonlat::{Latitude, Longitude, Precision},
+    maidenhead::MaidenheadLocator,
     position::*,
     timestamp::{DhmTimestamp, Timestamp},
+    weather::Weather,
 };
 pub use compressed_cs::{AprsAltitude, AprsCompressedCs, AprsCourseSpeed, AprsRadioRange};
 pub use compression_type::AprsCompressionType;
+pub use decode_options::{DecodeOptions, DecodeWarning, DecodedPacket};
+pub use diagnostic::{Diagnostic, Field};
 pub use error::{DecodeError, EncodeError};
 
-pub use message::AprsMessage;
+pub use grid::AprsGrid;
+pub use message::{AddresseeKind, AprsMessage, MessageKind, NwsPrefix};
 pub use mic_e::AprsMicE;
 pub use object::AprsObject;
 pub use packet::{AprsData, AprsPacket};
+pub use packet_ref::{
+    AprsDataRef, AprsPacketRef, CallsignRef, ItemRef, MessageRef, MicERef, ObjectRef, PositionRef,
+    ViaRef,
+};
 pub use position::AprsPosition;
-pub use status::AprsStatus;
+pub use query::{AprsQuery, QueryKind};
+pub use raw_gps::{AprsRawGps, FixQuality, NmeaSentence};
+pub use raw_packet::RawAprsPacket;
+pub use status::{AprsStatus, GridStatus};
+pub use telemetry::{AprsTelemetry, TelemetryDefinition, TelemetryEquation, TelemetrySequence};
+pub use telemetry_registry::{AnalogReading, DigitalReading, TelemetryReadings, TelemetryRegistry};
+pub use third_party::AprsThirdParty;
+pub use unknown::AprsUnknown;
+pub use user_defined::{AprsTestData, AprsUserDefined};
+pub use validate::{EncodeOptions, SpecViolation};
 pub use via::{QConstruct, Via};
+pub use weather::AprsWeather;
 
 #[cfg(test)]
 mod tests {
@@ -3,53 +3,300 @@ use std::io::Write;
 use Callsign;
 use DecodeError;
 use EncodeError;
+use QueryKind;
+use SpecViolation;
+use TelemetryDefinition;
+
+use crate::diagnostic::SpannedError;
+use crate::validate;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum MessageKind {
+    /// A regular text message
+    Message,
+    /// Acknowledges the message with the given ID (`ackMM`)
+    Ack,
+    /// Rejects the message with the given ID (`rejMM`)
+    Rej,
+}
+
+/// The category of a message's addressee.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum AddresseeKind {
+    /// A station, or anything that doesn't fall into another category
+    Station,
+    /// `BLN0` to `BLN9`. `id` is the ASCII digit.
+    Bulletin { id: u8 },
+    /// `BLNA` to `BLNZ`. `id` is the ASCII letter.
+    Announcement { id: u8 },
+    /// A bulletin to a group, e.g. `BLN4WX` with `id` `b'4'` and `group` `WX`
+    GroupBulletin { id: u8, group: Vec<u8> },
+    /// A National Weather Service bulletin, e.g. `NWS-WARN`.
+    /// `name` is the part after the prefix.
+    Nws { prefix: NwsPrefix, name: Vec<u8> },
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum NwsPrefix {
+    /// `NWS-`
+    Nws,
+    /// `SKY`
+    Sky,
+    /// `CWA`
+    Cwa,
+}
+
+impl AddresseeKind {
+    fn classify(addressee: &[u8]) -> Self {
+        for (prefix, p) in [
+            (NwsPrefix::Nws, &b"NWS-"[..]),
+            (NwsPrefix::Sky, b"SKY"),
+            (NwsPrefix::Cwa, b"CWA"),
+        ] {
+            if addressee.len() > p.len() && addressee.starts_with(p) {
+                return AddresseeKind::Nws {
+                    prefix,
+                    name: addressee[p.len()..].to_vec(),
+                };
+            }
+        }
+
+        let (id, group) = match addressee {
+            [b'B', b'L', b'N', id, group @ ..] => (*id, group),
+            _ => return AddresseeKind::Station,
+        };
+
+        match (id, group) {
+            (b'0'..=b'9', []) => AddresseeKind::Bulletin { id },
+            (b'A'..=b'Z', []) => AddresseeKind::Announcement { id },
+            (b'0'..=b'9', _) if group.iter().all(u8::is_ascii_alphanumeric) => {
+                AddresseeKind::GroupBulletin {
+                    id,
+                    group: group.to_vec(),
+                }
+            }
+            _ => AddresseeKind::Station,
+        }
+    }
+}
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct AprsMessage {
     pub to: Callsign,
 
     pub addressee: Vec<u8>,
+    pub kind: MessageKind,
+    /// Always empty for acks and rejects.
     pub text: Vec<u8>,
+    /// The message ID. For acks and rejects, this is the ID of the
+    /// message that is being answered and must be present.
     pub id: Option<Vec<u8>>,
+    /// The reply-ack from the `{MM}AA` form.
+    /// An empty reply-ack (`{MM}`) means that the sender supports reply-acks
+    /// but doesn't have anything to acknowledge.
+    pub reply_ack: Option<Vec<u8>>,
 }
 
 impl AprsMessage {
+    /// Creates an ack for the message with the given ID.
+    pub fn new_ack(to: Callsign, addressee: Vec<u8>, id: Vec<u8>) -> Self {
+        Self::new_response(to, addressee, MessageKind::Ack, id)
+    }
+
+    /// Creates a reject for the message with the given ID.
+    pub fn new_rej(to: Callsign, addressee: Vec<u8>, id: Vec<u8>) -> Self {
+        Self::new_response(to, addressee, MessageKind::Rej, id)
+    }
+
+    fn new_response(to: Callsign, addressee: Vec<u8>, kind: MessageKind, id: Vec<u8>) -> Self {
+        Self {
+            to,
+            addressee,
+            kind,
+            text: vec![],
+            id: Some(id),
+            reply_ack: None,
+        }
+    }
+
+    /// Creates a directed query, e.g. `?APRSP`.
+    pub fn new_query(
+        to: Callsign,
+        addressee: Vec<u8>,
+        kind: &QueryKind,
+        args: &[u8],
+    ) -> Result<Self, EncodeError> {
+        let mut text = vec![];
+        kind.encode(&mut text, args)?;
+
+        Ok(Self {
+            to,
+            addressee,
+            kind: MessageKind::Message,
+            text,
+            id: None,
+            reply_ack: None,
+        })
+    }
+
+    /// Returns the directed query and its arguments contained in this message, if any.
+    /// Unknown query types are treated as regular text.
+    pub fn directed_query(&self) -> Option<(QueryKind, &[u8])> {
+        if self.kind != MessageKind::Message {
+            return None;
+        }
+
+        match QueryKind::decode(self.text.strip_prefix(b"?")?) {
+            (QueryKind::Unknown(_), _) => None,
+            query => Some(query),
+        }
+    }
+
+    /// Creates a telemetry definition message.
+    /// These are addressed to the telemetry station itself.
+    pub fn new_telemetry_definition(
+        to: Callsign,
+        station: &Callsign,
+        definition: &TelemetryDefinition,
+    ) -> Result<Self, EncodeError> {
+        let mut text = vec![];
+        definition.encode(&mut text)?;
+
+        Ok(Self {
+            to,
+            addressee: station.to_string().into_bytes(),
+            kind: MessageKind::Message,
+            text,
+            id: None,
+            reply_ack: None,
+        })
+    }
+
+    /// Classifies the addressee as a station, a bulletin, an announcement,
+    /// a group bulletin or an NWS bulletin.
+    pub fn addressee_kind(&self) -> AddresseeKind {
+        AddresseeKind::classify(&self.addressee)
+    }
+
+    /// Returns the telemetry definition (`PARM.`, `UNIT.`, `EQNS.` or `BITS.`)
+    /// contained in this message, if any.
+    pub fn telemetry_definition(&self) -> Option<TelemetryDefinition> {
+        if self.kind != MessageKind::Message {
+            return None;
+        }
+
+        TelemetryDefinition::decode(&self.text)
+    }
+
     pub fn decode(b: &[u8], to: Callsign) -> Result<Self, DecodeError> {
+        Self::decode_spanned(b, to).map_err(DecodeError::from)
+    }
+
+    pub(crate) fn decode_spanned(b: &[u8], to: Callsign) -> Result<Self, SpannedError> {
         let mut splitter = b.splitn(2, |x| *x == b':');
 
         let mut addressee = match splitter.next() {
             Some(x) => x.to_vec(),
             None => {
-                return Err(DecodeError::InvalidMessageDestination(vec![]));
+                return Err(SpannedError::new(
+                    DecodeError::InvalidMessageDestination(vec![]),
+                    0..0,
+                ));
             }
         };
 
         if addressee.len() != 9 {
-            return Err(DecodeError::InvalidMessageDestination(addressee.to_owned()));
+            let span = 0..addressee.len();
+            return Err(SpannedError::new(
+                DecodeError::InvalidMessageDestination(addressee),
+                span,
+            ));
         }
 
         crate::utils::trim_spaces_end(&mut addressee);
 
         let text = splitter.next().unwrap_or(&[]);
+
+        let response =
+            response_kind(text).and_then(|(kind, rest)| split_reply_ack(rest).map(|x| (kind, x)));
+        if let Some((kind, (id, reply_ack))) = response {
+            return Ok(Self {
+                to,
+
+                addressee,
+                kind,
+                text: vec![],
+                id: Some(id.to_vec()),
+                reply_ack: reply_ack.map(|x| x.to_vec()),
+            });
+        }
+
         let mut text_splitter = text.splitn(2, |x| *x == b'{');
         let text = text_splitter.next().unwrap_or(&[]).to_vec();
-        let id = text_splitter.next().map(|x| x.to_vec());
+        // IDs that don't follow the spec are kept as they are,
+        // `validate` reports them
+        let (id, reply_ack) = match text_splitter.next() {
+            Some(x) => match split_reply_ack(x) {
+                Some((id, reply_ack)) => (Some(id.to_vec()), reply_ack.map(|x| x.to_vec())),
+                None => (Some(x.to_vec()), None),
+            },
+            None => (None, None),
+        };
 
         Ok(Self {
             to,
 
             addressee,
+            kind: MessageKind::Message,
             text,
             id,
+            reply_ack,
         })
     }
 
+    /// Returns all violations of the APRS spec.
+    pub fn validate(&self) -> Vec<SpecViolation> {
+        let mut violations = vec![];
+        if self.addressee.len() > 9 {
+            violations.push(SpecViolation::AddresseeTooLong(self.addressee.clone()));
+        }
+        if self.text.len() > validate::MAX_MESSAGE_LEN {
+            violations.push(SpecViolation::MessageTooLong {
+                len: self.text.len(),
+                max: validate::MAX_MESSAGE_LEN,
+            });
+        }
+        if let Some(c) = self.text.iter().find(|c| [b'|', b'~', b'{'].contains(c)) {
+            violations.push(SpecViolation::InvalidMessageCharacter(*c));
+        }
+        if let Some(id) = &self.id {
+            if !is_valid_id(id) {
+                violations.push(SpecViolation::InvalidMessageId(id.clone()));
+            }
+        }
+        if let Some(reply_ack) = &self.reply_ack {
+            if !reply_ack.is_empty() && !is_valid_id(reply_ack) {
+                violations.push(SpecViolation::InvalidMessageId(reply_ack.clone()));
+            }
+            // the reply-ack is written after the ID
+            if self.id.is_none() {
+                violations.push(SpecViolation::InvalidMessageId(vec![]));
+            }
+        }
+
+        violations
+    }
+
     pub fn encode<W: Write>(&self, buf: &mut W) -> Result<(), EncodeError> {
         if self.addressee.len() > 9 {
             return Err(EncodeError::InvalidMessageAddressee(
                 self.addressee.to_owned(),
             ));
         }
+        // the reply-ack is written after the ID
+        if self.reply_ack.is_some() && self.id.is_none() {
+            return Err(EncodeError::InvalidMessageId(vec![]));
+        }
 
         buf.write_all(b":")?;
         buf.write_all(&self.addressee)?;
@@ -58,17 +305,74 @@ impl AprsMessage {
         }
 
         buf.write_all(b":")?;
-        buf.write_all(&self.text)?;
 
-        if let Some(id) = &self.id {
-            buf.write_all(b"{")?;
-            buf.write_all(id)?;
+        match self.kind {
+            MessageKind::Message => {
+                buf.write_all(&self.text)?;
+
+                if let Some(id) = &self.id {
+                    buf.write_all(b"{")?;
+                    buf.write_all(id)?;
+                }
+            }
+            MessageKind::Ack | MessageKind::Rej => {
+                let id = self
+                    .id
+                    .as_ref()
+                    .ok_or_else(|| EncodeError::InvalidMessageId(vec![]))?;
+
+                if self.kind == MessageKind::Ack {
+                    buf.write_all(b"ack")?;
+                } else {
+                    buf.write_all(b"rej")?;
+                }
+                buf.write_all(id)?;
+            }
+        }
+
+        if let Some(reply_ack) = &self.reply_ack {
+            buf.write_all(b"}")?;
+            buf.write_all(reply_ack)?;
         }
 
         Ok(())
     }
 }
 
+// Message IDs are 1-5 alphanumeric characters
+fn is_valid_id(id: &[u8]) -> bool {
+    (1..=5).contains(&id.len()) && id.iter().all(u8::is_ascii_alphanumeric)
+}
+
+// Splits `MM}AA` into the ID and the optional reply-ack
+fn split_reply_ack(b: &[u8]) -> Option<(&[u8], Option<&[u8]>)> {
+    let mut splitter = b.splitn(2, |x| *x == b'}');
+    let id = splitter.next()?;
+    let reply_ack = splitter.next();
+
+    if !is_valid_id(id) {
+        return None;
+    }
+    if let Some(reply_ack) = reply_ack {
+        if !reply_ack.is_empty() && !is_valid_id(reply_ack) {
+            return None;
+        }
+    }
+
+    Some((id, reply_ack))
+}
+
+// Anything that isn't followed by a valid ID is a regular message
+fn response_kind(text: &[u8]) -> Option<(MessageKind, &[u8])> {
+    let kind = match text.get(0..3)? {
+        b"ack" => MessageKind::Ack,
+        b"rej" => MessageKind::Rej,
+        _ => return None,
+    };
+
+    Some((kind, &text[3..]))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -103,8 +407,10 @@ mod tests {
             Ok(AprsMessage {
                 to: default_callsign(),
                 addressee: b"DESTINATI".to_vec(),
+                kind: MessageKind::Message,
                 id: Some(b"329A7D5Z4".to_vec()),
-                text: b"Hello World! This msg has a : colon ".to_vec()
+                text: b"Hello World! This msg has a : colon ".to_vec(),
+                reply_ack: None,
             })
         );
     }
@@ -121,12 +427,180 @@ mod tests {
             Ok(AprsMessage {
                 to: default_callsign(),
                 addressee: b"DESTINATI".to_vec(),
+                kind: MessageKind::Message,
                 id: Some(vec![]),
-                text: b"Hello World! This msg has a : colon ".to_vec()
+                text: b"Hello World! This msg has a : colon ".to_vec(),
+                reply_ack: None,
             })
         );
     }
 
+    #[test]
+    fn parse_message_invalid_id() {
+        for (b, text, id) in [
+            (
+                &b"DEST     :Hello World {3a2B975"[..],
+                &b"Hello World "[..],
+                &b"3a2B975"[..],
+            ),
+            (b"DEST     :Meet at {the park}", b"Meet at ", b"the park}"),
+            (b"DEST     :smile :-{", b"smile :-", b""),
+            (b"DEST     :{123456", b"", b"123456"),
+            (b"DEST     :Hello {32}A-", b"Hello ", b"32}A-"),
+        ] {
+            let message = AprsMessage::decode(b, default_callsign()).unwrap();
+            assert_eq!(message.text, text);
+            assert_eq!(message.id, Some(id.to_vec()));
+            assert_eq!(message.reply_ack, None);
+            assert_eq!(
+                message.validate(),
+                vec![SpecViolation::InvalidMessageId(id.to_vec())]
+            );
+
+            let mut buf = vec![];
+            message.encode(&mut buf).unwrap();
+            assert_eq!(&buf[1..], b);
+        }
+    }
+
+    #[test]
+    fn parse_reply_ack() {
+        let result = AprsMessage::decode(b"DESTINATI:Hello{MM}AA", default_callsign()).unwrap();
+
+        assert_eq!(result.kind, MessageKind::Message);
+        assert_eq!(result.text, b"Hello");
+        assert_eq!(result.id, Some(b"MM".to_vec()));
+        assert_eq!(result.reply_ack, Some(b"AA".to_vec()));
+
+        let result = AprsMessage::decode(b"DESTINATI:Hello{MM}", default_callsign()).unwrap();
+        assert_eq!(result.id, Some(b"MM".to_vec()));
+        assert_eq!(result.reply_ack, Some(vec![]));
+    }
+
+    #[test]
+    fn parse_ack_and_rej() {
+        let result = AprsMessage::decode(b"DESTINATI:ack123", default_callsign());
+        assert_eq!(
+            result,
+            Ok(AprsMessage {
+                to: default_callsign(),
+                addressee: b"DESTINATI".to_vec(),
+                kind: MessageKind::Ack,
+                id: Some(b"123".to_vec()),
+                text: vec![],
+                reply_ack: None,
+            })
+        );
+
+        let result = AprsMessage::decode(b"DESTINATI:rejAB}CD", default_callsign()).unwrap();
+        assert_eq!(result.kind, MessageKind::Rej);
+        assert_eq!(result.id, Some(b"AB".to_vec()));
+        assert_eq!(result.reply_ack, Some(b"CD".to_vec()));
+    }
+
+    #[test]
+    fn parse_ack_lookalikes() {
+        for text in [&b"ack"[..], b"acknowledged", b"ack 12", b"reject this"] {
+            let mut b = b"DESTINATI:".to_vec();
+            b.extend_from_slice(text);
+
+            let result = AprsMessage::decode(&b, default_callsign()).unwrap();
+            assert_eq!(result.kind, MessageKind::Message);
+            assert_eq!(result.text, text);
+            assert_eq!(result.id, None);
+        }
+    }
+
+    #[test]
+    fn encode_ack_and_rej() {
+        let mut buf = vec![];
+        AprsMessage::new_ack(default_callsign(), b"N0CALL".to_vec(), b"123".to_vec())
+            .encode(&mut buf)
+            .unwrap();
+        assert_eq!(buf, b":N0CALL   :ack123");
+
+        let mut rej = AprsMessage::new_rej(default_callsign(), b"N0CALL".to_vec(), b"AB".to_vec());
+        rej.reply_ack = Some(b"CD".to_vec());
+        let mut buf = vec![];
+        rej.encode(&mut buf).unwrap();
+        assert_eq!(buf, b":N0CALL   :rejAB}CD");
+    }
+
+    #[test]
+    fn encode_reply_ack() {
+        let message = AprsMessage {
+            to: default_callsign(),
+            addressee: b"N0CALL".to_vec(),
+            kind: MessageKind::Message,
+            text: b"Hello".to_vec(),
+            id: Some(b"MM".to_vec()),
+            reply_ack: Some(vec![]),
+        };
+
+        let mut buf = vec![];
+        message.encode(&mut buf).unwrap();
+        assert_eq!(buf, b":N0CALL   :Hello{MM}");
+    }
+
+    #[test]
+    fn encode_missing_id() {
+        let mut message =
+            AprsMessage::new_ack(default_callsign(), b"N0CALL".to_vec(), b"123".to_vec());
+        message.id = None;
+        assert!(matches!(
+            message.encode(&mut vec![]),
+            Err(EncodeError::InvalidMessageId(id)) if id.is_empty()
+        ));
+
+        let message = AprsMessage {
+            to: default_callsign(),
+            addressee: b"N0CALL".to_vec(),
+            kind: MessageKind::Message,
+            text: b"Hello".to_vec(),
+            id: None,
+            reply_ack: Some(b"AA".to_vec()),
+        };
+        assert!(matches!(
+            message.encode(&mut vec![]),
+            Err(EncodeError::InvalidMessageId(id)) if id.is_empty()
+        ));
+        assert_eq!(
+            message.validate(),
+            vec![SpecViolation::InvalidMessageId(vec![])]
+        );
+    }
+
+    #[test]
+    fn parse_telemetry_definition() {
+        let result = AprsMessage::decode(
+            r"N0QBF-11 :BITS.11111111,10-mile Balloon".as_bytes(),
+            default_callsign(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            result.telemetry_definition(),
+            Some(TelemetryDefinition::Bits {
+                sense: [true; 8],
+                project_title: b"10-mile Balloon".to_vec()
+            })
+        );
+    }
+
+    #[test]
+    fn encode_telemetry_definition() {
+        let message = AprsMessage::new_telemetry_definition(
+            default_callsign(),
+            &Callsign::new_with_ssid("N0QBF", "11"),
+            &TelemetryDefinition::Units(vec![b"v/100".to_vec(), b"deg.F".to_vec()]),
+        )
+        .unwrap();
+
+        let mut buf = vec![];
+        message.encode(&mut buf).unwrap();
+        assert_eq!(&b":N0QBF-11 :UNIT.v/100,deg.F"[..], buf);
+    }
+
     #[test]
     fn parse_message_no_id() {
         let result = AprsMessage::decode(
@@ -139,9 +613,107 @@ mod tests {
             Ok(AprsMessage {
                 to: default_callsign(),
                 addressee: b"DESTINATI".to_vec(),
+                kind: MessageKind::Message,
                 id: None,
-                text: b"Hello World! This msg has a : colon ".to_vec()
+                text: b"Hello World! This msg has a : colon ".to_vec(),
+                reply_ack: None,
             })
         );
     }
+
+    #[test]
+    fn addressee_kinds() {
+        for (addressee, kind) in [
+            (&b"N0CALL-5"[..], AddresseeKind::Station),
+            (b"BLN", AddresseeKind::Station),
+            (b"BLN3", AddresseeKind::Bulletin { id: b'3' }),
+            (b"BLNQ", AddresseeKind::Announcement { id: b'Q' }),
+            (
+                b"BLN4WX",
+                AddresseeKind::GroupBulletin {
+                    id: b'4',
+                    group: b"WX".to_vec(),
+                },
+            ),
+            (b"BLNAWX", AddresseeKind::Station),
+            (b"BLN4W-X", AddresseeKind::Station),
+            (
+                b"NWS-WARN",
+                AddresseeKind::Nws {
+                    prefix: NwsPrefix::Nws,
+                    name: b"WARN".to_vec(),
+                },
+            ),
+            (
+                b"SKYCWA",
+                AddresseeKind::Nws {
+                    prefix: NwsPrefix::Sky,
+                    name: b"CWA".to_vec(),
+                },
+            ),
+            (
+                b"CWAKMOB",
+                AddresseeKind::Nws {
+                    prefix: NwsPrefix::Cwa,
+                    name: b"KMOB".to_vec(),
+                },
+            ),
+            (b"NWS-", AddresseeKind::Station),
+        ] {
+            let mut b = addressee.to_vec();
+            b.resize(9, b' ');
+            b.extend_from_slice(b":Hello");
+
+            let message = AprsMessage::decode(&b, default_callsign()).unwrap();
+            assert_eq!(message.addressee_kind(), kind);
+        }
+    }
+
+    #[test]
+    fn directed_query() {
+        let message = AprsMessage::decode(b"N0CALL   :?APRSP", default_callsign()).unwrap();
+        assert_eq!(
+            message.directed_query(),
+            Some((QueryKind::Position, &b""[..]))
+        );
+
+        let message =
+            AprsMessage::decode(b"N0CALL   :?APRSH VE9ABC{12", default_callsign()).unwrap();
+        assert_eq!(
+            message.directed_query(),
+            Some((QueryKind::Heard, &b"VE9ABC"[..]))
+        );
+
+        let message = AprsMessage::decode(b"N0CALL   :?what", default_callsign()).unwrap();
+        assert_eq!(message.directed_query(), None);
+
+        let message = AprsMessage::new_query(
+            default_callsign(),
+            b"N0CALL".to_vec(),
+            &QueryKind::Ping,
+            b"",
+        )
+        .unwrap();
+        let mut buf = vec![];
+        message.encode(&mut buf).unwrap();
+        assert_eq!(buf, b":N0CALL   :?PING?");
+    }
+
+    #[test]
+    fn validate() {
+        let message = AprsMessage::decode(b"N0CALL   :Hello", default_callsign()).unwrap();
+        assert!(message.validate().is_empty());
+
+        let mut message = message;
+        message.addressee = b"LONGADDRESSEE".to_vec();
+        message.text = format!("{}|", "x".repeat(67)).into_bytes();
+        assert_eq!(
+            message.validate(),
+            vec![
+                SpecViolation::AddresseeTooLong(b"LONGADDRESSEE".to_vec()),
+                SpecViolation::MessageTooLong { len: 68, max: 67 },
+                SpecViolation::InvalidMessageCharacter(b'|'),
+            ]
+        );
+    }
 }
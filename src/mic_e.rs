@@ -1,12 +1,22 @@
 use std::convert::TryInto;
 use std::io::Write;
 
+use base91;
 use Callsign;
+use CommentOffsets;
+use CommentTelemetry;
+use Dao;
 use DecodeError;
 use EncodeError;
+use Field;
 use Latitude;
 use Longitude;
 use Precision;
+use SpecViolation;
+
+use crate::components::comment::{self, CommentTokens};
+use crate::diagnostic::{SpanResult, SpannedError};
+use crate::validate;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum Message {
@@ -28,7 +38,43 @@ pub enum Message {
     Unknown,
 }
 
+// the texts from the APRS spec
+const MESSAGE_TEXTS: [(Message, &str); 15] = [
+    (Message::M0, "Off Duty"),
+    (Message::M1, "En Route"),
+    (Message::M2, "In Service"),
+    (Message::M3, "Returning"),
+    (Message::M4, "Committed"),
+    (Message::M5, "Special"),
+    (Message::M6, "Priority"),
+    (Message::C0, "Custom-0"),
+    (Message::C1, "Custom-1"),
+    (Message::C2, "Custom-2"),
+    (Message::C3, "Custom-3"),
+    (Message::C4, "Custom-4"),
+    (Message::C5, "Custom-5"),
+    (Message::C6, "Custom-6"),
+    (Message::Emergency, "Emergency"),
+];
+
 impl Message {
+    /// Returns the standard text of the message, e.g. `"En Route"` for `M1`.
+    /// Returns `None` for `Unknown`.
+    pub fn text(&self) -> Option<&'static str> {
+        MESSAGE_TEXTS
+            .iter()
+            .find(|(m, _)| m == self)
+            .map(|(_, text)| *text)
+    }
+
+    /// Finds the message with the given standard text. Ignores case.
+    pub fn from_text(text: &str) -> Option<Self> {
+        MESSAGE_TEXTS
+            .iter()
+            .find(|(_, t)| t.eq_ignore_ascii_case(text))
+            .map(|(m, _)| *m)
+    }
+
     fn decode(a: MessageBit, b: MessageBit, c: MessageBit) -> Self {
         use self::Message::*;
         use self::MessageBit::{CustomOne, StandardOne, Zero};
@@ -135,6 +181,103 @@ impl Course {
     }
 }
 
+/// The device that sent a Mic-E report.
+/// It is identified by a type code at the start of the status text
+/// and, for newer devices, a suffix at the end of it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Device {
+    KenwoodThD7a,
+    KenwoodThD72,
+    KenwoodThD74,
+    KenwoodThD75,
+    KenwoodTmD700,
+    KenwoodTmD710,
+    YaesuVx8,
+    YaesuFtm350,
+    YaesuVx8g,
+    YaesuFt1d,
+    YaesuFtm400dr,
+    YaesuFtm100d,
+    YaesuFt2d,
+    YaesuFt3d,
+    YaesuFt5d,
+    YaesuFtm300d,
+    YaesuFtm500d,
+    Ap510,
+    AnytoneD578uv,
+    AnytoneD878uv,
+    ByonicsTinyTrak3,
+    ByonicsTinyTrak4,
+    ScsDr7400,
+    ScsDr7800,
+    /// A type code without a known suffix
+    Unknown(u8),
+}
+
+// type code and suffix of each device.
+// devices without a suffix must come after the ones with the same type code.
+const DEVICES: &[(Device, u8, &[u8])] = &[
+    (Device::KenwoodThD72, b'>', b"="),
+    (Device::KenwoodThD74, b'>', b"^"),
+    (Device::KenwoodThD75, b'>', b"&"),
+    (Device::KenwoodThD7a, b'>', b""),
+    (Device::KenwoodTmD710, b']', b"="),
+    (Device::KenwoodTmD700, b']', b""),
+    (Device::YaesuVx8, b'`', b"_ "),
+    (Device::YaesuFtm350, b'`', b"_\""),
+    (Device::YaesuVx8g, b'`', b"_#"),
+    (Device::YaesuFt1d, b'`', b"_$"),
+    (Device::YaesuFtm400dr, b'`', b"_%"),
+    (Device::YaesuFtm100d, b'`', b"_)"),
+    (Device::YaesuFt2d, b'`', b"_("),
+    (Device::YaesuFt3d, b'`', b"_0"),
+    (Device::YaesuFt5d, b'`', b"_3"),
+    (Device::YaesuFtm300d, b'`', b"_1"),
+    (Device::YaesuFtm500d, b'`', b"_5"),
+    (Device::Ap510, b'`', b" X"),
+    (Device::AnytoneD578uv, b'`', b"(5"),
+    (Device::AnytoneD878uv, b'`', b"(8"),
+    (Device::ByonicsTinyTrak3, b'\'', b"|3"),
+    (Device::ByonicsTinyTrak4, b'\'', b"|4"),
+    (Device::ScsDr7400, b'\'', b":4"),
+    (Device::ScsDr7800, b'\'', b":8"),
+];
+
+const TYPE_CODES: [u8; 4] = [b'>', b']', b'`', b'\''];
+
+impl Device {
+    // Removes the type code and the suffix from the status text
+    fn extract(comment: &mut Vec<u8>) -> Option<Self> {
+        let type_code = *comment.first().filter(|c| TYPE_CODES.contains(c))?;
+
+        let (device, suffix_len) = DEVICES
+            .iter()
+            .find(|(_, t, suffix)| {
+                *t == type_code && comment.len() > suffix.len() && comment.ends_with(suffix)
+            })
+            .map(|(d, _, suffix)| (*d, suffix.len()))
+            .unwrap_or((Device::Unknown(type_code), 0));
+
+        comment.truncate(comment.len() - suffix_len);
+        comment.remove(0);
+
+        Some(device)
+    }
+
+    fn type_code_and_suffix(&self) -> (u8, &'static [u8]) {
+        if let Device::Unknown(type_code) = self {
+            return (*type_code, b"");
+        }
+
+        // safe to unwrap, all other devices are in the table
+        DEVICES
+            .iter()
+            .find(|(d, _, _)| d == self)
+            .map(|(_, t, suffix)| (*t, *suffix))
+            .unwrap()
+    }
+}
+
 #[derive(PartialEq, Debug, Clone)]
 pub struct AprsMicE {
     pub latitude: Latitude,
@@ -146,44 +289,71 @@ pub struct AprsMicE {
     pub course: Course,
     pub symbol_table: u8,
     pub symbol_code: u8,
+    pub device: Option<Device>,
+    /// Altitude in meters, encoded as three base91 digits followed by `}`
+    /// at the start of the status text
+    pub altitude_meters: Option<i32>,
+    /// Altitude in feet, from a `/A=` in the status text
+    pub altitude: Option<i32>,
     pub comment: Vec<u8>,
+    pub telemetry: Option<CommentTelemetry>,
+    /// The `!DAO!` token of the status text.
+    /// It isn't applied to the coordinates, so it keeps its steps.
+    pub dao: Option<Dao>,
+    pub comment_offsets: CommentOffsets,
 
     pub current: bool,
 }
 
 impl AprsMicE {
     pub fn decode(b: &[u8], to: Callsign, current: bool) -> Result<Self, DecodeError> {
-        let (latitude, precision, message, long_offset, long_dir) =
-            decode_callsign(&to).ok_or(DecodeError::InvalidMicEDestination(to))?;
+        Self::decode_spanned(b, to, current).map_err(DecodeError::from)
+    }
 
-        let info = b
-            .get(0..8)
-            .ok_or_else(|| DecodeError::InvalidMicEInformation(b.to_vec()))?;
-        let comment = b.get(8..).unwrap_or(&[]).to_vec();
+    pub(crate) fn decode_spanned(
+        b: &[u8],
+        to: Callsign,
+        current: bool,
+    ) -> Result<Self, SpannedError> {
+        let fixed = MicEFixed::decode(to.call(), b, || {
+            DecodeError::InvalidMicEDestination(to.clone())
+        })?;
 
-        let longitude = decode_longitude(&info[0..3], long_offset, long_dir)
-            .ok_or_else(|| DecodeError::InvalidMicEInformation(b.to_vec()))?;
-        let (speed, course) = decode_speed_and_course(&info[3..6])
-            .ok_or_else(|| DecodeError::InvalidMicEInformation(b.to_vec()))?;
-        let symbol_code = info[6];
-        let symbol_table = info[7];
+        let mut comment = b.get(8..).unwrap_or(&[]).to_vec();
+        let device = Device::extract(&mut comment);
+        let altitude_meters = decode_altitude(&mut comment);
+        let tokens = CommentTokens::extract(&mut comment, None);
 
         Ok(Self {
-            latitude,
-            longitude,
-            precision,
-
-            message,
-            speed,
-            course,
-            symbol_table,
-            symbol_code,
+            latitude: fixed.latitude,
+            longitude: fixed.longitude,
+            precision: fixed.precision,
+
+            message: fixed.message,
+            speed: fixed.speed,
+            course: fixed.course,
+            symbol_table: fixed.symbol_table,
+            symbol_code: fixed.symbol_code,
+            device,
+            altitude_meters,
+            altitude: tokens.altitude,
             comment,
+            telemetry: tokens.telemetry,
+            dao: tokens.dao,
+            comment_offsets: tokens.offsets,
 
             current,
         })
     }
 
+    /// Returns all violations of the APRS spec
+    pub fn validate(&self) -> Vec<SpecViolation> {
+        let mut violations = vec![];
+        validate::check_symbol(self.symbol_table, self.symbol_code, &mut violations);
+
+        violations
+    }
+
     pub fn encode<W: Write>(&self, buf: &mut W) -> Result<(), EncodeError> {
         if self.current {
             buf.write_all(b"`")?;
@@ -195,7 +365,32 @@ impl AprsMicE {
         self.encode_speed_and_course(buf)?;
 
         buf.write_all(&[self.symbol_code, self.symbol_table])?;
-        buf.write_all(&self.comment)?;
+
+        let suffix = match self.device {
+            Some(device) => {
+                let (type_code, suffix) = device.type_code_and_suffix();
+                buf.write_all(&[type_code])?;
+                suffix
+            }
+            None => &[],
+        };
+        if let Some(altitude) = self.altitude_meters {
+            encode_altitude(buf, altitude)?;
+        }
+        let mut dao = vec![];
+        if let Some(d) = &self.dao {
+            d.encode(&mut dao, 0, 0)?;
+        }
+        comment::encode(
+            buf,
+            &self.comment,
+            self.altitude,
+            self.telemetry.as_ref(),
+            &dao,
+            &self.comment_offsets,
+        )?;
+
+        buf.write_all(suffix)?;
 
         Ok(())
     }
@@ -247,6 +442,7 @@ impl AprsMicE {
 
     fn encode_longitude<W: Write>(&self, w: &mut W) -> Result<(), EncodeError> {
         let (d, m, h, _) = self.longitude.dmh();
+        let (d, m, h) = blank_longitude_digits(d, m, h, self.precision);
 
         // safe to unwrap - all values must be less than 255
         let d: u8 = d.try_into().unwrap();
@@ -290,6 +486,88 @@ impl AprsMicE {
     }
 }
 
+/// The destination and the first 8 bytes of the information field,
+/// which are shared with `MicERef`
+pub(crate) struct MicEFixed {
+    pub latitude: Latitude,
+    pub longitude: Longitude,
+    pub precision: Precision,
+    pub message: Message,
+    pub speed: Speed,
+    pub course: Course,
+    pub symbol_table: u8,
+    pub symbol_code: u8,
+}
+
+impl MicEFixed {
+    /// Errors in the destination have the field `Destination`
+    /// and an empty span, because it isn't part of `b`
+    pub(crate) fn decode<F>(
+        call: &str,
+        b: &[u8],
+        invalid_destination: F,
+    ) -> Result<Self, SpannedError>
+    where
+        F: FnOnce() -> DecodeError,
+    {
+        let (latitude, precision, message, long_offset, long_dir) = decode_destination(call)
+            .ok_or_else(invalid_destination)
+            .span(0..0)
+            .map_err(|e| e.with_field(Field::Destination))?;
+
+        let info = b
+            .get(0..8)
+            .ok_or_else(|| DecodeError::InvalidMicEInformation(b.to_vec()))
+            .span(0..b.len())?;
+
+        let longitude = decode_longitude(&info[0..3], long_offset, long_dir, precision)
+            .ok_or_else(|| DecodeError::InvalidMicEInformation(b.to_vec()))
+            .span(0..3)?;
+        let (speed, course) = decode_speed_and_course(&info[3..6])
+            .ok_or_else(|| DecodeError::InvalidMicEInformation(b.to_vec()))
+            .span(3..6)?;
+
+        Ok(Self {
+            latitude,
+            longitude,
+            precision,
+            message,
+            speed,
+            course,
+            symbol_table: info[7],
+            symbol_code: info[6],
+        })
+    }
+}
+
+// Mic-E altitudes are relative to 10km below sea level
+const ALTITUDE_OFFSET: i32 = 10_000;
+
+// Removes the altitude (`xxx}`) from the start of the status text
+fn decode_altitude(comment: &mut Vec<u8>) -> Option<i32> {
+    let b = comment.get(..4)?;
+    if b[3] != b'}' || !b[..3].iter().all(|c| (b'!'..=b'{').contains(c)) {
+        return None;
+    }
+
+    let altitude = base91::decode_ascii(&b[..3])? as i32 - ALTITUDE_OFFSET;
+    comment.drain(..4);
+
+    Some(altitude)
+}
+
+fn encode_altitude<W: Write>(buf: &mut W, altitude: i32) -> Result<(), EncodeError> {
+    let value = altitude + ALTITUDE_OFFSET;
+    if !(0..91 * 91 * 91).contains(&value) {
+        return Err(EncodeError::InvalidAltitude(altitude));
+    }
+
+    base91::encode_ascii(value.into(), buf, 3)?;
+    buf.write_all(b"}")?;
+
+    Ok(())
+}
+
 enum MessageBit {
     Zero,
     CustomOne,
@@ -372,8 +650,8 @@ fn decode_latitude_digit(c: u8) -> Option<u8> {
     }
 }
 
-fn decode_callsign(c: &Callsign) -> Option<(Latitude, Precision, Message, LongOffset, LongDir)> {
-    let data = c.call().as_bytes();
+fn decode_destination(call: &str) -> Option<(Latitude, Precision, Message, LongOffset, LongDir)> {
+    let data = call.as_bytes();
     if data.len() != 6 {
         return None;
     }
@@ -404,7 +682,12 @@ fn decode_callsign(c: &Callsign) -> Option<(Latitude, Precision, Message, LongOf
     Some((lat, precision, msg, long_offset, long_dir))
 }
 
-fn decode_longitude(b: &[u8], offset: LongOffset, dir: LongDir) -> Option<Longitude> {
+fn decode_longitude(
+    b: &[u8],
+    offset: LongOffset,
+    dir: LongDir,
+    precision: Precision,
+) -> Option<Longitude> {
     if b.len() != 3 {
         return None;
     }
@@ -429,7 +712,22 @@ fn decode_longitude(b: &[u8], offset: LongOffset, dir: LongDir) -> Option<Longit
 
     let h = b[2].checked_sub(28)?;
 
-    Longitude::from_dmh(d.into(), m.into(), h.into(), dir == LongDir::East)
+    let (d, m, h) = blank_longitude_digits(d.into(), m.into(), h.into(), precision);
+
+    Longitude::from_dmh(d, m, h, dir == LongDir::East)
+}
+
+// The longitude is as ambiguous as the latitude,
+// so the digits that are blanked out in the latitude are ignored
+fn blank_longitude_digits(d: u32, m: u32, h: u32, precision: Precision) -> (u32, u32, u32) {
+    match precision.num_digits() {
+        0 => (d, m, h),
+        1 => (d, m, h - h % 10),
+        2 => (d, m, 0),
+        3 => (d, m - m % 10, 0),
+        4 => (d, 0, 0),
+        _ => (d - d % 10, 0, 0),
+    }
 }
 
 fn decode_speed_and_course(b: &[u8]) -> Option<(Speed, Course)> {
@@ -507,6 +805,7 @@ fn encode_bit_5(lat_digit: u8, long_dir: LongDir) -> u8 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use DaoPrecision;
 
     #[test]
     fn course_from_u32() {
@@ -552,8 +851,7 @@ mod tests {
 
     #[test]
     fn decode_dest_test() {
-        let (latitude, precision, message, offset, dir) =
-            decode_callsign(&Callsign::new_no_ssid("S32U6T")).unwrap();
+        let (latitude, precision, message, offset, dir) = decode_destination("S32U6T").unwrap();
 
         assert_eq!(Latitude::new(33.42733333333333).unwrap(), latitude);
         assert_eq!(Precision::HundredthMinute, precision);
@@ -580,7 +878,13 @@ mod tests {
                 course: Course::new(251).unwrap(),
                 symbol_table: b'/',
                 symbol_code: b'j',
+                device: None,
+                altitude_meters: None,
+                altitude: None,
                 comment: b"Hello world!".to_vec(),
+                telemetry: None,
+                dao: None,
+                comment_offsets: CommentOffsets::default(),
                 current: true
             },
             data
@@ -594,6 +898,98 @@ mod tests {
         assert_eq!(to, data.encode_destination());
     }
 
+    #[test]
+    fn decode_comment_telemetry() {
+        let information = &br#"(_fn"Oj/Hello|ss1122334455#a|"#[..];
+        let to = Callsign::new_no_ssid("PPPPPP");
+
+        let data = AprsMicE::decode(information, to, true).unwrap();
+
+        assert_eq!(data.comment, b"Hello");
+        assert_eq!(data.telemetry.as_ref().unwrap().sequence, 7544);
+
+        let mut re_encoded = vec![];
+        data.encode(&mut re_encoded).unwrap();
+        assert_eq!(information, &re_encoded[1..]);
+    }
+
+    #[test]
+    fn decode_status_text() {
+        let information = &b"]Q\x1cl|ok/'\"4<}Nick - Monitoring IRG|!\"&7'M|!wTD!|3"[..];
+        let to = Callsign::new_no_ssid("PPPPPP");
+
+        let data = AprsMicE::decode(information, to, true).unwrap();
+
+        assert_eq!(data.device, Some(Device::ByonicsTinyTrak3));
+        assert_eq!(data.altitude_meters, Some(37));
+        assert_eq!(data.comment, b"Nick - Monitoring IRG");
+        assert_eq!(data.telemetry.as_ref().map(|t| t.sequence), Some(1));
+        assert_eq!(
+            data.dao,
+            Some(Dao {
+                datum: 'W',
+                precision: DaoPrecision::Base91,
+                steps: Some((51, 35)),
+            })
+        );
+
+        let mut re_encoded = vec![];
+        data.encode(&mut re_encoded).unwrap();
+        assert_eq!(information, &re_encoded[1..]);
+    }
+
+    #[test]
+    fn decode_devices() {
+        let devices = vec![
+            (&b">"[..], Device::KenwoodThD7a, &b""[..]),
+            (b">Hello=", Device::KenwoodThD72, b"Hello"),
+            (b"]Hello", Device::KenwoodTmD700, b"Hello"),
+            (b"]=", Device::KenwoodTmD710, b""),
+            (b"`Hello_ ", Device::YaesuVx8, b"Hello"),
+            (b"`_%", Device::YaesuFtm400dr, b""),
+            (b"`Hello(8", Device::AnytoneD878uv, b"Hello"),
+            (b"'Hello:4", Device::ScsDr7400, b"Hello"),
+            (b"`Hello", Device::Unknown(b'`'), b"Hello"),
+            (b"'_ ", Device::Unknown(b'\''), b"_ "),
+        ];
+
+        for (status, device, comment) in devices {
+            let mut information = br#"(_fn"Oj/"#.to_vec();
+            information.extend_from_slice(status);
+
+            let data =
+                AprsMicE::decode(&information, Callsign::new_no_ssid("PPPPPP"), true).unwrap();
+            assert_eq!(data.device, Some(device));
+            assert_eq!(data.comment, comment);
+
+            let mut re_encoded = vec![];
+            data.encode(&mut re_encoded).unwrap();
+            assert_eq!(information, &re_encoded[1..]);
+        }
+    }
+
+    #[test]
+    fn encode_altitude() {
+        let mut data = AprsMicE::decode(
+            &br#"(_fn"Oj/Hello"#[..],
+            Callsign::new_no_ssid("PPPPPP"),
+            true,
+        )
+        .unwrap();
+        data.device = Some(Device::KenwoodTmD710);
+        data.altitude_meters = Some(-37);
+
+        let mut buf = vec![];
+        data.encode(&mut buf).unwrap();
+        assert_eq!(&buf[9..], br#"]"3M}Hello="#);
+
+        data.altitude_meters = Some(-10_001);
+        assert!(matches!(
+            data.encode(&mut vec![]),
+            Err(EncodeError::InvalidAltitude(-10_001))
+        ));
+    }
+
     #[test]
     fn encode_destination_test() {
         let information = &br#"(_fn"Oj/Hello world!"#[..];
@@ -603,4 +999,61 @@ mod tests {
 
         assert_eq!(to, data.encode_destination());
     }
+
+    #[test]
+    fn encode_ambiguous_destination() {
+        let information = &br#"(_fn"Oj/"#[..];
+        for dest in [
+            "S32U6Z", "S32UZZ", "S32ZZZ", "S3ZZZZ", "3KLLLL", "A2LZLL", "SZZZZZ",
+        ] {
+            let to = Callsign::new_no_ssid(dest);
+            let data = AprsMicE::decode(information, to.clone(), true).unwrap();
+
+            assert_eq!(to, data.encode_destination());
+        }
+    }
+
+    #[test]
+    fn decode_ambiguous_longitude() {
+        let information = &br#"(_fn"Oj/"#[..];
+        let to = Callsign::new_no_ssid("S32ZZZ");
+
+        let data = AprsMicE::decode(information, to, true).unwrap();
+        assert_eq!(Precision::TenMinute, data.precision);
+        assert_relative_eq!(*data.latitude, 33.0 + 20.0 / 60.0);
+        assert_relative_eq!(*data.longitude, -112.0);
+
+        let mut buf = vec![];
+        data.encode(&mut buf).unwrap();
+        assert_eq!(buf, b"`(X\x1cn\"Oj/");
+    }
+
+    #[test]
+    fn message_text() {
+        assert_eq!(Some("Off Duty"), Message::M0.text());
+        assert_eq!(Some("Priority"), Message::M6.text());
+        assert_eq!(Some("Custom-3"), Message::C3.text());
+        assert_eq!(Some("Emergency"), Message::Emergency.text());
+        assert_eq!(None, Message::Unknown.text());
+
+        assert_eq!(Some(Message::M1), Message::from_text("en route"));
+        assert_eq!(Some(Message::C0), Message::from_text("Custom-0"));
+        assert_eq!(None, Message::from_text("Unknown"));
+    }
+
+    #[test]
+    fn validate_symbol() {
+        let to = Callsign::new_no_ssid("S32U6T");
+        let data = AprsMicE::decode(&br#"(_fn"Oj/"#[..], to.clone(), true).unwrap();
+        assert_eq!(data.validate(), vec![]);
+
+        let data = AprsMicE::decode(&br#"(_fn"Oj?"#[..], to, true).unwrap();
+        assert_eq!(
+            data.validate(),
+            vec![SpecViolation::InvalidSymbol {
+                table: b'?',
+                code: b'j'
+            }]
+        );
+    }
 }
@@ -10,14 +10,22 @@ use std::convert::TryFrom;
 use std::io::Write;
 
 use Callsign;
+use CommentOffsets;
+use CommentTelemetry;
 use DecodeError;
 
 use EncodeError;
+use SpecViolation;
 use Timestamp;
 
 use AprsCst;
 use Extension;
 use Position;
+use Weather;
+
+use crate::components::comment::{self, CommentTokens};
+use crate::diagnostic::{OffsetResult, SpanResult, SpannedError};
+use crate::validate;
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct AprsObject {
@@ -27,11 +35,19 @@ pub struct AprsObject {
     pub timestamp: Timestamp,
     pub position: Position,
     pub extension: Option<Extension>,
+    pub weather: Option<Weather>,
+    pub altitude: Option<i32>,
     pub comment: Vec<u8>,
+    pub telemetry: Option<CommentTelemetry>,
+    pub comment_offsets: CommentOffsets,
 }
 
 impl AprsObject {
     pub fn decode(b: &[u8], to: Callsign) -> Result<Self, DecodeError> {
+        Self::decode_spanned(b, to).map_err(DecodeError::from)
+    }
+
+    pub(crate) fn decode_spanned(b: &[u8], to: Callsign) -> Result<Self, SpannedError> {
         // format for uncompressed is
         // N Bytes      Description/Value
         // -----------------------------
@@ -55,29 +71,15 @@ impl AprsObject {
         // [13]         (compressed position data)
         // [43]         (comment)
 
-        let mut name = b
-            .get(..9)
-            .ok_or_else(|| DecodeError::InvalidObjectName(b.to_vec()))?
-            .to_vec();
-
-        crate::utils::trim_spaces_end(&mut name);
-        let live = match *b.get(9).ok_or(DecodeError::InvalidObject)? as char {
-            '*' => Ok(true),
-            ' ' => Ok(false),
-            others => Err(DecodeError::InvalidObjectLiveness(others)),
-        }?;
-        let timestamp_bytes = b
-            .get(10..17)
-            .ok_or_else(|| DecodeError::InvalidTimestamp(b.to_vec()))?;
-        let timestamp = Timestamp::try_from(timestamp_bytes)?;
-
-        let (remaining_buffer, position) = Position::decode(
-            b.get(17..)
-                .ok_or_else(|| DecodeError::InvalidTimestamp(b.to_vec()))?,
-        )?;
+        let (name, live, timestamp, rest) = decode_header(b)?;
+        let (remaining_buffer, mut position) = Position::decode(rest).offset(17)?;
 
         // decide where the comment comes from
-        let (extension, comment) = if matches!(position.cst, AprsCst::Uncompressed) {
+        let weather = remaining_buffer.and_then(|b| position.decode_weather(b));
+        let (weather, extension, mut comment) = if let Some((weather, comment)) = weather {
+            // weather stations report wind direction/speed in place of an extension
+            (Some(weather), None, comment.to_vec())
+        } else if matches!(position.cst, AprsCst::Uncompressed) {
             // opportunistically decode extensions if we can
             if let Some(comment_bytes) = remaining_buffer {
                 if let Some(ext) = comment_bytes
@@ -85,30 +87,62 @@ impl AprsObject {
                     .and_then(|ext| Extension::decode(ext).ok())
                 {
                     (
+                        None,
                         Some(ext),
                         comment_bytes.get(7..).unwrap_or_default().to_vec(),
                     )
                 } else {
-                    (None, comment_bytes.to_vec())
+                    (None, None, comment_bytes.to_vec())
                 }
             } else {
-                (None, vec![])
+                (None, None, vec![])
             }
         } else {
-            (None, remaining_buffer.unwrap_or_default().to_vec())
+            (None, None, remaining_buffer.unwrap_or_default().to_vec())
         };
 
+        let tokens = CommentTokens::extract(&mut comment, Some(&mut position));
+
         Ok(Self {
             to,
-            name,
+            name: name.to_vec(),
             live,
             timestamp,
             position,
+            altitude: tokens.altitude,
             comment,
             extension,
+            weather,
+            telemetry: tokens.telemetry,
+            comment_offsets: tokens.offsets,
         })
     }
 
+    /// Returns all violations of the APRS spec
+    pub fn validate(&self) -> Vec<SpecViolation> {
+        let mut violations = vec![];
+        if self.name.is_empty() || self.name.len() > validate::MAX_NAME_LEN {
+            violations.push(SpecViolation::InvalidNameLength(self.name.clone()));
+        }
+        if self.name.contains(&b' ') {
+            violations.push(SpecViolation::SpaceInObjectName(self.name.clone()));
+        }
+        validate::check_timestamp(Some(&self.timestamp), &mut violations);
+        if self.weather.is_none() {
+            let max = validate::max_comment_len(self.extension.is_some());
+            let len = comment::encoded_len(
+                &self.comment,
+                self.altitude,
+                self.telemetry.as_ref(),
+                &self.position.dao_token().unwrap_or_default(),
+                &self.comment_offsets,
+            );
+            validate::check_comment(len, max, &mut violations);
+        }
+
+        violations
+    }
+
     pub fn encode<W: Write>(&self, buf: &mut W) -> Result<(), EncodeError> {
         // format for uncompressed is
         // N Bytes      Description/Value
@@ -136,8 +170,8 @@ impl AprsObject {
         let mut name = self.name.clone();
         name.truncate(9);
         write!(buf, ";")?;
-        buf.write_all(&self.name)?;
-        for _ in self.name.len()..9 {
+        buf.write_all(&name)?;
+        for _ in name.len()..9 {
             buf.write_all(b" ")?; // pad out the remainder
         }
 
@@ -145,7 +179,10 @@ impl AprsObject {
         self.timestamp.encode(buf)?;
 
         // if we have extensions, we have to do an uncompressed encoding to support it
-        if let Some(ext) = &self.extension {
+        if let Some(weather) = &self.weather {
+            self.position.encode(buf)?;
+            weather.encode(buf, self.position.weather_format())?;
+        } else if let Some(ext) = &self.extension {
             self.position.encode_uncompressed(buf)?;
             ext.encode(buf)?;
         } else if matches!(self.position.cst, AprsCst::Uncompressed) {
@@ -154,12 +191,50 @@ impl AprsObject {
             self.position.encode_compressed(buf)?;
         }
 
-        buf.write_all(&self.comment)?;
+        comment::encode(
+            buf,
+            &self.comment,
+            self.altitude,
+            self.telemetry.as_ref(),
+            &self.position.dao_token()?,
+            &self.comment_offsets,
+        )?;
 
         Ok(())
     }
 }
 
+/// The name (without padding), liveness, timestamp
+/// and the remaining position and comment
+pub(crate) type Header<'a> = (&'a [u8], bool, Timestamp, &'a [u8]);
+
+pub(crate) fn decode_header(b: &[u8]) -> Result<Header<'_>, SpannedError> {
+    let name = b
+        .get(..9)
+        .ok_or_else(|| DecodeError::InvalidObjectName(b.to_vec()))
+        .span(0..b.len())?;
+    let name = &name[..name.iter().rposition(|&c| c != b' ').map_or(0, |i| i + 1)];
+
+    let live = match b.get(9) {
+        Some(b'*') => true,
+        Some(b' ') => false,
+        Some(&c) => {
+            return Err(SpannedError::new(
+                DecodeError::InvalidObjectLiveness(c as char),
+                9..10,
+            ))
+        }
+        None => return Err(SpannedError::new(DecodeError::InvalidObject, 9..9)),
+    };
+    let timestamp_bytes = b
+        .get(10..17)
+        .ok_or_else(|| DecodeError::InvalidTimestamp(b.to_vec()))
+        .span(10..b.len())?;
+    let timestamp = Timestamp::try_from(timestamp_bytes).span(10..17)?;
+
+    Ok((name, live, timestamp, &b[17..]))
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -260,6 +335,31 @@ mod tests {
         }
     }
 
+    #[test]
+    fn parse_uncompressed_weather_object() {
+        let packet = AprsPacket::decode_textual(
+            b"N0CALL>APRS:;WX       *111111z4903.50N/07201.75W_220/004g005t077Storm",
+        )
+        .unwrap();
+
+        if let AprsData::Object(o) = packet.data {
+            assert_eq!(o.extension, None);
+            assert_eq!(
+                o.weather,
+                Some(Weather {
+                    wind_direction: Some(220),
+                    wind_speed: Some(4),
+                    wind_gust: Some(5),
+                    temperature: Some(77),
+                    ..Default::default()
+                })
+            );
+            assert_eq!(o.comment, b"Storm");
+        } else {
+            panic!("Unexpected data type");
+        }
+    }
+
     #[test]
     fn decode_recode_uncompressed() {
         let textual_repr = br"N8DEU-7>APZWX,WIDE2-2:;HFEST-18H*170403z3443.55N\08635.47Wh146.940MHz T100 Huntsville Hamfest";
@@ -281,4 +381,25 @@ mod tests {
         packet.encode_textual(&mut buf).unwrap();
         assert_eq!(buf, textual_repr);
     }
+
+    #[test]
+    fn encode_truncates_long_name() {
+        let mut packet =
+            AprsPacket::decode_textual(b"N0CALL>APRS:;CAR       092345z4903.50N/07201.75W>")
+                .unwrap();
+        if let AprsData::Object(o) = &mut packet.data {
+            o.name = b"LONGOBJECTNAME".to_vec();
+            assert_eq!(
+                o.validate(),
+                vec![SpecViolation::InvalidNameLength(b"LONGOBJECTNAME".to_vec())]
+            );
+        }
+
+        let mut buf = vec![];
+        packet.encode_textual(&mut buf).unwrap();
+        assert_eq!(
+            buf,
+            &b"N0CALL>APRS:;LONGOBJEC 092345z4903.50N/07201.75W>"[..]
+        );
+    }
 }
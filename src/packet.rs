@@ -1,16 +1,39 @@
 use std::borrow::Cow;
 use std::io::Write;
+use std::ops::Range;
 
 use callsign::CallsignField;
+use third_party;
+use validate;
+use AprsCapabilities;
+use AprsCompressedCs;
+use AprsCst;
+use AprsGrid;
 use AprsMessage;
 use AprsMicE;
 use AprsPosition;
+use AprsQuery;
+use AprsRawGps;
 use AprsStatus;
+use AprsTelemetry;
+use AprsTestData;
+use AprsThirdParty;
+use AprsUnknown;
+use AprsUserDefined;
+use AprsWeather;
 use Callsign;
 use DecodeError;
+use DecodeOptions;
+use DecodeWarning;
+use DecodedPacket;
+use Diagnostic;
 use EncodeError;
+use EncodeOptions;
+use Field;
+use SpecViolation;
 use Via;
 
+use crate::diagnostic::{OffsetResult, SpanResult, SpannedError};
 use crate::item::AprsItem;
 use crate::object::AprsObject;
 
@@ -23,33 +46,122 @@ pub struct AprsPacket {
 
 impl AprsPacket {
     pub fn decode_textual(s: &[u8]) -> Result<Self, DecodeError> {
+        Self::decode_textual_with(s, &mut vec![], AprsData::decode).map_err(DecodeError::from)
+    }
+
+    /// Like `decode_textual`, but only fails if the header is invalid.
+    /// If the information field can't be decoded, the packet contains
+    /// `AprsData::Invalid` with the raw bytes and the error.
+    pub fn decode_textual_partial(s: &[u8]) -> Result<Self, DecodeError> {
+        Self::decode_textual_with(s, &mut vec![], |body, to, recovered| {
+            Ok(match AprsData::decode(body, to.clone(), recovered) {
+                Ok(data) => data,
+                Err(e) => AprsData::Invalid {
+                    to,
+                    raw: body.to_vec(),
+                    error: e.error,
+                },
+            })
+        })
+        .map_err(DecodeError::from)
+    }
+
+    /// Like `decode_textual`, but the error points at the offending bytes
+    /// and can be rendered for humans with `Diagnostic::render`.
+    pub fn decode_textual_diagnostic(s: &[u8]) -> Result<Self, Diagnostic> {
+        Self::decode_textual_with(s, &mut vec![], AprsData::decode).map_err(Diagnostic::new)
+    }
+
+    /// Decodes a packet and checks it for violations of the APRS spec.
+    /// Lenient decoding returns them as warnings, together with data
+    /// that couldn't be decoded and is kept as `AprsData::Invalid`
+    /// or `AprsData::Unknown`. Strict decoding fails with `DecodeError::NonCompliant`.
+    pub fn decode_textual_with_options(
+        s: &[u8],
+        options: &DecodeOptions,
+    ) -> Result<DecodedPacket, DecodeError> {
+        let mut recovered = vec![];
+        let packet = Self::decode_textual_with(s, &mut recovered, AprsData::decode)?;
+
+        // the header ends at the first ':', like in `decode_textual_with`
+        let header_len = s.iter().position(|x| *x == b':').unwrap_or(s.len());
+        let violations = |violations: Vec<SpecViolation>, span: Range<usize>| {
+            violations
+                .into_iter()
+                .map(move |violation| DecodeWarning::NonCompliant {
+                    violation,
+                    span: span.clone(),
+                })
+        };
+
+        let mut warnings: Vec<_> = violations(packet.validate_header(), 0..header_len).collect();
+        warnings.extend(recovered.into_iter().map(DecodeWarning::from));
+        warnings.extend(violations(
+            packet.data.validate(),
+            (header_len + 1)..s.len(),
+        ));
+
+        if options.strict && !warnings.is_empty() {
+            return Err(DecodeError::NonCompliant(warnings));
+        }
+
+        Ok(DecodedPacket { packet, warnings })
+    }
+
+    /// Decodes the header and passes the information field to `decode_data`.
+    /// Errors that `decode_data` recovered from are added to `recovered`.
+    pub(crate) fn decode_textual_with<F>(
+        s: &[u8],
+        recovered: &mut Vec<SpannedError>,
+        decode_data: F,
+    ) -> Result<Self, SpannedError>
+    where
+        F: FnOnce(&[u8], Callsign, &mut Vec<SpannedError>) -> Result<AprsData, SpannedError>,
+    {
         let header_delimiter = s
             .iter()
             .position(|x| *x == b':')
-            .ok_or_else(|| DecodeError::InvalidPacket(s.to_owned()))?;
+            .ok_or_else(|| DecodeError::InvalidPacket(s.to_owned()))
+            .span(s.len()..s.len())?;
         let (header, rest) = s.split_at(header_delimiter);
         let body = &rest[1..];
 
         let from_delimiter = header
             .iter()
             .position(|x| *x == b'>')
-            .ok_or_else(|| DecodeError::InvalidPacket(s.to_owned()))?;
+            .ok_or_else(|| DecodeError::InvalidPacket(s.to_owned()))
+            .span(0..header_delimiter)?;
         let (from, rest) = header.split_at(from_delimiter);
         let (from, _) = Callsign::decode_textual(from)
-            .ok_or_else(|| DecodeError::InvalidCallsign(from.to_owned()))?;
+            .ok_or_else(|| DecodeError::InvalidCallsign(from.to_owned()))
+            .span(0..from_delimiter)
+            .map_err(|e| e.with_field(Field::Source))?;
 
         let to_and_via = &rest[1..];
         let mut to_and_via = to_and_via.split(|x| *x == b',');
 
+        // the start of the next entry of the path
+        let mut start = from_delimiter + 1;
+
         let to = to_and_via
             .next()
-            .ok_or_else(|| DecodeError::InvalidPacket(s.to_owned()))?;
+            .ok_or_else(|| DecodeError::InvalidPacket(s.to_owned()))
+            .span(0..header_delimiter)?;
+        let to_span = start..(start + to.len());
         let (to, _) = Callsign::decode_textual(to)
-            .ok_or_else(|| DecodeError::InvalidCallsign(to.to_owned()))?;
+            .ok_or_else(|| DecodeError::InvalidCallsign(to.to_owned()))
+            .span(to_span.clone())
+            .map_err(|e| e.with_field(Field::Destination))?;
+        start = to_span.end + 1;
 
         let mut via = vec![];
         for v in to_and_via {
-            via.push(Via::decode_textual(v).ok_or_else(|| DecodeError::InvalidVia(v.to_owned()))?);
+            via.push(
+                Via::decode_textual(v)
+                    .ok_or_else(|| DecodeError::InvalidVia(v.to_owned()))
+                    .span(start..(start + v.len()))?,
+            );
+            start += v.len() + 1;
         }
 
         // if our Via path looks like A,B,C*,D,E
@@ -65,7 +177,17 @@ impl AprsPacket {
             }
         }
 
-        let data = AprsData::decode(body, to)?;
+        let mut body_recovered = vec![];
+        let data = decode_data(body, to, &mut body_recovered).map_err(|e| match e.field {
+            // the Mic-E destination is part of the header
+            Field::Destination => SpannedError { span: to_span, ..e },
+            _ => e.offset(header_delimiter + 1),
+        })?;
+        recovered.extend(
+            body_recovered
+                .into_iter()
+                .map(|e| e.offset(header_delimiter + 1)),
+        );
 
         Ok(AprsPacket { from, via, data })
     }
@@ -74,8 +196,67 @@ impl AprsPacket {
         self.data.to()
     }
 
+    /// Returns all violations of the APRS spec,
+    /// including those of the information field
+    pub fn validate(&self) -> Vec<SpecViolation> {
+        let mut violations = self.validate_header();
+        violations.extend(self.data.validate());
+
+        violations
+    }
+
+    fn validate_header(&self) -> Vec<SpecViolation> {
+        let mut violations = vec![];
+        validate::check_callsign(&self.from, &mut violations);
+        validate::check_callsign(&self.data.dest_field(), &mut violations);
+        // the entries after a q construct are APRS-IS server names,
+        // which don't follow the AX.25 limits
+        for v in self
+            .via
+            .iter()
+            .take_while(|v| !matches!(v, Via::QConstruct(_)))
+        {
+            if let Via::Callsign(c, _) = v {
+                validate::check_callsign(c, &mut violations);
+            }
+        }
+
+        violations
+    }
+
     /// Used for encoding a packet into ASCII for transmission on the internet (APRS-IS)
     pub fn encode_textual<W: Write>(&self, buf: &mut W) -> Result<(), EncodeError> {
+        self.encode_textual_header(buf)?;
+        write!(buf, ":")?;
+        self.data.encode(buf)?;
+
+        Ok(())
+    }
+
+    /// Like `encode_textual`, but strict encoding refuses packets
+    /// that violate the APRS spec with `EncodeError::NonCompliant`.
+    pub fn encode_textual_with_options<W: Write>(
+        &self,
+        buf: &mut W,
+        options: &EncodeOptions,
+    ) -> Result<(), EncodeError> {
+        self.check_compliance(options)?;
+        self.encode_textual(buf)
+    }
+
+    fn check_compliance(&self, options: &EncodeOptions) -> Result<(), EncodeError> {
+        if options.strict {
+            let violations = self.validate();
+            if !violations.is_empty() {
+                return Err(EncodeError::NonCompliant(violations));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Encodes everything before the ':'
+    pub(crate) fn encode_textual_header<W: Write>(&self, buf: &mut W) -> Result<(), EncodeError> {
         // logic to clear extraneous asterisks
         let mut via = self.via.clone();
         let mut heard = false;
@@ -96,8 +277,6 @@ impl AprsPacket {
             write!(buf, ",")?;
             v.encode_textual(buf)?;
         }
-        write!(buf, ":")?;
-        self.data.encode(buf)?;
 
         Ok(())
     }
@@ -144,11 +323,22 @@ impl AprsPacket {
         i += 2;
 
         // remainder is the information field
-        let data = AprsData::decode(data.get(i..).unwrap_or(&[]), to)?;
+        let data = AprsData::decode(data.get(i..).unwrap_or(&[]), to, &mut vec![])?;
 
         Ok(Self { data, from, via })
     }
 
+    /// Like `encode_ax25`, but strict encoding refuses packets
+    /// that violate the APRS spec with `EncodeError::NonCompliant`.
+    pub fn encode_ax25_with_options<W: Write>(
+        &self,
+        buf: &mut W,
+        options: &EncodeOptions,
+    ) -> Result<(), EncodeError> {
+        self.check_compliance(options)?;
+        self.encode_ax25(buf)
+    }
+
     /// Used for encoding a packet for transmission on the air (via KISS or otherwise)
     pub fn encode_ax25<W: Write>(&self, buf: &mut W) -> Result<(), EncodeError> {
         // Destination address
@@ -183,6 +373,8 @@ impl AprsPacket {
     }
 }
 
+const FEET_PER_METER: f64 = 3.28084;
+
 #[derive(PartialEq, Debug, Clone)]
 pub enum AprsData {
     Position(AprsPosition),
@@ -191,7 +383,25 @@ pub enum AprsData {
     MicE(AprsMicE),
     Object(AprsObject),
     Item(AprsItem),
-    Unknown(Callsign),
+    Weather(AprsWeather),
+    Telemetry(AprsTelemetry),
+    Query(AprsQuery),
+    Capabilities(AprsCapabilities),
+    ThirdParty(AprsThirdParty),
+    RawGps(AprsRawGps),
+    Grid(AprsGrid),
+    UserDefined(AprsUserDefined),
+    TestData(AprsTestData),
+    Unknown(AprsUnknown),
+    /// An information field that couldn't be decoded.
+    /// `AprsPacket::decode_textual_partial` returns it for every data type,
+    /// `AprsPacket::decode_textual` only for data types that used to be
+    /// decoded as `Unknown`, e.g. weather reports.
+    Invalid {
+        to: Callsign,
+        raw: Vec<u8>,
+        error: DecodeError,
+    },
 }
 
 impl AprsData {
@@ -203,36 +413,223 @@ impl AprsData {
             AprsData::MicE(_) => None,
             AprsData::Object(_) => None,
             AprsData::Item(_) => None,
-            AprsData::Unknown(to) => Some(to),
+            AprsData::Weather(w) => Some(&w.to),
+            AprsData::Telemetry(t) => Some(&t.to),
+            AprsData::Query(q) => Some(&q.to),
+            AprsData::Capabilities(c) => Some(&c.to),
+            AprsData::ThirdParty(t) => Some(&t.to),
+            AprsData::RawGps(r) => Some(&r.to),
+            AprsData::Grid(g) => Some(&g.to),
+            AprsData::UserDefined(u) => Some(&u.to),
+            AprsData::TestData(t) => Some(&t.to),
+            AprsData::Unknown(u) => Some(&u.to),
+            AprsData::Invalid { to, .. } => Some(to),
         }
     }
 
-    fn dest_field(&self) -> Cow<Callsign> {
+    /// The altitude in feet, if the packet reports one.
+    /// Falls back to the altitude of a compressed position if the comment
+    /// doesn't contain an altitude.
+    pub fn altitude(&self) -> Option<i32> {
+        let (altitude, position) = match self {
+            AprsData::Position(p) => (p.altitude, Some(&p.position)),
+            AprsData::MicE(m) => (
+                m.altitude.or_else(|| {
+                    m.altitude_meters
+                        .map(|a| (f64::from(a) * FEET_PER_METER).round() as i32)
+                }),
+                None,
+            ),
+            AprsData::Object(o) => (o.altitude, Some(&o.position)),
+            AprsData::Item(i) => (i.altitude, Some(&i.position)),
+            _ => return None,
+        };
+
+        altitude.or_else(|| match position?.cst {
+            AprsCst::CompressedSome {
+                cs: AprsCompressedCs::Altitude(a),
+                ..
+            } => Some(a.altitude_feet().round() as i32),
+            _ => None,
+        })
+    }
+
+    /// Returns all violations of the APRS spec by the information field
+    pub fn validate(&self) -> Vec<SpecViolation> {
+        match self {
+            AprsData::Position(p) => p.validate(),
+            AprsData::Message(m) => m.validate(),
+            AprsData::Status(s) => s.validate(),
+            AprsData::Object(o) => o.validate(),
+            AprsData::Item(i) => i.validate(),
+            AprsData::Weather(w) => w.validate(),
+            AprsData::ThirdParty(t) => t.validate(),
+            AprsData::RawGps(r) => r.validate(),
+            AprsData::MicE(m) => m.validate(),
+            AprsData::Telemetry(t) => t.validate(),
+            AprsData::Unknown(_)
+            | AprsData::Invalid { .. }
+            | AprsData::Query(_)
+            | AprsData::Capabilities(_)
+            | AprsData::Grid(_)
+            | AprsData::UserDefined(_)
+            | AprsData::TestData(_) => vec![],
+        }
+    }
+
+    pub(crate) fn dest_field(&self) -> Cow<'_, Callsign> {
         match self {
             AprsData::Position(p) => Cow::Borrowed(&p.to),
             AprsData::Message(m) => Cow::Borrowed(&m.to),
             AprsData::Status(s) => Cow::Borrowed(&s.to),
             AprsData::MicE(m) => Cow::Owned(m.encode_destination()),
-            AprsData::Unknown(to) => Cow::Borrowed(to),
+            AprsData::Unknown(u) => Cow::Borrowed(&u.to),
+            AprsData::Invalid { to, .. } => Cow::Borrowed(to),
             AprsData::Object(o) => Cow::Borrowed(&o.to),
             AprsData::Item(i) => Cow::Borrowed(&i.to),
+            AprsData::Weather(w) => Cow::Borrowed(&w.to),
+            AprsData::Telemetry(t) => Cow::Borrowed(&t.to),
+            AprsData::Query(q) => Cow::Borrowed(&q.to),
+            AprsData::Capabilities(c) => Cow::Borrowed(&c.to),
+            AprsData::ThirdParty(t) => Cow::Borrowed(&t.to),
+            AprsData::RawGps(r) => Cow::Borrowed(&r.to),
+            AprsData::Grid(g) => Cow::Borrowed(&g.to),
+            AprsData::UserDefined(u) => Cow::Borrowed(&u.to),
+            AprsData::TestData(t) => Cow::Borrowed(&t.to),
         }
     }
 
-    fn decode(s: &[u8], to: Callsign) -> Result<Self, DecodeError> {
+    fn decode(
+        s: &[u8],
+        to: Callsign,
+        recovered: &mut Vec<SpannedError>,
+    ) -> Result<Self, SpannedError> {
+        Self::decode_nested(s, to, 0, recovered)
+    }
+
+    // `depth` is the number of third-party headers around `s`,
+    // `recovered` collects the errors of data that was kept as `Invalid` or `Unknown`
+    pub(crate) fn decode_nested(
+        s: &[u8],
+        to: Callsign,
+        depth: usize,
+        recovered: &mut Vec<SpannedError>,
+    ) -> Result<Self, SpannedError> {
+        let rest = 1..s.len();
+
         Ok(match *s.first().unwrap_or(&0) {
-            b':' => AprsData::Message(AprsMessage::decode(&s[1..], to)?),
-            b'!' | b'/' | b'=' | b'@' => AprsData::Position(AprsPosition::decode(s, to)?),
-            b'>' => AprsData::Status(AprsStatus::decode(&s[1..], to)?),
-            0x1c | b'`' => AprsData::MicE(AprsMicE::decode(&s[1..], to, true)?),
-            0x1d | b'\'' => AprsData::MicE(AprsMicE::decode(&s[1..], to, false)?),
-            b';' => AprsData::Object(AprsObject::decode(&s[1..], to)?),
-            b')' => AprsData::Item(AprsItem::decode(&s[1..], to)?),
-            _ => AprsData::Unknown(to),
+            b':' => AprsData::Message(AprsMessage::decode_spanned(&s[1..], to).offset(1)?),
+            b'!' | b'/' | b'=' | b'@' => AprsData::Position(AprsPosition::decode_spanned(s, to)?),
+            b'>' => AprsData::Status(AprsStatus::decode(&s[1..], to).span(rest)?),
+            0x1c | b'`' => AprsData::MicE(AprsMicE::decode_spanned(&s[1..], to, true).offset(1)?),
+            0x1d | b'\'' => AprsData::MicE(AprsMicE::decode_spanned(&s[1..], to, false).offset(1)?),
+            b';' => AprsData::Object(AprsObject::decode_spanned(&s[1..], to).offset(1)?),
+            b')' => AprsData::Item(AprsItem::decode_spanned(&s[1..], to).offset(1)?),
+            b'_' => Self::decode_or_invalid(s, to, recovered, |to, _| {
+                AprsWeather::decode(&s[1..], to)
+                    .map(AprsData::Weather)
+                    .span(rest)
+            }),
+            b'T' if s.get(1) == Some(&b'#') => {
+                Self::decode_or_invalid(s, to, recovered, |to, _| {
+                    AprsTelemetry::decode(&s[2..], to)
+                        .map(AprsData::Telemetry)
+                        .span(2..s.len())
+                })
+            }
+            b'?' => AprsData::Query(AprsQuery::decode(&s[1..], to).span(rest)?),
+            b'<' => AprsData::Capabilities(AprsCapabilities::decode(&s[1..], to).span(rest)?),
+            // invalid or too deeply nested packets are kept as they are
+            b'}' if depth < third_party::MAX_DEPTH => {
+                Self::decode_or_unknown(s, to, recovered, |to, recovered| {
+                    let mut inner = vec![];
+                    let data = AprsThirdParty::decode_nested(&s[1..], to, depth + 1, &mut inner)
+                        .map(AprsData::ThirdParty);
+                    recovered.extend(inner.into_iter().map(|e| e.offset(1)));
+
+                    data.offset(1)
+                })
+            }
+            // e.g. a '[' without the closing ']' is kept as text
+            b'[' => Self::decode_or_unknown(s, to, recovered, |to, _| {
+                AprsGrid::decode(&s[1..], to).map(AprsData::Grid).span(rest)
+            }),
+            // `$ULTW` is used by Ultimeter weather stations, not NMEA
+            b'$' if !s.starts_with(b"$ULTW") => {
+                Self::decode_or_invalid(s, to, recovered, |to, _| {
+                    AprsRawGps::decode(&s[1..], to)
+                        .map(AprsData::RawGps)
+                        .span(rest)
+                })
+            }
+            // too short to contain a user ID and packet type
+            b'{' if s.len() < 3 => AprsData::Unknown(AprsUnknown::decode(s, to)),
+            b'{' => AprsData::UserDefined(AprsUserDefined::decode(&s[1..], to).span(rest)?),
+            b',' => AprsData::TestData(AprsTestData::decode(&s[1..], to)),
+            _ => AprsData::Unknown(AprsUnknown::decode(s, to)),
+        })
+    }
+
+    // Data types that used to be decoded as `Unknown` don't fail the whole packet.
+    // If they can't be decoded, the information field is kept as `Invalid`.
+    fn decode_or_invalid<F>(
+        s: &[u8],
+        to: Callsign,
+        recovered: &mut Vec<SpannedError>,
+        decode: F,
+    ) -> Self
+    where
+        F: FnOnce(Callsign, &mut Vec<SpannedError>) -> Result<Self, SpannedError>,
+    {
+        Self::recover(to.clone(), recovered, decode, |e| AprsData::Invalid {
+            to,
+            raw: s.to_vec(),
+            error: e.error.clone(),
+        })
+    }
+
+    // Like `decode_or_invalid`, but the information field is kept as `Unknown`
+    fn decode_or_unknown<F>(
+        s: &[u8],
+        to: Callsign,
+        recovered: &mut Vec<SpannedError>,
+        decode: F,
+    ) -> Self
+    where
+        F: FnOnce(Callsign, &mut Vec<SpannedError>) -> Result<Self, SpannedError>,
+    {
+        Self::recover(to.clone(), recovered, decode, |_| {
+            AprsData::Unknown(AprsUnknown::decode(s, to))
         })
     }
 
-    fn encode<W: Write>(&self, buf: &mut W) -> Result<(), EncodeError> {
+    // Errors recovered from by data that failed as a whole are dropped,
+    // only the error that made it fail is kept
+    fn recover<F, G>(
+        to: Callsign,
+        recovered: &mut Vec<SpannedError>,
+        decode: F,
+        fallback: G,
+    ) -> Self
+    where
+        F: FnOnce(Callsign, &mut Vec<SpannedError>) -> Result<Self, SpannedError>,
+        G: FnOnce(&SpannedError) -> Self,
+    {
+        let mut inner = vec![];
+        match decode(to, &mut inner) {
+            Ok(data) => {
+                recovered.append(&mut inner);
+                data
+            }
+            Err(e) => {
+                let data = fallback(&e);
+                recovered.push(e);
+                data
+            }
+        }
+    }
+
+    pub(crate) fn encode<W: Write>(&self, buf: &mut W) -> Result<(), EncodeError> {
         match self {
             Self::Position(p) => {
                 p.encode(buf)?;
@@ -248,7 +645,17 @@ impl AprsData {
             }
             Self::Object(o) => o.encode(buf)?,
             Self::Item(i) => i.encode(buf)?,
-            Self::Unknown(_) => return Err(EncodeError::InvalidData),
+            Self::Weather(w) => w.encode(buf)?,
+            Self::Telemetry(t) => t.encode(buf)?,
+            Self::Query(q) => q.encode(buf)?,
+            Self::Capabilities(c) => c.encode(buf)?,
+            Self::ThirdParty(t) => t.encode(buf)?,
+            Self::RawGps(r) => r.encode(buf)?,
+            Self::Grid(g) => g.encode(buf)?,
+            Self::UserDefined(u) => u.encode(buf)?,
+            Self::TestData(t) => t.encode(buf)?,
+            Self::Unknown(u) => u.encode(buf)?,
+            Self::Invalid { raw, .. } => buf.write_all(raw)?,
         }
 
         Ok(())
@@ -260,12 +667,16 @@ mod tests {
     use crate::components::position::Position;
 
     use super::*;
-    use mic_e::{Course, Message, Speed};
+    use mic_e::{Course, Device, Message, Speed};
     use AprsCst;
+    use CommentOffsets;
+    use Dao;
+    use DaoPrecision;
     use Latitude;
     use Longitude;
     use Precision;
     use QConstruct;
+    use TelemetrySequence;
     use Timestamp;
 
     #[test]
@@ -285,10 +696,19 @@ mod tests {
             AprsData::Position(position) => {
                 assert_eq!(position.timestamp, Some(Timestamp::HHMMSS(7, 48, 49)));
                 assert_eq!(position.position.latitude.value(), 48.36016666666667);
-                assert_eq!(position.position.longitude.value(), 12.408166666666666);
+                assert_relative_eq!(position.position.longitude.value(), 12.0 + 24.499 / 60.0);
+                assert_eq!(
+                    position.position.dao,
+                    Some(Dao {
+                        datum: 'W',
+                        precision: DaoPrecision::ThousandthMinute,
+                        steps: None,
+                    })
+                );
+                assert_eq!(position.altitude, Some(3054));
                 assert_eq!(
                     position.comment,
-                    b"322/103/A=003054 !W09! id213D17F2 -039fpm +0.0rot 2.5dB 3e -0.0kHz gps1x1"
+                    b"  id213D17F2 -039fpm +0.0rot 2.5dB 3e -0.0kHz gps1x1"
                 );
             }
             _ => panic!("Unexpected data type"),
@@ -345,6 +765,177 @@ mod tests {
         }
     }
 
+    #[test]
+    fn parse_weather() {
+        let result = AprsPacket::decode_textual(
+            &b"ICA3F2>APRS,qAS,dl4mea:_10090556c220s004g005t077r000p000P000h50b09900wRSW"[..],
+        )
+        .unwrap();
+        assert_eq!(result.to(), Some(&Callsign::new_no_ssid("APRS")));
+
+        match result.data {
+            AprsData::Weather(weather) => {
+                assert_eq!(weather.timestamp, Timestamp::MDHM(10, 9, 5, 56));
+                assert_eq!(weather.weather.wind_direction, Some(220));
+                assert_eq!(weather.weather.temperature, Some(77));
+                assert_eq!(weather.weather.barometric_pressure, Some(9900));
+                assert_eq!(weather.comment, b"wRSW");
+            }
+            _ => panic!("Unexpected data type"),
+        }
+    }
+
+    #[test]
+    fn parse_invalid_weather() {
+        let original = &b"ICA3F2>APRS:_10090556g005t077"[..];
+        let result = AprsPacket::decode_textual(original).unwrap();
+
+        assert_eq!(
+            result.data,
+            AprsData::Invalid {
+                to: Callsign::new_no_ssid("APRS"),
+                raw: b"_10090556g005t077".to_vec(),
+                error: DecodeError::InvalidWeather(b"10090556g005t077".to_vec()),
+            }
+        );
+
+        let mut buf = vec![];
+        result.encode_textual(&mut buf).unwrap();
+        assert_eq!(buf, original);
+    }
+
+    #[test]
+    fn parse_position_with_weather() {
+        let result = AprsPacket::decode_textual(
+            &b"ICA3F2>APRS,qAS,dl4mea:!4903.50N/07201.75W_220/004g005t077r000p000P000h50b09900wRSW"
+                [..],
+        )
+        .unwrap();
+
+        match result.data {
+            AprsData::Position(position) => {
+                let weather = position.weather.unwrap();
+                assert_eq!(weather.wind_direction, Some(220));
+                assert_eq!(weather.wind_speed, Some(4));
+                assert_eq!(weather.humidity, Some(50));
+                assert_eq!(position.comment, b"wRSW");
+            }
+            _ => panic!("Unexpected data type"),
+        }
+    }
+
+    #[test]
+    fn altitude() {
+        let packets = vec![
+            (
+                &b"ICA3F2>APRS:!4903.50N/07201.75W-Hello/A=-00012"[..],
+                Some(-12),
+            ),
+            (
+                b"ICA3F2>APRS:;LEADER   *092345z4903.50N/07201.75W>/A=001234",
+                Some(1234),
+            ),
+            (b"ICA3F2>APRS:)AID #2!4903.50N/07201.75WA/A=000005", Some(5)),
+            (br#"ICA3F2>PPPPPP:`(_fn"Oj//A=000100Hello"#, Some(100)),
+            // compressed altitude
+            (b"ICA3F2>APRS:=/5L!!<*e7OS]S", Some(10005)),
+            (b"ICA3F2>APRS:!4903.50N/07201.75W-Hello", None),
+            (b"ICA3F2>APRS:>Hello/A=001234", None),
+        ];
+
+        for (packet, altitude) in packets {
+            let result = AprsPacket::decode_textual(packet).unwrap();
+            assert_eq!(result.data.altitude(), altitude);
+        }
+    }
+
+    #[test]
+    fn parse_telemetry() {
+        let result = AprsPacket::decode_textual(
+            &b"ICA3F2>APRS,qAS,dl4mea:T#005,199,000,255,073,123,01101001"[..],
+        )
+        .unwrap();
+
+        match result.data {
+            AprsData::Telemetry(telemetry) => {
+                assert_eq!(telemetry.sequence, TelemetrySequence::Number(5));
+                assert_eq!(telemetry.analog.len(), 5);
+                assert_eq!(
+                    telemetry.digital,
+                    Some([false, true, true, false, true, false, false, true])
+                );
+            }
+            _ => panic!("Unexpected data type"),
+        }
+    }
+
+    #[test]
+    fn parse_invalid_telemetry() {
+        let result = AprsPacket::decode_textual(&b"ICA3F2>APRS:T#12x,001"[..]).unwrap();
+
+        assert!(matches!(
+            result.data,
+            AprsData::Invalid {
+                error: DecodeError::InvalidTelemetry(_),
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn parse_invalid_third_party() {
+        for original in [
+            &b"ICA3F2>APRS:}no header"[..],
+            b"ICA3F2>APRS:}A>B:}C>D:}E>F:>Hello",
+        ] {
+            let result = AprsPacket::decode_textual(original).unwrap();
+
+            let mut buf = vec![];
+            result.encode_textual(&mut buf).unwrap();
+            assert_eq!(buf, original);
+        }
+
+        let result = AprsPacket::decode_textual(&b"ICA3F2>APRS:}no header"[..]).unwrap();
+        assert!(matches!(result.data, AprsData::Unknown(_)));
+    }
+
+    #[test]
+    fn parse_unterminated_grid() {
+        let original = &b"ICA3F2>APRS:[IO91SX 35 miles NNW of London"[..];
+        let result = AprsPacket::decode_textual(original).unwrap();
+        assert!(matches!(result.data, AprsData::Unknown(_)));
+
+        let mut buf = vec![];
+        result.encode_textual(&mut buf).unwrap();
+        assert_eq!(buf, original);
+    }
+
+    #[test]
+    fn parse_raw_gps_without_checksum() {
+        let result =
+            AprsPacket::decode_textual(&b"ICA3F2>APRS:$GPGLL,3723.2475,N,12158.3416,W"[..])
+                .unwrap();
+        match &result.data {
+            AprsData::RawGps(r) => assert!(!r.checksum_valid()),
+            d => panic!("Unexpected data type {:?}", d),
+        }
+        assert_eq!(
+            result.validate(),
+            vec![SpecViolation::InvalidNmeaChecksum(
+                b"GPGLL,3723.2475,N,12158.3416,W".to_vec()
+            )]
+        );
+
+        let result = AprsPacket::decode_textual(&b"ICA3F2>APRS:$GPGLL,37x3.2475,N*00"[..]).unwrap();
+        assert!(matches!(
+            result.data,
+            AprsData::Invalid {
+                error: DecodeError::InvalidNmea(_),
+                ..
+            }
+        ));
+    }
+
     #[test]
     fn encode_ax25_basic() {
         let encoded_ax25 = vec![
@@ -402,7 +993,13 @@ mod tests {
                     course: Course::new(35).unwrap(),
                     symbol_table: b'/',
                     symbol_code: b'>',
-                    comment: br#">"4z}="#.to_vec(),
+                    device: Some(Device::KenwoodThD72),
+                    altitude_meters: Some(99),
+                    altitude: None,
+                    comment: vec![],
+                    telemetry: None,
+                    dao: None,
+                    comment_offsets: CommentOffsets::default(),
                     current: true
                 })
             },
@@ -426,9 +1023,14 @@ mod tests {
                     symbol_table: '/',
                     symbol_code: 'c',
                     cst: AprsCst::Uncompressed,
+                    dao: None,
                 },
-
+                extension: None,
+                weather: None,
+                altitude: None,
                 comment: b"Hello world".to_vec(),
+                telemetry: None,
+                comment_offsets: CommentOffsets::default(),
             }),
         };
 
@@ -456,8 +1058,14 @@ mod tests {
                     symbol_table: '/',
                     symbol_code: 'c',
                     cst: AprsCst::Uncompressed,
+                    dao: None,
                 },
+                extension: None,
+                weather: None,
+                altitude: None,
                 comment: b"Hello world".to_vec(),
+                telemetry: None,
+                comment_offsets: CommentOffsets::default(),
             }),
         };
 
@@ -483,6 +1091,28 @@ mod tests {
             r"ICA3F2>APRS,qAS,DL4MEA:>184050hAlso with HMS format...",
             "VE9MP-12>T5RX8P,VE9GFI-2,WIDE1*,WIDE2-1,qAR,VE9QLE-10:`]Q\x1cl|ok/'\"4<}Nick - Monitoring IRG|!\"&7'M|!wTD!|3",
             r#"DF1CHB-9>UQ0RT6,ARISS,APRSAT,WIDE1-1,qAU,DB0KOE-1:`|9g\"H?>/>\"4z}="#,
+            r"ICA3F2>APRS,qAS,DL4MEA:_10090556c220s004g005t077r000p000P000h50b09900wRSW",
+            r"ICA3F2>APRS,qAS,DL4MEA:!4903.50N/07201.75W_220/004g005t077r000p000P000h50b09900wRSW",
+            r"ICA3F2>APRS,qAS,DL4MEA:=/5L!!<*e7_7P[g005t077r000p000P000h50b09900wRSW",
+            r"ICA3F2>APRS,qAS,DL4MEA:;WX       *111111z4903.50N/07201.75W_220/004g005t077",
+            r"ICA3F2>APRS,qAS,DL4MEA:;LEADER   *092345z4903.50N/07201.75W>Hello/A=001234",
+            r"ICA3F2>APRS,qAS,DL4MEA:)AID #2!4903.50N/07201.75WAHi /A=000005 there",
+            r#"ICA3F2>PPPPPP,qAS,DL4MEA:`(_fn"Oj/Hello/A=000100"#,
+            r"ICA3F2>APRS,qAS,DL4MEA:T#005,199,000,255,073,123,01101001",
+            r"ICA3F2>APRS,qAS,DL4MEA:T#MIC199,000,255,073,123,01101001",
+            r"ICA3F2>APRS,qAS,DL4MEA:?APRS?",
+            r"ICA3F2>APRS,qAS,DL4MEA:?IGATE? 34.02,-117.15,0200",
+            r"ICA3F2>APRS,qAS,DL4MEA:<IGATE,MSG_CNT=43,LOC_CNT=14",
+            r"ICA3F2>GPSLJ,qAS,DL4MEA:$GPRMC,063909,A,3349.4302,N,11700.3721,W,43.022,89.3,291099,13.6,E*52",
+            r"ICA3F2>APRS,qAS,DL4MEA:[IO91SX] 35 miles NNW of London",
+            r"ICA3F2>APRS,qAS,DL4MEA:>IO91SX/G Hello",
+            r"ICA3F2>APRS,qAS,DL4MEA:{Q1qwerty",
+            r"ICA3F2>APRS,qAS,DL4MEA:,Test data",
+            r"ICA3F2>APRS,qAS,DL4MEA:$ULTW0000000001FF000427C70002CCD30001026E003A050F00040000",
+            r"ICA3F2>APRS,qAS,DL4MEA:#Not supported",
+            r"ICA3F2>APRS,qAS,DL4MEA:",
+            r"WB2OSZ-3>APRS,WIDE2-1:}WB2OSZ-4>APN383,TCPIP,WB2OSZ-3*:!4237.14N/07120.83W#",
+            "WB2OSZ-3>APRS:}VE9MP-12>T5RX8P,TCPIP,WB2OSZ-3*:`]Q\x1cl|ok/'",
         ];
 
         for v in valids {
@@ -585,4 +1215,85 @@ mod tests {
         decoded.encode_textual(&mut buf).unwrap();
         assert_eq!(buf, original);
     }
+
+    #[test]
+    fn decode_partial() {
+        let original = b"N0CALL>APRS,WIDE1-1:!4903.50X/07201.75W-Hello";
+
+        assert_eq!(
+            AprsPacket::decode_textual(original),
+            Err(DecodeError::InvalidLatitude(b"4903.50X".to_vec()))
+        );
+
+        let packet = AprsPacket::decode_textual_partial(original).unwrap();
+        assert_eq!(packet.from, Callsign::new_no_ssid("N0CALL"));
+        assert_eq!(
+            packet.via,
+            vec![Via::Callsign(Callsign::new_with_ssid("WIDE1", "1"), false)]
+        );
+        assert_eq!(
+            packet.data,
+            AprsData::Invalid {
+                to: Callsign::new_no_ssid("APRS"),
+                raw: b"!4903.50X/07201.75W-Hello".to_vec(),
+                error: DecodeError::InvalidLatitude(b"4903.50X".to_vec()),
+            }
+        );
+
+        let mut buf = vec![];
+        packet.encode_textual(&mut buf).unwrap();
+        assert_eq!(buf, original);
+    }
+
+    #[test]
+    fn decode_partial_valid_and_invalid_header() {
+        let original = b"N0CALL>APRS:>Hello";
+        assert_eq!(
+            AprsPacket::decode_textual_partial(original),
+            AprsPacket::decode_textual(original)
+        );
+
+        assert_eq!(
+            AprsPacket::decode_textual_partial(b"N0CALL:>Hello"),
+            Err(DecodeError::InvalidPacket(b"N0CALL:>Hello".to_vec()))
+        );
+    }
+
+    #[test]
+    fn encode_strict() {
+        let mut packet =
+            AprsPacket::decode_textual(b"N0CALL>APRS:!4903.50N/07201.75W-Hello").unwrap();
+
+        let mut buf = vec![];
+        packet
+            .encode_textual_with_options(&mut buf, &EncodeOptions::strict())
+            .unwrap();
+        assert_eq!(buf, b"N0CALL>APRS:!4903.50N/07201.75W-Hello");
+
+        packet.from = Callsign::new_no_ssid("n0call");
+        if let AprsData::Position(p) = &mut packet.data {
+            p.comment = vec![b'x'; 44];
+        }
+        let expected = vec![
+            SpecViolation::LowercaseCallsign(Callsign::new_no_ssid("n0call")),
+            SpecViolation::CommentTooLong { len: 44, max: 43 },
+        ];
+        assert_eq!(packet.validate(), expected);
+
+        let mut buf = vec![];
+        match packet.encode_textual_with_options(&mut buf, &EncodeOptions::strict()) {
+            Err(EncodeError::NonCompliant(violations)) => assert_eq!(violations, expected),
+            e => panic!("Unexpected result: {:?}", e),
+        }
+        match packet.encode_ax25_with_options(&mut buf, &EncodeOptions::strict()) {
+            Err(EncodeError::NonCompliant(violations)) => assert_eq!(violations, expected),
+            e => panic!("Unexpected result: {:?}", e),
+        }
+        assert!(buf.is_empty());
+
+        packet
+            .encode_textual_with_options(&mut buf, &EncodeOptions::lenient())
+            .unwrap();
+        assert!(!buf.is_empty());
+    }
 }
@@ -0,0 +1,494 @@
+//! Borrowed packets, which reference the input instead of copying it.
+//! They only decode what is needed to filter packets cheaply:
+//! the header, positions, Mic-E reports, objects, items, messages
+//! and statuses. Everything else is available as the raw information field.
+//! Use `AprsPacketRef::to_owned` for a fully decoded `AprsPacket`.
+
+use std::convert::TryFrom;
+use std::fmt::{Display, Formatter};
+
+use item;
+use mic_e::{Course, Message, MicEFixed, Speed};
+use object;
+use AprsPacket;
+use Callsign;
+use DecodeError;
+use Latitude;
+use Longitude;
+use Position;
+use Precision;
+use QConstruct;
+use Timestamp;
+
+#[derive(Eq, PartialEq, Debug, Copy, Clone, Hash)]
+pub struct CallsignRef<'a> {
+    call: &'a str,
+    ssid: Option<&'a str>,
+}
+
+impl<'a> CallsignRef<'a> {
+    /// Decodes a callsign, with a trailing '*' if it has been heard.
+    pub fn decode_textual(bytes: &'a [u8]) -> Option<(Self, bool)> {
+        let (bytes, heard) = match bytes.split_last() {
+            Some((b'*', rest)) => (rest, true),
+            _ => (bytes, false),
+        };
+
+        let s = std::str::from_utf8(bytes).ok()?;
+        let callsign = match s.split_once('-') {
+            Some((call, ssid)) if call.is_empty() || ssid.is_empty() => return None,
+            // like `Callsign`, an SSID of 0 is the same as none
+            Some((call, "0")) => Self { call, ssid: None },
+            Some((call, ssid)) => Self {
+                call,
+                ssid: Some(ssid),
+            },
+            None => Self {
+                call: s,
+                ssid: None,
+            },
+        };
+
+        Some((callsign, heard))
+    }
+
+    pub fn call(&self) -> &'a str {
+        self.call
+    }
+
+    pub fn ssid(&self) -> Option<&'a str> {
+        self.ssid
+    }
+
+    pub fn to_owned(&self) -> Callsign {
+        match self.ssid {
+            Some(ssid) => Callsign::new_with_ssid(self.call, ssid),
+            None => Callsign::new_no_ssid(self.call),
+        }
+    }
+}
+
+impl Display for CallsignRef<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        write!(f, "{}", self.call)?;
+
+        if let Some(ssid) = self.ssid {
+            write!(f, "-{}", ssid)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl PartialEq<Callsign> for CallsignRef<'_> {
+    fn eq(&self, other: &Callsign) -> bool {
+        self.call == other.call() && self.ssid == other.ssid()
+    }
+}
+
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub enum ViaRef<'a> {
+    Callsign(CallsignRef<'a>, bool),
+    QConstruct(QConstruct),
+}
+
+impl<'a> ViaRef<'a> {
+    pub fn decode_textual(bytes: &'a [u8]) -> Option<Self> {
+        if let Some(q) = QConstruct::decode_textual(bytes) {
+            return Some(Self::QConstruct(q));
+        }
+
+        CallsignRef::decode_textual(bytes).map(|(c, heard)| Self::Callsign(c, heard))
+    }
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct PositionRef<'a> {
+    pub timestamp: Option<Timestamp>,
+    pub messaging_supported: bool,
+    pub position: Position,
+    /// Everything after the position, including extensions,
+    /// weather data, altitudes, telemetry and DAO tokens.
+    pub comment: &'a [u8],
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct MicERef<'a> {
+    pub latitude: Latitude,
+    pub longitude: Longitude,
+    pub precision: Precision,
+    pub message: Message,
+    pub speed: Speed,
+    pub course: Course,
+    pub symbol_table: u8,
+    pub symbol_code: u8,
+    pub current: bool,
+    /// The status text, including the device, altitudes and telemetry
+    pub comment: &'a [u8],
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct ObjectRef<'a> {
+    /// Without the padding spaces
+    pub name: &'a [u8],
+    pub live: bool,
+    pub timestamp: Timestamp,
+    pub position: Position,
+    /// Everything after the position, including extensions,
+    /// weather data, altitudes, telemetry and DAO tokens.
+    pub comment: &'a [u8],
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct ItemRef<'a> {
+    pub name: &'a [u8],
+    pub live: bool,
+    pub position: Position,
+    /// Everything after the position, including extensions,
+    /// weather data, altitudes, telemetry and DAO tokens.
+    pub comment: &'a [u8],
+}
+
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub struct MessageRef<'a> {
+    /// Without the padding spaces
+    pub addressee: &'a [u8],
+    /// Including the message ID, if any
+    pub text: &'a [u8],
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum AprsDataRef<'a> {
+    Position(PositionRef<'a>),
+    MicE(MicERef<'a>),
+    Object(ObjectRef<'a>),
+    Item(ItemRef<'a>),
+    Message(MessageRef<'a>),
+    /// The status text, including a timestamp if there is one
+    Status(&'a [u8]),
+    /// Any other data type
+    Other(&'a [u8]),
+}
+
+impl<'a> AprsDataRef<'a> {
+    fn decode(b: &'a [u8], to: &CallsignRef<'a>) -> Result<Self, DecodeError> {
+        Ok(match *b.first().unwrap_or(&0) {
+            b'!' | b'/' | b'=' | b'@' => AprsDataRef::Position(decode_position(b)?),
+            0x1c | b'`' => AprsDataRef::MicE(decode_mic_e(&b[1..], to, true)?),
+            0x1d | b'\'' => AprsDataRef::MicE(decode_mic_e(&b[1..], to, false)?),
+            b';' => {
+                let (name, live, timestamp, rest) = object::decode_header(&b[1..])?;
+                let (comment, position) = Position::decode(rest)?;
+
+                AprsDataRef::Object(ObjectRef {
+                    name,
+                    live,
+                    timestamp,
+                    position,
+                    comment: comment.unwrap_or_default(),
+                })
+            }
+            b')' => {
+                let (name, live, rest) = item::decode_header(&b[1..])?;
+                let (comment, position) = Position::decode(rest)?;
+
+                AprsDataRef::Item(ItemRef {
+                    name,
+                    live,
+                    position,
+                    comment: comment.unwrap_or_default(),
+                })
+            }
+            b':' => {
+                let addressee = b
+                    .get(1..10)
+                    .filter(|_| b.get(10) == Some(&b':'))
+                    .ok_or_else(|| DecodeError::InvalidMessageDestination(b[1..].to_vec()))?;
+                let end = addressee
+                    .iter()
+                    .rposition(|&c| c != b' ')
+                    .map(|i| i + 1)
+                    .unwrap_or(0);
+
+                AprsDataRef::Message(MessageRef {
+                    addressee: &addressee[..end],
+                    text: &b[11..],
+                })
+            }
+            b'>' => AprsDataRef::Status(&b[1..]),
+            _ => AprsDataRef::Other(b),
+        })
+    }
+}
+
+fn decode_position(b: &[u8]) -> Result<PositionRef<'_>, DecodeError> {
+    let first = b[0];
+    let messaging_supported = first == b'=' || first == b'@';
+
+    let has_timestamp = first == b'@' || first == b'/';
+    let timestamp = if has_timestamp {
+        Some(Timestamp::try_from(
+            b.get(1..8)
+                .ok_or_else(|| DecodeError::InvalidPosition(b.to_vec()))?,
+        )?)
+    } else {
+        None
+    };
+
+    let b = if has_timestamp { &b[8..] } else { &b[1..] };
+    let (comment, position) = Position::decode(b)?;
+
+    Ok(PositionRef {
+        timestamp,
+        messaging_supported,
+        position,
+        comment: comment.unwrap_or_default(),
+    })
+}
+
+fn decode_mic_e<'a>(
+    b: &'a [u8],
+    to: &CallsignRef<'a>,
+    current: bool,
+) -> Result<MicERef<'a>, DecodeError> {
+    let fixed = MicEFixed::decode(to.call(), b, || {
+        DecodeError::InvalidMicEDestination(to.to_owned())
+    })?;
+
+    Ok(MicERef {
+        latitude: fixed.latitude,
+        longitude: fixed.longitude,
+        precision: fixed.precision,
+        message: fixed.message,
+        speed: fixed.speed,
+        course: fixed.course,
+        symbol_table: fixed.symbol_table,
+        symbol_code: fixed.symbol_code,
+        current,
+        comment: b.get(8..).unwrap_or_default(),
+    })
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct AprsPacketRef<'a> {
+    pub from: CallsignRef<'a>,
+    pub to: CallsignRef<'a>,
+    pub data: AprsDataRef<'a>,
+
+    path: &'a [u8],
+    body: &'a [u8],
+    raw: &'a [u8],
+}
+
+impl<'a> AprsPacketRef<'a> {
+    /// Decodes a packet without allocating.
+    /// Fails for the same headers as `AprsPacket::decode_textual`,
+    /// but only checks the information field of the data types
+    /// that are decoded.
+    pub fn decode_textual(s: &'a [u8]) -> Result<Self, DecodeError> {
+        let header_delimiter = s
+            .iter()
+            .position(|x| *x == b':')
+            .ok_or_else(|| DecodeError::InvalidPacket(s.to_owned()))?;
+        let (header, rest) = s.split_at(header_delimiter);
+        let body = &rest[1..];
+
+        let from_delimiter = header
+            .iter()
+            .position(|x| *x == b'>')
+            .ok_or_else(|| DecodeError::InvalidPacket(s.to_owned()))?;
+        let (from, rest) = header.split_at(from_delimiter);
+        let (from, _) = CallsignRef::decode_textual(from)
+            .ok_or_else(|| DecodeError::InvalidCallsign(from.to_owned()))?;
+
+        let to_and_via = &rest[1..];
+        let (to, path) = match to_and_via.iter().position(|x| *x == b',') {
+            Some(i) => (&to_and_via[..i], &to_and_via[(i + 1)..]),
+            None => (to_and_via, &[][..]),
+        };
+        let (to, _) = CallsignRef::decode_textual(to)
+            .ok_or_else(|| DecodeError::InvalidCallsign(to.to_owned()))?;
+
+        if let Some(v) = Self::path_entries(path).find(|v| ViaRef::decode_textual(v).is_none()) {
+            return Err(DecodeError::InvalidVia(v.to_owned()));
+        }
+
+        Ok(Self {
+            from,
+            to,
+            data: AprsDataRef::decode(body, &to)?,
+            path,
+            body,
+            raw: s,
+        })
+    }
+
+    /// The via path as it was received. Unlike `AprsPacket::via`,
+    /// the heard flags aren't propagated to earlier entries.
+    pub fn via(&self) -> impl Iterator<Item = ViaRef<'a>> + 'a {
+        // all entries were validated while decoding
+        Self::path_entries(self.path).filter_map(ViaRef::decode_textual)
+    }
+
+    /// The information field, starting with the APRS Data Type Identifier
+    pub fn body(&self) -> &'a [u8] {
+        self.body
+    }
+
+    /// Fully decodes the packet into an owned `AprsPacket`
+    pub fn to_packet(&self) -> Result<AprsPacket, DecodeError> {
+        AprsPacket::decode_textual(self.raw)
+    }
+
+    fn path_entries(path: &'a [u8]) -> impl Iterator<Item = &'a [u8]> {
+        path.split(|x| *x == b',').filter(move |_| !path.is_empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use AprsData;
+    use Via;
+
+    #[test]
+    fn decode_position() {
+        let original =
+            &b"ICA3D2>APRS,qAS,dl4mea*:/074849h4821.61N\\01224.49E^322/103/A=003054 !W09!"[..];
+        let packet = AprsPacketRef::decode_textual(original).unwrap();
+
+        assert_eq!(packet.from, Callsign::new_no_ssid("ICA3D2"));
+        assert_eq!(packet.to.call(), "APRS");
+        assert_eq!(
+            packet.via().collect::<Vec<_>>(),
+            vec![
+                ViaRef::QConstruct(QConstruct::AS),
+                ViaRef::Callsign(CallsignRef::decode_textual(b"dl4mea").unwrap().0, true),
+            ]
+        );
+
+        match &packet.data {
+            AprsDataRef::Position(p) => {
+                assert_eq!(p.timestamp, Some(Timestamp::HHMMSS(7, 48, 49)));
+                assert_relative_eq!(*p.position.latitude, 48.36016666666667);
+                assert_eq!(p.comment, b"322/103/A=003054 !W09!");
+            }
+            _ => panic!("Unexpected data type"),
+        }
+
+        let owned = packet.to_packet().unwrap();
+        assert_eq!(owned, AprsPacket::decode_textual(original).unwrap());
+    }
+
+    #[test]
+    fn decode_message() {
+        let packet =
+            AprsPacketRef::decode_textual(b"N0CALL-0>APRS,WIDE2-1::DEST     :Hello{12").unwrap();
+
+        assert_eq!(packet.from.ssid(), None);
+        assert_eq!(
+            packet.data,
+            AprsDataRef::Message(MessageRef {
+                addressee: b"DEST",
+                text: b"Hello{12",
+            })
+        );
+
+        let owned = packet.to_packet().unwrap();
+        assert_eq!(owned.from, Callsign::new_no_ssid("N0CALL"));
+        assert_eq!(
+            owned.via,
+            vec![Via::Callsign(Callsign::new_with_ssid("WIDE2", "1"), false)]
+        );
+        assert!(matches!(owned.data, AprsData::Message(_)));
+    }
+
+    #[test]
+    fn decode_mic_e() {
+        let original = &br#"ICA3F2>PPPPPP,qAS,DL4MEA:`(_fn"Oj/Hello/A=000100"#[..];
+        let packet = AprsPacketRef::decode_textual(original).unwrap();
+        let owned = match packet.to_packet().unwrap().data {
+            AprsData::MicE(m) => m,
+            _ => panic!("Unexpected data type"),
+        };
+
+        match &packet.data {
+            AprsDataRef::MicE(m) => {
+                assert_eq!(m.latitude, owned.latitude);
+                assert_eq!(m.longitude, owned.longitude);
+                assert_eq!(m.speed, owned.speed);
+                assert_eq!(m.course, owned.course);
+                assert_eq!(m.symbol_code, b'j');
+                assert!(m.current);
+                assert_eq!(m.comment, b"Hello/A=000100");
+            }
+            _ => panic!("Unexpected data type"),
+        }
+
+        assert_eq!(
+            AprsPacketRef::decode_textual(br#"ICA3F2>APRS:`(_fn"Oj/"#),
+            Err(DecodeError::InvalidMicEDestination(Callsign::new_no_ssid(
+                "APRS"
+            )))
+        );
+    }
+
+    #[test]
+    fn decode_object_and_item() {
+        let packet = AprsPacketRef::decode_textual(
+            b"ICA3F2>APRS:;LEADER   *092345z4903.50N/07201.75W>Hello/A=001234",
+        )
+        .unwrap();
+        match &packet.data {
+            AprsDataRef::Object(o) => {
+                assert_eq!(o.name, b"LEADER");
+                assert!(o.live);
+                assert_eq!(o.timestamp, Timestamp::DDHHMM(9, 23, 45));
+                assert_relative_eq!(*o.position.latitude, 49.05833333333333);
+                assert_eq!(o.comment, b"Hello/A=001234");
+            }
+            _ => panic!("Unexpected data type"),
+        }
+
+        let packet =
+            AprsPacketRef::decode_textual(b"ICA3F2>APRS:)AID#2!4903.50N/07201.75WAHi there")
+                .unwrap();
+        match &packet.data {
+            AprsDataRef::Item(i) => {
+                assert_eq!(i.name, b"AID#2");
+                assert!(i.live);
+                assert_relative_eq!(*i.position.longitude, -72.02916666666667);
+                assert_eq!(i.comment, b"Hi there");
+            }
+            _ => panic!("Unexpected data type"),
+        }
+    }
+
+    #[test]
+    fn decode_other() {
+        let packet =
+            AprsPacketRef::decode_textual(b"N0CALL>APRS:T#005,199,000,255,073,123,01101001")
+                .unwrap();
+
+        assert_eq!(packet.via().count(), 0);
+        assert_eq!(
+            packet.data,
+            AprsDataRef::Other(b"T#005,199,000,255,073,123,01101001")
+        );
+        assert_eq!(packet.body(), b"T#005,199,000,255,073,123,01101001");
+    }
+
+    #[test]
+    fn decode_invalid() {
+        assert_eq!(
+            AprsPacketRef::decode_textual(b"N0CALL>APRS,-1:>Hello"),
+            Err(DecodeError::InvalidVia(b"-1".to_vec()))
+        );
+        assert_eq!(
+            AprsPacketRef::decode_textual(b"N0CALL>APRS::DEST:Hello"),
+            Err(DecodeError::InvalidMessageDestination(
+                b"DEST:Hello".to_vec()
+            ))
+        );
+    }
+}
@@ -2,13 +2,21 @@ use std::convert::TryFrom;
 use std::io::Write;
 
 use Callsign;
+use CommentOffsets;
+use CommentTelemetry;
 use DecodeError;
 use EncodeError;
+use SpecViolation;
 use Timestamp;
 
+use AprsCst;
+use Extension;
 use Position;
+use Weather;
 
-use AprsCst;
+use crate::components::comment::{self, CommentTokens};
+use crate::diagnostic::{OffsetResult, SpanResult, SpannedError};
+use crate::validate;
 
 #[derive(PartialEq, Debug, Clone)]
 pub struct AprsPosition {
@@ -18,45 +26,95 @@ pub struct AprsPosition {
     pub messaging_supported: bool,
 
     pub position: Position,
+    pub extension: Option<Extension>,
+    pub weather: Option<Weather>,
 
+    pub altitude: Option<i32>,
     pub comment: Vec<u8>,
+    pub telemetry: Option<CommentTelemetry>,
+    pub comment_offsets: CommentOffsets,
 }
 
 impl AprsPosition {
     pub fn decode(b: &[u8], to: Callsign) -> Result<Self, DecodeError> {
+        Self::decode_spanned(b, to).map_err(DecodeError::from)
+    }
+
+    pub(crate) fn decode_spanned(b: &[u8], to: Callsign) -> Result<Self, SpannedError> {
         let first = *b
             .first()
-            .ok_or_else(|| DecodeError::InvalidPosition(vec![]))?;
+            .ok_or_else(|| DecodeError::InvalidPosition(vec![]))
+            .span(0..0)?;
         let messaging_supported = first == b'=' || first == b'@';
 
         // parse timestamp if necessary
         let has_timestamp = first == b'@' || first == b'/';
         let timestamp = if has_timestamp {
-            Some(Timestamp::try_from(
-                b.get(1..8)
-                    .ok_or_else(|| DecodeError::InvalidPosition(b.to_vec()))?,
-            )?)
+            let timestamp = b
+                .get(1..8)
+                .ok_or_else(|| DecodeError::InvalidPosition(b.to_vec()))
+                .span(1..b.len())?;
+            Some(Timestamp::try_from(timestamp).span(1..8)?)
         } else {
             None
         };
 
         // strip leading type symbol and potential timestamp
-        let b = if has_timestamp { &b[8..] } else { &b[1..] };
+        let start = if has_timestamp { 8 } else { 1 };
+        let b = &b[start..];
 
         // decode the position and symbol data
-        let (remaining_buffer, position) = Position::decode(b)?;
+        let (remaining_buffer, mut position) = Position::decode(b).offset(start)?;
         // comment is entire rest of buffer, blank comment if not provided
-        let comment = remaining_buffer.unwrap_or_default().to_vec();
+        let remaining_buffer = remaining_buffer.unwrap_or_default();
+
+        // weather stations report wind direction/speed in place of an extension,
+        // otherwise opportunistically decode extensions of uncompressed positions
+        let extension = remaining_buffer
+            .get(..7)
+            .filter(|_| matches!(position.cst, AprsCst::Uncompressed))
+            .and_then(|ext| Extension::decode(ext).ok());
+        let (weather, extension, mut comment) = match position.decode_weather(remaining_buffer) {
+            Some((weather, comment)) => (Some(weather), None, comment.to_vec()),
+            None if extension.is_some() => (None, extension, remaining_buffer[7..].to_vec()),
+            None => (None, None, remaining_buffer.to_vec()),
+        };
+        let tokens = CommentTokens::extract(&mut comment, Some(&mut position));
 
         Ok(Self {
             to,
             timestamp,
             messaging_supported,
             position,
+            extension,
+            weather,
+            altitude: tokens.altitude,
             comment,
+            telemetry: tokens.telemetry,
+            comment_offsets: tokens.offsets,
         })
     }
 
+    /// Returns all violations of the APRS spec
+    pub fn validate(&self) -> Vec<SpecViolation> {
+        let mut violations = vec![];
+        validate::check_timestamp(self.timestamp.as_ref(), &mut violations);
+        // weather reports don't have a comment
+        if self.weather.is_none() {
+            let max = validate::max_comment_len(self.extension.is_some());
+            let len = comment::encoded_len(
+                &self.comment,
+                self.altitude,
+                self.telemetry.as_ref(),
+                &self.position.dao_token().unwrap_or_default(),
+                &self.comment_offsets,
+            );
+            validate::check_comment(len, max, &mut violations);
+        }
+
+        violations
+    }
+
     pub fn encode<W: Write>(&self, buf: &mut W) -> Result<(), EncodeError> {
         let sym = match (self.timestamp.is_some(), self.messaging_supported) {
             (true, true) => '@',
@@ -71,13 +129,26 @@ impl AprsPosition {
             ts.encode(buf)?;
         }
 
-        // if we have a compressed cst, we must use a compressed position
-        if matches!(self.position.cst, AprsCst::Uncompressed) {
+        if let Some(weather) = &self.weather {
+            self.position.encode(buf)?;
+            weather.encode(buf, self.position.weather_format())?;
+        } else if let Some(ext) = &self.extension {
+            // extensions can only follow uncompressed positions
             self.position.encode_uncompressed(buf)?;
+            ext.encode(buf)?;
         } else {
-            self.position.encode_compressed(buf)?;
+            // if we have a compressed cst, we must use a compressed position
+            self.position.encode(buf)?;
         }
-        buf.write_all(&self.comment)?;
+
+        comment::encode(
+            buf,
+            &self.comment,
+            self.altitude,
+            self.telemetry.as_ref(),
+            &self.position.dao_token()?,
+            &self.comment_offsets,
+        )?;
 
         Ok(())
     }
@@ -92,6 +163,10 @@ mod tests {
     use AprsCompressionType;
     use AprsCourseSpeed;
     use AprsRadioRange;
+    use Dao;
+    use DaoPrecision;
+    use Latitude;
+    use Longitude;
 
     fn default_callsign() -> Callsign {
         Callsign::new_no_ssid("VE9")
@@ -123,7 +198,8 @@ mod tests {
         assert_relative_eq!(*result.position.longitude, -171.95429033460567);
         assert_eq!(result.position.symbol_table, '/');
         assert_eq!(result.position.symbol_code, '-');
-        assert_eq!(result.comment, b"Hello/A=001000");
+        assert_eq!(result.altitude, Some(1000));
+        assert_eq!(result.comment, b"Hello");
         assert_eq!(
             result.position.cst,
             AprsCst::CompressedSome {
@@ -151,7 +227,9 @@ mod tests {
         assert_relative_eq!(*result.position.longitude, -171.95429033460567);
         assert_eq!(result.position.symbol_table, '\\');
         assert_eq!(result.position.symbol_code, '^');
-        assert_eq!(result.comment, b"322/103/A=003054");
+        assert_eq!(result.extension, None);
+        assert_eq!(result.altitude, Some(3054));
+        assert_eq!(result.comment, b"322/103");
         assert_eq!(
             result.position.cst,
             AprsCst::CompressedSome {
@@ -205,7 +283,8 @@ mod tests {
         assert_relative_eq!(*result.position.longitude, -171.95429033460567);
         assert_eq!(result.position.symbol_table, '\\');
         assert_eq!(result.position.symbol_code, '^');
-        assert_eq!(result.comment, b"322/103/A=003054");
+        assert_eq!(result.altitude, Some(3054));
+        assert_eq!(result.comment, b"322/103");
         assert_eq!(result.position.cst, AprsCst::CompressedNone);
     }
 
@@ -251,7 +330,8 @@ mod tests {
         );
         assert_eq!(result.position.symbol_table, '/');
         assert_eq!(result.position.symbol_code, '-');
-        assert_eq!(result.comment, b"Hello/A=001000");
+        assert_eq!(result.altitude, Some(1000));
+        assert_eq!(result.comment, b"Hello");
         assert_eq!(result.position.cst, AprsCst::Uncompressed);
     }
 
@@ -270,7 +350,15 @@ mod tests {
         assert_relative_eq!(*result.position.longitude, 12.408166666666666);
         assert_eq!(result.position.symbol_table, '\\');
         assert_eq!(result.position.symbol_code, '^');
-        assert_eq!(result.comment, b"322/103/A=003054");
+        assert_eq!(
+            result.extension,
+            Some(Extension::DirectionSpeed {
+                direction_degrees: 322,
+                speed_knots: 103,
+            })
+        );
+        assert_eq!(result.altitude, Some(3054));
+        assert!(result.comment.is_empty());
         assert_eq!(result.position.cst, AprsCst::Uncompressed);
     }
 
@@ -305,10 +393,98 @@ mod tests {
         assert_relative_eq!(*result.position.longitude, 12.408166666666666);
         assert_eq!(result.position.symbol_table, '\\');
         assert_eq!(result.position.symbol_code, '^');
-        assert_eq!(result.comment, b"322/103/A=003054");
+        assert_eq!(
+            result.extension,
+            Some(Extension::DirectionSpeed {
+                direction_degrees: 322,
+                speed_knots: 103,
+            })
+        );
+        assert_eq!(result.altitude, Some(3054));
+        assert!(result.comment.is_empty());
         assert_eq!(result.position.cst, AprsCst::Uncompressed);
     }
 
+    #[test]
+    fn parse_with_comment_telemetry() {
+        let result =
+            AprsPosition::decode(&b"!4903.50N/07201.75W-Hello|!\"&7|"[..], default_callsign())
+                .unwrap();
+
+        assert_eq!(result.comment, b"Hello");
+        assert_eq!(
+            result.telemetry,
+            Some(CommentTelemetry {
+                sequence: 1,
+                analog: vec![477],
+                digital: None,
+            })
+        );
+    }
+
+    #[test]
+    fn parse_with_dao() {
+        let result =
+            AprsPosition::decode(&b"!4903.50N/07201.75W-Hello !wTD!"[..], default_callsign())
+                .unwrap();
+
+        assert_relative_eq!(
+            *result.position.latitude,
+            49.0 + (3.5 + 51.0 / 9100.0) / 60.0
+        );
+        assert_relative_eq!(
+            *result.position.longitude,
+            -(72.0 + (1.75 + 35.0 / 9100.0) / 60.0)
+        );
+        assert_eq!(
+            result.position.dao,
+            Some(Dao {
+                datum: 'W',
+                precision: DaoPrecision::Base91,
+                steps: None,
+            })
+        );
+        assert_eq!(result.comment, b"Hello ");
+    }
+
+    #[test]
+    fn parse_compressed_with_dao() {
+        let result =
+            AprsPosition::decode(&b"!/ABCD#$%^-X>DHello !W12!"[..], default_callsign()).unwrap();
+
+        // the DAO isn't applied to compressed positions
+        assert_eq!(
+            result.position.dao,
+            Some(Dao {
+                datum: 'W',
+                precision: DaoPrecision::ThousandthMinute,
+                steps: Some((1, 2)),
+            })
+        );
+        assert_eq!(result.comment, b"Hello ");
+
+        let mut buf = vec![];
+        result.encode(&mut buf).unwrap();
+        assert_eq!(buf, b"!/ABCD#$%^-X>DHello !W12!");
+    }
+
+    #[test]
+    fn encode_with_dao() {
+        let mut position =
+            AprsPosition::decode(&b"!4903.50N/07201.75W-Hello"[..], default_callsign()).unwrap();
+        position.position.latitude = Latitude::new(49.0 + 3.5059 / 60.0).unwrap();
+        position.position.longitude = Longitude::new(-(72.0 + 1.7531 / 60.0)).unwrap();
+        position.position.dao = Some(Dao {
+            datum: 'W',
+            precision: DaoPrecision::ThousandthMinute,
+            steps: None,
+        });
+
+        let mut buf = vec![];
+        position.encode(&mut buf).unwrap();
+        assert_eq!(buf, b"!4903.50N/07201.75W-Hello !W63!");
+    }
+
     #[test]
     fn parse_and_reencode_positions() {
         let positions = vec![
@@ -319,10 +495,22 @@ mod tests {
             &b"@074849h/ABCD#$%^- sT"[..],
             &b"!4903.50N/07201.75W-"[..],
             &b"!4903.50N/07201.75W-Hello/A=001000"[..],
+            &b"!4903.50N/07201.75W-/A=-00100Hello"[..],
             &br"/074849h4821.61N\01224.49E^322/103/A=003054"[..],
             &b"=4903.50N/07201.75W-"[..],
             &br"@074849h4821.61N\01224.49E^322/103/A=003054"[..],
             &br"@074849h4821.  N\01224.00E^322/103/A=003054"[..],
+            &b"!4903.50N/07201.75W-Hello|ss1122334455#a|"[..],
+            &b"!4903.50N/07201.75W-Hello!wTD!"[..],
+            &b"!4903.50N/07201.75W-Hello!W  !"[..],
+            &b"!4903.50N/07201.75W-Hello !W12! world"[..],
+            &b"!4903.50N/07201.75W-Hello!wTD!|ss1122334455#a|"[..],
+            &b"!4903.50N/07201.75W-/A=001000 !W12! Hello"[..],
+            &b"!4903.50N/07201.75W-wow!w12!there"[..],
+            &b"!4903.50N/07201.75W-wow!w12!"[..],
+            &b"!/ABCD#$%^-X>DHello!W12!"[..],
+            &b"!/ABCD#$%^-X>DHello !W12!"[..],
+            &b"!/ABCD#$%^-X>DHello !wTD!|ss1122334455#a|"[..],
         ];
 
         for p in positions {
@@ -339,4 +527,33 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn validate_encoded_comment() {
+        // the altitude is part of the comment that goes on air
+        let comment = "x".repeat(40);
+        let packet = format!("!4903.50N/07201.75W-{}/A=001234", comment);
+        let result = AprsPosition::decode(packet.as_bytes(), Callsign::new_no_ssid("VE9")).unwrap();
+
+        assert_eq!(result.comment, comment.as_bytes());
+        assert_eq!(
+            result.validate(),
+            vec![SpecViolation::CommentTooLong { len: 49, max: 43 }]
+        );
+    }
+
+    #[test]
+    fn parse_compressed_weather() {
+        let result = AprsPosition::decode(
+            &b"=/5L!!<*e7_7P[g005t077r000p000P000h50b09900wRSW"[..],
+            default_callsign(),
+        )
+        .unwrap();
+
+        let weather = result.weather.unwrap();
+        assert_eq!(weather.wind_direction, Some(88));
+        assert_eq!(weather.wind_speed, Some(42));
+        assert_eq!(weather.wind_gust, Some(5));
+        assert_eq!(result.comment, b"wRSW");
+    }
 }
@@ -0,0 +1,177 @@
+//! Queries ask other stations to respond with information.
+//! General queries start with the '?' APRS Data Type Identifier and are
+//! answered by all stations they apply to. Directed queries are sent to a
+//! single station as the text of a message.
+//!
+//! Examples:
+//! - "?APRS?"                          (general query, all stations)
+//! - "?IGATE? 34.02,-117.15,0200"      (general query with target footprint)
+//! - "?APRSP"                          (directed query for a position)
+
+use std::io::Write;
+
+use Callsign;
+use DecodeError;
+use EncodeError;
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum QueryKind {
+    /// `?APRS?`: all stations
+    Aprs,
+    /// `?IGATE?`: IGates
+    Igate,
+    /// `?WX?`: weather stations
+    Weather,
+    /// `?PING?`: same as `?APRST`
+    Ping,
+    /// `?APRSD`: stations heard directly
+    DirectStations,
+    /// `?APRSH`: how often a station was heard
+    Heard,
+    /// `?APRSM`: outstanding messages
+    Messages,
+    /// `?APRSO`: objects
+    Objects,
+    /// `?APRSP`: position
+    Position,
+    /// `?APRSS`: status
+    Status,
+    /// `?APRST`: the route the query took
+    Trace,
+    /// Any other query. Contains the query type without the leading '?'.
+    Unknown(Vec<u8>),
+}
+
+const QUERY_KINDS: [(QueryKind, &[u8]); 11] = [
+    (QueryKind::Aprs, b"APRS?"),
+    (QueryKind::Igate, b"IGATE?"),
+    (QueryKind::Weather, b"WX?"),
+    (QueryKind::Ping, b"PING?"),
+    (QueryKind::DirectStations, b"APRSD"),
+    (QueryKind::Heard, b"APRSH"),
+    (QueryKind::Messages, b"APRSM"),
+    (QueryKind::Objects, b"APRSO"),
+    (QueryKind::Position, b"APRSP"),
+    (QueryKind::Status, b"APRSS"),
+    (QueryKind::Trace, b"APRST"),
+];
+
+impl QueryKind {
+    /// Decodes the query type after the '?'.
+    /// Returns the remaining arguments without the separating space.
+    pub(crate) fn decode(b: &[u8]) -> (Self, &[u8]) {
+        let known = QUERY_KINDS
+            .iter()
+            .find(|(_, token)| b.starts_with(token))
+            .map(|(kind, token)| (kind.clone(), &b[token.len()..]));
+
+        let (kind, rest) = known.unwrap_or_else(|| {
+            // unknown queries end with a '?' or a space
+            let len = b
+                .iter()
+                .position(|&c| c == b'?')
+                .map(|i| i + 1)
+                .or_else(|| b.iter().position(|&c| c == b' '))
+                .unwrap_or(b.len());
+            (QueryKind::Unknown(b[..len].to_vec()), &b[len..])
+        });
+
+        (kind, rest.strip_prefix(b" ").unwrap_or(rest))
+    }
+
+    /// Encodes the query type and its arguments, including the leading '?'.
+    pub(crate) fn encode<W: Write>(&self, buf: &mut W, args: &[u8]) -> Result<(), EncodeError> {
+        buf.write_all(b"?")?;
+        buf.write_all(self.token())?;
+
+        if !args.is_empty() {
+            buf.write_all(b" ")?;
+            buf.write_all(args)?;
+        }
+
+        Ok(())
+    }
+
+    fn token(&self) -> &[u8] {
+        match self {
+            QueryKind::Unknown(token) => token,
+            kind => QUERY_KINDS
+                .iter()
+                .find(|(k, _)| k == kind)
+                .map(|(_, token)| *token)
+                .unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AprsQuery {
+    pub to: Callsign,
+
+    pub kind: QueryKind,
+    /// Everything after the query type, e.g. the target footprint
+    /// of a general query or the callsign of `?APRSH`
+    pub args: Vec<u8>,
+}
+
+impl AprsQuery {
+    pub fn decode(b: &[u8], to: Callsign) -> Result<Self, DecodeError> {
+        let (kind, args) = QueryKind::decode(b);
+
+        Ok(Self {
+            to,
+            kind,
+            args: args.to_vec(),
+        })
+    }
+
+    pub fn encode<W: Write>(&self, buf: &mut W) -> Result<(), EncodeError> {
+        self.kind.encode(buf, &self.args)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn default_callsign() -> Callsign {
+        Callsign::new_no_ssid("VE9")
+    }
+
+    #[test]
+    fn decode_general_queries() {
+        for (b, kind, args) in [
+            (&b"APRS?"[..], QueryKind::Aprs, &b""[..]),
+            (b"IGATE?", QueryKind::Igate, b""),
+            (
+                b"WX? 34.02,-117.15,0200",
+                QueryKind::Weather,
+                b"34.02,-117.15,0200",
+            ),
+            (b"FOO?bar", QueryKind::Unknown(b"FOO?".to_vec()), b"bar"),
+            (b"FOO bar", QueryKind::Unknown(b"FOO".to_vec()), b"bar"),
+        ] {
+            assert_eq!(
+                AprsQuery::decode(b, default_callsign()),
+                Ok(AprsQuery {
+                    to: default_callsign(),
+                    kind,
+                    args: args.to_vec(),
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn encode_query() {
+        let query = AprsQuery {
+            to: default_callsign(),
+            kind: QueryKind::Igate,
+            args: b"34.02,-117.15,0200".to_vec(),
+        };
+
+        let mut buf = vec![];
+        query.encode(&mut buf).unwrap();
+        assert_eq!(buf, b"?IGATE? 34.02,-117.15,0200");
+    }
+}
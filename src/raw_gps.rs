@@ -0,0 +1,492 @@
+//! A Raw GPS report starts with the '$' APRS Data Type Identifier,
+//! followed by an NMEA sentence from the station's GPS receiver.
+//! RMC, GGA, GLL, VTG and WPL sentences are decoded, other sentences are
+//! kept as they are.
+//!
+//! Examples:
+//! - "$GPRMC,063909,A,3349.4302,N,11700.3721,W,43.022,89.3,291099,13.6,E*52"
+//! - "$GPGGA,102705,5157.9762,N,00029.3256,W,1,04,2.0,75.7,M,47.6,M,,*62"
+
+use std::io::Write;
+
+use bytes::parse_bytes;
+use AprsCst;
+use Callsign;
+use DecodeError;
+use EncodeError;
+use Latitude;
+use Longitude;
+use Position;
+use Precision;
+use SpecViolation;
+use Timestamp;
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum NmeaSentence {
+    /// Recommended minimum data
+    Rmc,
+    /// Fix data
+    Gga,
+    /// Geographic position
+    Gll,
+    /// Course and speed over ground
+    Vtg,
+    /// Waypoint location
+    Wpl,
+    /// Any other sentence. Contains the sentence type, e.g. `GSA`.
+    Unknown(Vec<u8>),
+}
+
+/// The fix quality from a GGA sentence
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum FixQuality {
+    Invalid,
+    Gps,
+    Dgps,
+    Pps,
+    Rtk,
+    FloatRtk,
+    Estimated,
+    Manual,
+    Simulation,
+    Unknown(u8),
+}
+
+impl FixQuality {
+    fn decode(q: u8) -> Self {
+        match q {
+            0 => FixQuality::Invalid,
+            1 => FixQuality::Gps,
+            2 => FixQuality::Dgps,
+            3 => FixQuality::Pps,
+            4 => FixQuality::Rtk,
+            5 => FixQuality::FloatRtk,
+            6 => FixQuality::Estimated,
+            7 => FixQuality::Manual,
+            8 => FixQuality::Simulation,
+            q => FixQuality::Unknown(q),
+        }
+    }
+}
+
+/// The fields other than `to` are decoded from the sentence,
+/// so they can only be read. Use `AprsRawGps::new` to create a report
+/// from a sentence. The sentence is encoded as it was received.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AprsRawGps {
+    pub to: Callsign,
+
+    sentence: Vec<u8>,
+    checksum_valid: bool,
+
+    kind: NmeaSentence,
+    timestamp: Option<Timestamp>,
+    position: Option<Position>,
+    valid: bool,
+    course: Option<f64>,
+    speed_knots: Option<f64>,
+    altitude_meters: Option<f64>,
+    fix_quality: Option<FixQuality>,
+    satellites: Option<u8>,
+    waypoint: Option<Vec<u8>>,
+}
+
+impl AprsRawGps {
+    /// Creates a report from a sentence without the leading '$'.
+    /// The other fields are decoded from it.
+    pub fn new(to: Callsign, sentence: Vec<u8>) -> Result<Self, DecodeError> {
+        Self::decode(&sentence, to)
+    }
+
+    /// The complete sentence without the leading '$', including the checksum
+    /// and the line ending, if the receiver appended one
+    pub fn sentence(&self) -> &[u8] {
+        &self.sentence
+    }
+
+    /// `false` if the checksum is missing or doesn't match the sentence
+    pub fn checksum_valid(&self) -> bool {
+        self.checksum_valid
+    }
+
+    pub fn kind(&self) -> &NmeaSentence {
+        &self.kind
+    }
+
+    pub fn timestamp(&self) -> Option<&Timestamp> {
+        self.timestamp.as_ref()
+    }
+
+    /// NMEA doesn't contain a symbol, so the position uses the
+    /// default `/` `/` symbol
+    pub fn position(&self) -> Option<&Position> {
+        self.position.as_ref()
+    }
+
+    /// `false` if the receiver reports that it doesn't have a valid fix
+    pub fn valid(&self) -> bool {
+        self.valid
+    }
+
+    pub fn course(&self) -> Option<f64> {
+        self.course
+    }
+
+    pub fn speed_knots(&self) -> Option<f64> {
+        self.speed_knots
+    }
+
+    pub fn altitude_meters(&self) -> Option<f64> {
+        self.altitude_meters
+    }
+
+    pub fn fix_quality(&self) -> Option<FixQuality> {
+        self.fix_quality
+    }
+
+    pub fn satellites(&self) -> Option<u8> {
+        self.satellites
+    }
+
+    pub fn waypoint(&self) -> Option<&[u8]> {
+        self.waypoint.as_deref()
+    }
+
+    /// Returns all violations of the APRS spec
+    pub fn validate(&self) -> Vec<SpecViolation> {
+        if self.checksum_valid {
+            vec![]
+        } else {
+            vec![SpecViolation::InvalidNmeaChecksum(self.sentence.clone())]
+        }
+    }
+
+    pub fn decode(b: &[u8], to: Callsign) -> Result<Self, DecodeError> {
+        let invalid = || DecodeError::InvalidNmea(b.to_vec());
+
+        // receivers often append a line ending,
+        // it is kept in the sentence but isn't part of the checksum
+        let end = b
+            .iter()
+            .rposition(|c| !c.is_ascii_whitespace())
+            .map(|i| i + 1)
+            .unwrap_or(0);
+        let sentence = &b[..end];
+
+        // a missing or wrong checksum is reported by `validate`
+        let (data, checksum_valid) = match sentence.iter().rposition(|&c| c == b'*') {
+            Some(star) => {
+                let (data, checksum) = (&sentence[..star], &sentence[(star + 1)..]);
+                let checksum = std::str::from_utf8(checksum)
+                    .ok()
+                    .filter(|c| c.len() == 2)
+                    .and_then(|c| u8::from_str_radix(c, 16).ok());
+                (data, checksum == Some(nmea_checksum(data)))
+            }
+            None => (sentence, false),
+        };
+
+        let fields: Vec<&[u8]> = data.split(|&c| c == b',').collect();
+        let kind = match fields[0].get(2..) {
+            Some(b"RMC") => NmeaSentence::Rmc,
+            Some(b"GGA") => NmeaSentence::Gga,
+            Some(b"GLL") => NmeaSentence::Gll,
+            Some(b"VTG") => NmeaSentence::Vtg,
+            Some(b"WPL") => NmeaSentence::Wpl,
+            Some(k) if fields[0].len() == 5 => NmeaSentence::Unknown(k.to_vec()),
+            _ => return Err(invalid()),
+        };
+
+        let field = |i: usize| fields.get(i).copied().unwrap_or(&[]);
+        let number = |i: usize| -> Result<Option<f64>, DecodeError> {
+            match field(i) {
+                [] => Ok(None),
+                f => parse_bytes(f).map(Some).ok_or_else(invalid),
+            }
+        };
+        let position = |i: usize| -> Result<Option<Position>, DecodeError> {
+            decode_position(field(i), field(i + 1), field(i + 2), field(i + 3)).ok_or_else(invalid)
+        };
+        let timestamp = |i: usize| -> Result<Option<Timestamp>, DecodeError> {
+            match field(i) {
+                [] => Ok(None),
+                f => decode_time(f).map(Some).ok_or_else(invalid),
+            }
+        };
+
+        let mut raw_gps = Self {
+            to,
+            sentence: b.to_vec(),
+            checksum_valid,
+            kind: kind.clone(),
+            timestamp: None,
+            position: None,
+            valid: true,
+            course: None,
+            speed_knots: None,
+            altitude_meters: None,
+            fix_quality: None,
+            satellites: None,
+            waypoint: None,
+        };
+
+        match kind {
+            NmeaSentence::Rmc => {
+                raw_gps.timestamp = timestamp(1)?;
+                raw_gps.valid = field(2) == b"A";
+                raw_gps.position = position(3)?;
+                raw_gps.speed_knots = number(7)?;
+                raw_gps.course = number(8)?;
+            }
+            NmeaSentence::Gga => {
+                raw_gps.timestamp = timestamp(1)?;
+                raw_gps.position = position(2)?;
+                let quality = number(6)?.map(|q| FixQuality::decode(q as u8));
+                raw_gps.valid = !matches!(quality, Some(FixQuality::Invalid));
+                raw_gps.fix_quality = quality;
+                raw_gps.satellites = number(7)?.map(|s| s as u8);
+                raw_gps.altitude_meters = number(9)?;
+            }
+            NmeaSentence::Gll => {
+                raw_gps.position = position(1)?;
+                raw_gps.timestamp = timestamp(5)?;
+                raw_gps.valid = field(6) != b"V";
+            }
+            NmeaSentence::Vtg => {
+                raw_gps.course = number(1)?;
+                // older receivers leave out the unit fields
+                raw_gps.speed_knots = if field(2) == b"T" {
+                    number(5)?
+                } else {
+                    number(3)?
+                };
+            }
+            NmeaSentence::Wpl => {
+                raw_gps.position = position(1)?;
+                raw_gps.waypoint = Some(field(5).to_vec());
+            }
+            NmeaSentence::Unknown(_) => {}
+        }
+
+        Ok(raw_gps)
+    }
+
+    pub fn encode<W: Write>(&self, buf: &mut W) -> Result<(), EncodeError> {
+        buf.write_all(b"$")?;
+        buf.write_all(&self.sentence)?;
+
+        Ok(())
+    }
+}
+
+/// XORs all bytes between the '$' and the '*'
+fn nmea_checksum(data: &[u8]) -> u8 {
+    data.iter().fold(0, |acc, c| acc ^ c)
+}
+
+// hhmmss with optional fractional seconds
+fn decode_time(b: &[u8]) -> Option<Timestamp> {
+    if b.len() < 6 || !b[..6].iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+
+    Timestamp::new_hms(
+        parse_bytes(&b[0..2])?,
+        parse_bytes(&b[2..4])?,
+        parse_bytes(&b[4..6])?,
+    )
+}
+
+// Returns Some(None) if the fields are empty, which happens when there is no fix
+fn decode_position(lat: &[u8], ns: &[u8], lon: &[u8], ew: &[u8]) -> Option<Option<Position>> {
+    if lat.is_empty() && lon.is_empty() {
+        return Some(None);
+    }
+
+    let latitude = decode_degrees_minutes(lat, 2)?;
+    let latitude = match ns {
+        b"N" => latitude,
+        b"S" => -latitude,
+        _ => return None,
+    };
+    let longitude = decode_degrees_minutes(lon, 3)?;
+    let longitude = match ew {
+        b"E" => longitude,
+        b"W" => -longitude,
+        _ => return None,
+    };
+
+    Some(Some(Position {
+        latitude: Latitude::new(latitude)?,
+        longitude: Longitude::new(longitude)?,
+        precision: Precision::HundredthMinute,
+        symbol_table: '/',
+        symbol_code: '/',
+        cst: AprsCst::Uncompressed,
+        dao: None,
+    }))
+}
+
+// ddmm.mmmm or dddmm.mmmm
+fn decode_degrees_minutes(b: &[u8], degree_digits: usize) -> Option<f64> {
+    if b.len() < degree_digits + 2 || !b[..degree_digits].iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+
+    let degrees: f64 = parse_bytes(&b[..degree_digits])?;
+    let minutes: f64 = parse_bytes(&b[degree_digits..])?;
+    if !(0.0..60.0).contains(&minutes) {
+        return None;
+    }
+
+    Some(degrees + minutes / 60.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn default_callsign() -> Callsign {
+        Callsign::new_no_ssid("VE9")
+    }
+
+    #[test]
+    fn decode_rmc() {
+        let result = AprsRawGps::decode(
+            b"GPRMC,063909,A,3349.4302,N,11700.3721,W,43.022,89.3,291099,13.6,E*52",
+            default_callsign(),
+        )
+        .unwrap();
+
+        assert_eq!(result.kind(), &NmeaSentence::Rmc);
+        assert_eq!(result.timestamp(), Some(&Timestamp::HHMMSS(6, 39, 9)));
+        assert!(result.valid());
+        let position = result.position().unwrap();
+        assert_relative_eq!(*position.latitude, 33.0 + 49.4302 / 60.0);
+        assert_relative_eq!(*position.longitude, -(117.0 + 0.3721 / 60.0));
+        assert_eq!(result.speed_knots(), Some(43.022));
+        assert_eq!(result.course(), Some(89.3));
+    }
+
+    #[test]
+    fn decode_gga() {
+        let result = AprsRawGps::decode(
+            b"GPGGA,102705,5157.9762,N,00029.3256,W,1,04,2.0,75.7,M,47.6,M,,*62\r\n",
+            default_callsign(),
+        )
+        .unwrap();
+
+        assert_eq!(result.kind(), &NmeaSentence::Gga);
+        assert_eq!(result.fix_quality(), Some(FixQuality::Gps));
+        assert_eq!(result.satellites(), Some(4));
+        assert_eq!(result.altitude_meters(), Some(75.7));
+        assert_relative_eq!(*result.position().unwrap().latitude, 51.0 + 57.9762 / 60.0);
+        assert!(result.checksum_valid());
+        assert_eq!(
+            result.sentence(),
+            b"GPGGA,102705,5157.9762,N,00029.3256,W,1,04,2.0,75.7,M,47.6,M,,*62\r\n"
+        );
+
+        let mut buf = vec![];
+        result.encode(&mut buf).unwrap();
+        assert_eq!(&buf[1..], result.sentence());
+    }
+
+    #[test]
+    fn decode_gll_vtg_wpl() {
+        let result = AprsRawGps::decode(
+            b"GPGLL,3723.2475,N,12158.3416,W,161229.487,V*3B",
+            default_callsign(),
+        )
+        .unwrap();
+        assert_eq!(result.kind(), &NmeaSentence::Gll);
+        assert!(!result.valid());
+        assert_eq!(result.timestamp(), Some(&Timestamp::HHMMSS(16, 12, 29)));
+
+        let result = AprsRawGps::decode(
+            b"GPVTG,054.7,T,034.4,M,005.5,N,010.2,K*48",
+            default_callsign(),
+        )
+        .unwrap();
+        assert_eq!(result.kind(), &NmeaSentence::Vtg);
+        assert_eq!(result.course(), Some(54.7));
+        assert_eq!(result.speed_knots(), Some(5.5));
+        assert_eq!(result.position(), None);
+
+        let result = AprsRawGps::decode(
+            b"GPWPL,4807.038,N,01131.000,E,WPTNME*5C",
+            default_callsign(),
+        )
+        .unwrap();
+        assert_eq!(result.kind(), &NmeaSentence::Wpl);
+        assert_eq!(result.waypoint(), Some(&b"WPTNME"[..]));
+        assert_relative_eq!(*result.position().unwrap().longitude, 11.0 + 31.0 / 60.0);
+    }
+
+    #[test]
+    fn decode_no_fix() {
+        let result = AprsRawGps::decode(b"GPRMC,,V,,,,,,,,,,N*53", default_callsign()).unwrap();
+
+        assert!(!result.valid());
+        assert_eq!(result.position(), None);
+        assert_eq!(result.timestamp(), None);
+    }
+
+    #[test]
+    fn decode_invalid_checksum() {
+        for b in [
+            &b"GPRMC,063909,A,3349.4302,N,11700.3721,W,43.022,89.3,291099,13.6,E*53"[..],
+            b"GPRMC,063909,A,3349.4302,N,11700.3721,W,43.022,89.3,291099,13.6,E*5",
+            b"GPRMC,063909,A,3349.4302,N,11700.3721,W,43.022,89.3,291099,13.6,E",
+        ] {
+            let result = AprsRawGps::decode(b, default_callsign()).unwrap();
+            assert!(!result.checksum_valid());
+            assert_eq!(result.kind(), &NmeaSentence::Rmc);
+            assert_eq!(result.course(), Some(89.3));
+            assert_eq!(
+                result.validate(),
+                vec![SpecViolation::InvalidNmeaChecksum(b.to_vec())]
+            );
+
+            let mut buf = vec![];
+            result.encode(&mut buf).unwrap();
+            assert_eq!(&buf[1..], b);
+        }
+
+        assert_eq!(
+            AprsRawGps::decode(b"GPRMC,063909,A,33x9.4302,N*00", default_callsign()),
+            Err(DecodeError::InvalidNmea(
+                b"GPRMC,063909,A,33x9.4302,N*00".to_vec()
+            ))
+        );
+    }
+
+    #[test]
+    fn encode_raw_gps() {
+        let b = b"GPGSA,A,3,04,05,,09,12,,,24,,,,,2.5,1.3,2.1*39";
+        let result = AprsRawGps::decode(b, default_callsign()).unwrap();
+        assert_eq!(result.kind(), &NmeaSentence::Unknown(b"GSA".to_vec()));
+
+        let mut buf = vec![];
+        result.encode(&mut buf).unwrap();
+        assert_eq!(buf[0], b'$');
+        assert_eq!(&buf[1..], b);
+    }
+
+    #[test]
+    fn new_raw_gps() {
+        let result = AprsRawGps::new(
+            default_callsign(),
+            b"GPGLL,3723.2475,N,12158.3416,W,161229.487,A*2C".to_vec(),
+        )
+        .unwrap();
+        assert_eq!(result.kind(), &NmeaSentence::Gll);
+        assert!(result.valid());
+        assert_relative_eq!(*result.position().unwrap().latitude, 37.0 + 23.2475 / 60.0);
+
+        assert_eq!(
+            AprsRawGps::new(default_callsign(), b"GP".to_vec()),
+            Err(DecodeError::InvalidNmea(b"GP".to_vec()))
+        );
+    }
+}
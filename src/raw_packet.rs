@@ -0,0 +1,202 @@
+//! Re-encoding a decoded packet produces a canonical version of it,
+//! which isn't always identical to the original bytes.
+//! `RawAprsPacket` remembers the original header and body, so that
+//! unmodified packets can be forwarded byte-for-byte.
+
+use std::io::Write;
+use std::ops::Range;
+
+use AprsData;
+use AprsPacket;
+use Callsign;
+use DecodeError;
+use EncodeError;
+use Via;
+
+/// The packet can be modified through the `_mut` accessors.
+/// When encoding, it is compared against the decoded packet
+/// to find out whether the header or the body was changed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RawAprsPacket {
+    packet: AprsPacket,
+    // the packet as it was decoded
+    decoded: AprsPacket,
+
+    raw: Vec<u8>,
+    header: Range<usize>,
+    body: Range<usize>,
+}
+
+impl RawAprsPacket {
+    pub fn decode_textual(s: &[u8]) -> Result<Self, DecodeError> {
+        let packet = AprsPacket::decode_textual(s)?;
+
+        // decoding succeeded, so there is a delimiter
+        let header_delimiter = s.iter().position(|x| *x == b':').unwrap_or(s.len());
+
+        Ok(Self {
+            decoded: packet.clone(),
+            packet,
+            raw: s.to_vec(),
+            header: 0..header_delimiter,
+            body: (header_delimiter + 1).min(s.len())..s.len(),
+        })
+    }
+
+    /// Everything before the ':' of the original packet
+    pub fn raw_header(&self) -> &[u8] {
+        &self.raw[self.header.clone()]
+    }
+
+    /// The information field of the original packet
+    pub fn raw_body(&self) -> &[u8] {
+        &self.raw[self.body.clone()]
+    }
+
+    pub fn packet(&self) -> &AprsPacket {
+        &self.packet
+    }
+
+    /// The header is re-encoded if the source is changed
+    pub fn from_mut(&mut self) -> &mut Callsign {
+        &mut self.packet.from
+    }
+
+    /// The header is re-encoded if the path is changed
+    pub fn via_mut(&mut self) -> &mut Vec<Via> {
+        &mut self.packet.via
+    }
+
+    /// The body is re-encoded if the data is changed,
+    /// the header only if the destination is changed
+    pub fn data_mut(&mut self) -> &mut AprsData {
+        &mut self.packet.data
+    }
+
+    /// The parts of the packet that are changed are re-encoded
+    pub fn packet_mut(&mut self) -> &mut AprsPacket {
+        &mut self.packet
+    }
+
+    /// Returns `true` if the packet differs from the decoded one
+    pub fn is_modified(&self) -> bool {
+        self.packet != self.decoded
+    }
+
+    pub fn into_packet(self) -> AprsPacket {
+        self.packet
+    }
+
+    /// Writes the original bytes of the header and body,
+    /// unless they were changed by modifying the packet.
+    pub fn encode_textual<W: Write>(&self, buf: &mut W) -> Result<(), EncodeError> {
+        if self.is_header_modified() {
+            self.packet.encode_textual_header(buf)?;
+        } else {
+            buf.write_all(self.raw_header())?;
+        }
+
+        buf.write_all(b":")?;
+
+        if self.is_body_modified() {
+            self.packet.data.encode(buf)?;
+        } else {
+            buf.write_all(self.raw_body())?;
+        }
+
+        Ok(())
+    }
+
+    // the destination is encoded in the header, but is part of the data
+    fn is_header_modified(&self) -> bool {
+        self.packet.from != self.decoded.from
+            || self.packet.via != self.decoded.via
+            || self.packet.data.dest_field() != self.decoded.data.dest_field()
+    }
+
+    fn is_body_modified(&self) -> bool {
+        self.packet.data != self.decoded.data
+    }
+}
+
+impl From<RawAprsPacket> for AprsPacket {
+    fn from(raw: RawAprsPacket) -> Self {
+        raw.packet
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ORIGINAL: &[u8] = b"N0CALL-0>APRS,WIDE1*,WIDE2*:!4903.50N/07201.75W-Test/A=001234";
+
+    #[test]
+    fn encode_unmodified() {
+        let raw = RawAprsPacket::decode_textual(ORIGINAL).unwrap();
+        assert!(!raw.is_modified());
+
+        let mut buf = vec![];
+        raw.encode_textual(&mut buf).unwrap();
+        assert_eq!(buf, ORIGINAL);
+
+        // borrowing the packet mutably doesn't change it
+        let mut raw = raw;
+        let _ = raw.packet_mut();
+        assert!(!raw.is_modified());
+        let mut buf = vec![];
+        raw.encode_textual(&mut buf).unwrap();
+        assert_eq!(buf, ORIGINAL);
+
+        // the canonical encoding differs
+        let mut buf = vec![];
+        raw.packet().encode_textual(&mut buf).unwrap();
+        assert_ne!(buf, ORIGINAL);
+    }
+
+    #[test]
+    fn encode_modified_header() {
+        let mut raw = RawAprsPacket::decode_textual(ORIGINAL).unwrap();
+        raw.via_mut()[1] = Via::Callsign(Callsign::new_with_ssid("N0DIGI", "1"), true);
+        assert!(raw.is_modified());
+
+        let mut buf = vec![];
+        raw.encode_textual(&mut buf).unwrap();
+        assert_eq!(
+            buf,
+            &b"N0CALL>APRS,WIDE1,N0DIGI-1*:!4903.50N/07201.75W-Test/A=001234"[..]
+        );
+    }
+
+    #[test]
+    fn encode_modified_body() {
+        let mut raw = RawAprsPacket::decode_textual(ORIGINAL).unwrap();
+        match raw.data_mut() {
+            AprsData::Position(p) => p.comment = b"Changed".to_vec(),
+            _ => panic!("Unexpected data type"),
+        }
+
+        let mut buf = vec![];
+        raw.encode_textual(&mut buf).unwrap();
+        assert_eq!(
+            buf,
+            &b"N0CALL-0>APRS,WIDE1*,WIDE2*:!4903.50N/07201.75W-Changed/A=001234"[..]
+        );
+    }
+
+    #[test]
+    fn encode_modified_destination() {
+        let mut raw = RawAprsPacket::decode_textual(ORIGINAL).unwrap();
+        match raw.data_mut() {
+            AprsData::Position(p) => p.to = Callsign::new_no_ssid("APZ001"),
+            _ => panic!("Unexpected data type"),
+        }
+
+        let mut buf = vec![];
+        raw.encode_textual(&mut buf).unwrap();
+        assert_eq!(
+            buf,
+            &b"N0CALL>APZ001,WIDE1,WIDE2*:!4903.50N/07201.75W-Test/A=001234"[..]
+        );
+    }
+}
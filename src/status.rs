@@ -6,6 +6,7 @@
 //! - ">12.6V 0.2A 22degC"              (report without timestamp)
 //! - ">120503hFatal error"             (report with timestamp in HMS format)
 //! - ">281205zSystem will shutdown"    (report with timestamp in DHM format)
+//! - ">IO91SX/G Hello"                 (report starting with a Maidenhead locator)
 
 use std::convert::TryFrom;
 use std::io::Write;
@@ -14,8 +15,22 @@ use Callsign;
 use DecodeError;
 use DhmTimestamp;
 use EncodeError;
+use MaidenheadLocator;
+use SpecViolation;
 use Timestamp;
 
+use crate::validate;
+
+/// A status report without timestamp can begin with a 4 or 6 character
+/// Maidenhead locator and the station's symbol.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GridStatus {
+    pub locator: MaidenheadLocator,
+    pub symbol_table: char,
+    pub symbol_code: char,
+    pub text: Vec<u8>,
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct AprsStatus {
     pub to: Callsign,
@@ -45,6 +60,32 @@ impl AprsStatus {
         }
     }
 
+    /// Creates a status report that begins with a Maidenhead locator.
+    pub fn new_grid(to: Callsign, grid: &GridStatus) -> Self {
+        let mut comment = grid.locator.as_bytes().to_vec();
+        comment.extend_from_slice(&[grid.symbol_table as u8, grid.symbol_code as u8]);
+        if !grid.text.is_empty() {
+            comment.push(b' ');
+            comment.extend_from_slice(&grid.text);
+        }
+
+        Self {
+            to,
+            timestamp: None,
+            comment,
+        }
+    }
+
+    /// Returns the Maidenhead locator, symbol and remaining text
+    /// if the status begins with a locator.
+    pub fn grid(&self) -> Option<GridStatus> {
+        if self.timestamp.is_some() {
+            return None;
+        }
+
+        decode_grid(&self.comment)
+    }
+
     pub fn is_timestamp_compliant(&self) -> bool {
         self.timestamp
             .as_ref()
@@ -63,7 +104,11 @@ impl AprsStatus {
     pub fn decode(b: &[u8], to: Callsign) -> Result<Self, DecodeError> {
         // Interpret the first 7 bytes as a timestamp, if valid.
         // Otherwise the whole field is the comment.
-        let timestamp = b.get(..7).and_then(|b| Timestamp::try_from(b).ok());
+        // A locator with symbol table '/' looks like an unsupported timestamp
+        let timestamp = match decode_grid(b) {
+            Some(_) => None,
+            None => b.get(..7).and_then(|b| Timestamp::try_from(b).ok()),
+        };
         let comment = if timestamp.is_some() { &b[7..] } else { b };
 
         Ok(AprsStatus {
@@ -73,6 +118,26 @@ impl AprsStatus {
         })
     }
 
+    /// Returns all violations of the APRS spec
+    pub fn validate(&self) -> Vec<SpecViolation> {
+        let mut violations = vec![];
+        validate::check_timestamp(self.timestamp.as_ref(), &mut violations);
+        if !self.is_timestamp_compliant() {
+            if let Some(t) = &self.timestamp {
+                violations.push(SpecViolation::NoncompliantTimestamp(t.clone()));
+            }
+        }
+
+        let max = if self.timestamp.is_some() {
+            validate::MAX_STATUS_LEN_WITH_TIMESTAMP
+        } else {
+            validate::MAX_STATUS_LEN
+        };
+        validate::check_comment(self.comment.len(), max, &mut violations);
+
+        violations
+    }
+
     pub fn encode<W: Write>(&self, buf: &mut W) -> Result<(), EncodeError> {
         write!(buf, ">")?;
 
@@ -86,6 +151,25 @@ impl AprsStatus {
     }
 }
 
+fn decode_grid(b: &[u8]) -> Option<GridStatus> {
+    [6, 4].iter().find_map(|&len| {
+        let locator = MaidenheadLocator::decode(b.get(..len)?)?;
+        let symbol = b.get(len..(len + 2))?;
+        let text = match b.get(len + 2) {
+            None => &[][..],
+            Some(b' ') => &b[(len + 3)..],
+            Some(_) => return None,
+        };
+
+        Some(GridStatus {
+            locator,
+            symbol_table: symbol[0] as char,
+            symbol_code: symbol[1] as char,
+            text: text.to_vec(),
+        })
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -158,4 +242,46 @@ mod tests {
         assert_eq!(result.timestamp, None);
         assert!(result.is_timestamp_compliant());
     }
+
+    #[test]
+    fn parse_with_grid() {
+        let result = AprsStatus::decode(&b"IO91SX/G Hello"[..], default_callsign()).unwrap();
+
+        assert_eq!(
+            result.grid(),
+            Some(GridStatus {
+                locator: MaidenheadLocator::new("IO91SX").unwrap(),
+                symbol_table: '/',
+                symbol_code: 'G',
+                text: b"Hello".to_vec(),
+            })
+        );
+
+        let result = AprsStatus::decode(&b"IO91/G"[..], default_callsign()).unwrap();
+        let grid = result.grid().unwrap();
+        assert_eq!(grid.locator, MaidenheadLocator::new("IO91").unwrap());
+        assert_eq!(grid.text, b"");
+
+        for b in [&b"Hello world"[..], b"IO91SX/GHello", b"312359zIO91SX/G"] {
+            let result = AprsStatus::decode(b, default_callsign()).unwrap();
+            assert_eq!(result.grid(), None);
+        }
+    }
+
+    #[test]
+    fn encode_with_grid() {
+        let status = AprsStatus::new_grid(
+            default_callsign(),
+            &GridStatus {
+                locator: MaidenheadLocator::new("IO91SX").unwrap(),
+                symbol_table: '/',
+                symbol_code: 'G',
+                text: b"Hello".to_vec(),
+            },
+        );
+
+        let mut buf = vec![];
+        status.encode(&mut buf).unwrap();
+        assert_eq!(buf, b">IO91SX/G Hello");
+    }
 }
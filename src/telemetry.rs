@@ -0,0 +1,598 @@
+//! A Telemetry Report contains a sequence number, five analog values and
+//! eight digital values. The report starts with the 'T#' APRS Data Type
+//! Identifier. Mic-E stations use 'MIC' in place of the sequence number.
+//!
+//! Examples:
+//! - "T#005,199,000,255,073,123,01101001"
+//! - "T#MIC199,000,255,073,123,01101001"
+//! - "T#123,12.5,0.1,,4,5,00000000 comment"
+//!
+//! The meaning of the values is defined by the telemetry station using
+//! messages addressed to itself (see [`TelemetryDefinition`]).
+
+use std::io::Write;
+
+use bytes::parse_bytes;
+use Callsign;
+use DecodeError;
+use EncodeError;
+use SpecViolation;
+
+use crate::validate;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TelemetrySequence {
+    Number(u32),
+    /// Telemetry sent by a Mic-E station.
+    /// The comma after 'MIC' is optional, `comma` keeps whether it was there.
+    Mic {
+        comma: bool,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AprsTelemetry {
+    pub to: Callsign,
+
+    pub sequence: TelemetrySequence,
+    /// Up to five analog values. Values that are empty or not a number are `None`.
+    /// The APRS spec only allows integers from 0 to 255, but floats are common in practice.
+    pub analog: Vec<Option<f64>>,
+    /// The eight digital values, B1 first
+    pub digital: Option<[bool; 8]>,
+    pub comment: Vec<u8>,
+}
+
+impl AprsTelemetry {
+    pub fn decode(b: &[u8], to: Callsign) -> Result<Self, DecodeError> {
+        let (sequence, rest) = if let Some(rest) = b.strip_prefix(b"MIC") {
+            // the comma after MIC is optional
+            match rest.strip_prefix(b",") {
+                Some(rest) => (TelemetrySequence::Mic { comma: true }, rest),
+                None => (TelemetrySequence::Mic { comma: false }, rest),
+            }
+        } else {
+            let mut splitter = b.splitn(2, |x| *x == b',');
+            let sequence = splitter
+                .next()
+                .and_then(parse_bytes)
+                .ok_or_else(|| DecodeError::InvalidTelemetry(b.to_vec()))?;
+
+            (
+                TelemetrySequence::Number(sequence),
+                splitter.next().unwrap_or_default(),
+            )
+        };
+
+        let mut fields = rest.splitn(6, |x| *x == b',');
+
+        let analog = fields.by_ref().take(5).map(parse_analog).collect();
+
+        let (digital, comment) = match fields.next() {
+            Some(f) => match f.get(..8).and_then(parse_digital) {
+                Some(digital) => (Some(digital), f[8..].to_vec()),
+                None => (None, f.to_vec()),
+            },
+            None => (None, vec![]),
+        };
+
+        Ok(Self {
+            to,
+            sequence,
+            analog,
+            digital,
+            comment,
+        })
+    }
+
+    /// Returns all violations of the APRS spec.
+    /// Analog values that aren't integers from 0 to 255 are common,
+    /// but not allowed by the spec.
+    pub fn validate(&self) -> Vec<SpecViolation> {
+        let mut violations = vec![];
+        if let TelemetrySequence::Number(n) = self.sequence {
+            if n > validate::MAX_TELEMETRY_SEQUENCE {
+                violations.push(SpecViolation::TelemetrySequenceOutOfRange(n));
+            }
+        }
+
+        for (i, value) in self.analog.iter().enumerate() {
+            if let Some(v) = value {
+                if v.fract() != 0.0 || !(0.0..=255.0).contains(v) {
+                    violations.push(SpecViolation::AnalogValueOutOfRange(i));
+                }
+            }
+        }
+
+        violations
+    }
+
+    pub fn encode<W: Write>(&self, buf: &mut W) -> Result<(), EncodeError> {
+        if self.analog.len() > 5 {
+            return Err(EncodeError::InvalidData);
+        }
+
+        write!(buf, "T#")?;
+
+        match self.sequence {
+            TelemetrySequence::Number(n) => write!(buf, "{:03}", n)?,
+            TelemetrySequence::Mic { .. } => write!(buf, "MIC")?,
+        }
+
+        for (i, value) in self.analog.iter().enumerate() {
+            if i > 0 || self.sequence != (TelemetrySequence::Mic { comma: false }) {
+                write!(buf, ",")?;
+            }
+
+            match value {
+                Some(v) if v.fract() == 0.0 && *v >= 0.0 && *v < 1000.0 => {
+                    write!(buf, "{:03}", *v as u32)?
+                }
+                Some(v) => write!(buf, "{}", v)?,
+                None => {}
+            }
+        }
+
+        if self.digital.is_some() || !self.comment.is_empty() {
+            // digital values are always the sixth field
+            for _ in self.analog.len()..5 {
+                write!(buf, ",")?;
+            }
+            write!(buf, ",")?;
+        }
+
+        if let Some(digital) = &self.digital {
+            for bit in digital {
+                write!(buf, "{}", if *bit { '1' } else { '0' })?;
+            }
+        }
+
+        buf.write_all(&self.comment)?;
+
+        Ok(())
+    }
+}
+
+/// Names of the analog channels A1-A5 followed by the digital channels B1-B8
+/// are limited to these lengths in `PARM.` and `UNIT.` messages.
+const MAX_LABEL_LENGTHS: [usize; 13] = [7, 7, 6, 6, 5, 6, 5, 4, 4, 4, 3, 3, 3];
+const MAX_PROJECT_TITLE_LENGTH: usize = 23;
+
+/// Converts a raw analog value to its engineering value using `a*x^2 + b*x + c`
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TelemetryEquation {
+    pub a: f64,
+    pub b: f64,
+    pub c: f64,
+}
+
+impl TelemetryEquation {
+    pub fn apply(&self, raw: f64) -> f64 {
+        self.a * raw * raw + self.b * raw + self.c
+    }
+}
+
+impl Default for TelemetryEquation {
+    /// The identity equation, used when no `EQNS.` message was received
+    fn default() -> Self {
+        Self {
+            a: 0.0,
+            b: 1.0,
+            c: 0.0,
+        }
+    }
+}
+
+/// Telemetry metadata, sent by a telemetry station as messages addressed to itself.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TelemetryDefinition {
+    /// `PARM.` - names of the analog and digital channels
+    Parameters(Vec<Vec<u8>>),
+    /// `UNIT.` - units of the analog channels and labels of the digital channels
+    Units(Vec<Vec<u8>>),
+    /// `EQNS.` - equations for the analog channels
+    Equations(Vec<TelemetryEquation>),
+    /// `BITS.` - the value of each digital channel that means "active",
+    /// and the title of the project
+    Bits {
+        sense: [bool; 8],
+        project_title: Vec<u8>,
+    },
+}
+
+impl TelemetryDefinition {
+    /// Decodes the text of a message. Returns `None` if the text is not a telemetry definition.
+    pub fn decode(text: &[u8]) -> Option<Self> {
+        let (kind, rest) = (text.get(..5)?, &text[5..]);
+
+        let definition = match kind {
+            b"PARM." => Self::Parameters(split_labels(rest)),
+            b"UNIT." => Self::Units(split_labels(rest)),
+            b"EQNS." => {
+                let coefficients = rest
+                    .split(|x| *x == b',')
+                    .map(parse_analog)
+                    .collect::<Vec<_>>();
+
+                let equations = coefficients
+                    .chunks(3)
+                    .take(5)
+                    .map(|c| {
+                        let default = TelemetryEquation::default();
+                        TelemetryEquation {
+                            a: c.first().copied().flatten().unwrap_or(default.a),
+                            b: c.get(1).copied().flatten().unwrap_or(default.b),
+                            c: c.get(2).copied().flatten().unwrap_or(default.c),
+                        }
+                    })
+                    .collect();
+
+                Self::Equations(equations)
+            }
+            b"BITS." => {
+                let sense = rest.get(..8).and_then(parse_digital)?;
+                let project_title = rest
+                    .get(8..)
+                    .and_then(|t| t.strip_prefix(b","))
+                    .unwrap_or_default()
+                    .to_vec();
+
+                Self::Bits {
+                    sense,
+                    project_title,
+                }
+            }
+            _ => return None,
+        };
+
+        Some(definition)
+    }
+
+    /// Encodes the definition as message text.
+    /// Fails if the definition exceeds the limits of the APRS spec.
+    pub fn encode<W: Write>(&self, buf: &mut W) -> Result<(), EncodeError> {
+        match self {
+            Self::Parameters(labels) => {
+                write!(buf, "PARM.")?;
+                encode_labels(buf, labels)?;
+            }
+            Self::Units(labels) => {
+                write!(buf, "UNIT.")?;
+                encode_labels(buf, labels)?;
+            }
+            Self::Equations(equations) => {
+                if equations.len() > 5 {
+                    return Err(EncodeError::InvalidTelemetryDefinition);
+                }
+
+                write!(buf, "EQNS.")?;
+                for (i, e) in equations.iter().enumerate() {
+                    if i > 0 {
+                        write!(buf, ",")?;
+                    }
+                    write!(buf, "{},{},{}", e.a, e.b, e.c)?;
+                }
+            }
+            Self::Bits {
+                sense,
+                project_title,
+            } => {
+                if project_title.len() > MAX_PROJECT_TITLE_LENGTH {
+                    return Err(EncodeError::InvalidTelemetryDefinition);
+                }
+
+                write!(buf, "BITS.")?;
+                for bit in sense {
+                    write!(buf, "{}", if *bit { '1' } else { '0' })?;
+                }
+                if !project_title.is_empty() {
+                    write!(buf, ",")?;
+                    buf.write_all(project_title)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn split_labels(b: &[u8]) -> Vec<Vec<u8>> {
+    b.split(|x| *x == b',')
+        .take(13)
+        .map(|l| l.to_vec())
+        .collect()
+}
+
+fn encode_labels<W: Write>(buf: &mut W, labels: &[Vec<u8>]) -> Result<(), EncodeError> {
+    if labels.len() > MAX_LABEL_LENGTHS.len() {
+        return Err(EncodeError::InvalidTelemetryDefinition);
+    }
+
+    for (i, (label, max_len)) in labels.iter().zip(MAX_LABEL_LENGTHS).enumerate() {
+        if label.len() > max_len || label.contains(&b',') {
+            return Err(EncodeError::InvalidTelemetryDefinition);
+        }
+
+        if i > 0 {
+            write!(buf, ",")?;
+        }
+        buf.write_all(label)?;
+    }
+
+    Ok(())
+}
+
+// `parse` would also accept e.g. "nan", "inf" and "1e5"
+fn parse_analog(b: &[u8]) -> Option<f64> {
+    let value = std::str::from_utf8(b).ok()?.trim();
+    if !value
+        .bytes()
+        .all(|c| c.is_ascii_digit() || c == b'-' || c == b'.')
+    {
+        return None;
+    }
+
+    value.parse().ok()
+}
+
+fn parse_digital(b: &[u8]) -> Option<[bool; 8]> {
+    let mut digital = [false; 8];
+
+    for (bit, c) in digital.iter_mut().zip(b) {
+        *bit = match c {
+            b'0' => false,
+            b'1' => true,
+            _ => return None,
+        };
+    }
+
+    Some(digital)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn default_callsign() -> Callsign {
+        Callsign::new_no_ssid("VE9")
+    }
+
+    #[test]
+    fn parse_telemetry() {
+        let result =
+            AprsTelemetry::decode(&b"005,199,000,255,073,123,01101001"[..], default_callsign())
+                .unwrap();
+
+        assert_eq!(
+            result,
+            AprsTelemetry {
+                to: default_callsign(),
+                sequence: TelemetrySequence::Number(5),
+                analog: vec![Some(199.0), Some(0.0), Some(255.0), Some(73.0), Some(123.0)],
+                digital: Some([false, true, true, false, true, false, false, true]),
+                comment: vec![],
+            }
+        );
+    }
+
+    #[test]
+    fn parse_mic() {
+        let result =
+            AprsTelemetry::decode(&b"MIC199,000,255,073,123,01101001"[..], default_callsign())
+                .unwrap();
+        assert_eq!(result.sequence, TelemetrySequence::Mic { comma: false });
+        assert_eq!(result.analog[0], Some(199.0));
+
+        let result =
+            AprsTelemetry::decode(&b"MIC,199,000,255,073,123,01101001"[..], default_callsign())
+                .unwrap();
+        assert_eq!(result.sequence, TelemetrySequence::Mic { comma: true });
+        assert_eq!(result.analog[0], Some(199.0));
+    }
+
+    #[test]
+    fn parse_no_float_literals() {
+        let result =
+            AprsTelemetry::decode(&b"123,nan,inf,1e5,-1.5,+1"[..], default_callsign()).unwrap();
+
+        assert_eq!(result.analog, vec![None, None, None, Some(-1.5), None]);
+    }
+
+    #[test]
+    fn parse_floats_and_garbage() {
+        let result = AprsTelemetry::decode(
+            &b"123,12.5,-0.1,,abc,5,00000011 Balloon"[..],
+            default_callsign(),
+        )
+        .unwrap();
+
+        assert_eq!(result.sequence, TelemetrySequence::Number(123));
+        assert_eq!(
+            result.analog,
+            vec![Some(12.5), Some(-0.1), None, None, Some(5.0)]
+        );
+        assert_eq!(
+            result.digital,
+            Some([false, false, false, false, false, false, true, true])
+        );
+        assert_eq!(result.comment, b" Balloon");
+    }
+
+    #[test]
+    fn parse_without_digital() {
+        let result = AprsTelemetry::decode(&b"001,1,2,3"[..], default_callsign()).unwrap();
+
+        assert_eq!(result.analog, vec![Some(1.0), Some(2.0), Some(3.0)]);
+        assert_eq!(result.digital, None);
+        assert_eq!(result.comment, b"");
+    }
+
+    #[test]
+    fn parse_invalid_sequence() {
+        assert_eq!(
+            AprsTelemetry::decode(&b"abc,1,2,3"[..], default_callsign()),
+            Err(DecodeError::InvalidTelemetry(b"abc,1,2,3".to_vec()))
+        );
+    }
+
+    #[test]
+    fn parse_definitions() {
+        assert_eq!(
+            TelemetryDefinition::decode(
+                b"PARM.Battery,Btemp,ATemp,Pres,Alt,Camra,Chut,Sun,10m,ATV"
+            ),
+            Some(TelemetryDefinition::Parameters(vec![
+                b"Battery".to_vec(),
+                b"Btemp".to_vec(),
+                b"ATemp".to_vec(),
+                b"Pres".to_vec(),
+                b"Alt".to_vec(),
+                b"Camra".to_vec(),
+                b"Chut".to_vec(),
+                b"Sun".to_vec(),
+                b"10m".to_vec(),
+                b"ATV".to_vec(),
+            ]))
+        );
+
+        assert_eq!(
+            TelemetryDefinition::decode(b"UNIT.v/100,deg.F,deg.F,Mbar,Kft"),
+            Some(TelemetryDefinition::Units(vec![
+                b"v/100".to_vec(),
+                b"deg.F".to_vec(),
+                b"deg.F".to_vec(),
+                b"Mbar".to_vec(),
+                b"Kft".to_vec(),
+            ]))
+        );
+
+        assert_eq!(
+            TelemetryDefinition::decode(b"EQNS.0,5.2,0,0,.53,-32,3,4.39,49"),
+            Some(TelemetryDefinition::Equations(vec![
+                TelemetryEquation {
+                    a: 0.0,
+                    b: 5.2,
+                    c: 0.0
+                },
+                TelemetryEquation {
+                    a: 0.0,
+                    b: 0.53,
+                    c: -32.0
+                },
+                TelemetryEquation {
+                    a: 3.0,
+                    b: 4.39,
+                    c: 49.0
+                },
+            ]))
+        );
+
+        assert_eq!(
+            TelemetryDefinition::decode(b"BITS.10110000,N6VUJ High Altitude Balloon"),
+            Some(TelemetryDefinition::Bits {
+                sense: [true, false, true, true, false, false, false, false],
+                project_title: b"N6VUJ High Altitude Balloon".to_vec(),
+            })
+        );
+
+        assert_eq!(TelemetryDefinition::decode(b"Hello World"), None);
+        assert_eq!(TelemetryDefinition::decode(b"BITS.1011"), None);
+    }
+
+    #[test]
+    fn apply_equation() {
+        let e = TelemetryEquation {
+            a: 0.0,
+            b: 0.53,
+            c: -32.0,
+        };
+        assert_relative_eq!(e.apply(100.0), 21.0);
+        assert_relative_eq!(TelemetryEquation::default().apply(42.0), 42.0);
+    }
+
+    #[test]
+    fn encode_definitions() {
+        let originals = vec![
+            &b"PARM.Battery,Btemp,ATemp,Pres,Alt,Camra,Chut,Sun,10m,ATV"[..],
+            &b"UNIT.v/100,deg.F,deg.F,Mbar,Kft"[..],
+            &b"EQNS.0,5.2,0,0,0.53,-32,3,4.39,49"[..],
+            &b"BITS.10110000,N6VUJ High Altitude"[..],
+            &b"BITS.10110000"[..],
+        ];
+
+        for o in originals {
+            let mut buf = vec![];
+            TelemetryDefinition::decode(o)
+                .unwrap()
+                .encode(&mut buf)
+                .unwrap();
+            assert_eq!(o, &buf[..], "{}", buf.escape_ascii());
+        }
+    }
+
+    #[test]
+    fn encode_non_compliant_definitions() {
+        let mut buf = vec![];
+
+        let too_long = TelemetryDefinition::Parameters(vec![b"Battery voltage".to_vec()]);
+        assert!(matches!(
+            too_long.encode(&mut buf),
+            Err(EncodeError::InvalidTelemetryDefinition)
+        ));
+
+        let too_many = TelemetryDefinition::Units(vec![b"V".to_vec(); 14]);
+        assert!(matches!(
+            too_many.encode(&mut buf),
+            Err(EncodeError::InvalidTelemetryDefinition)
+        ));
+
+        let long_title = TelemetryDefinition::Bits {
+            sense: [true; 8],
+            project_title: b"A very long project title".to_vec(),
+        };
+        assert!(matches!(
+            long_title.encode(&mut buf),
+            Err(EncodeError::InvalidTelemetryDefinition)
+        ));
+    }
+
+    #[test]
+    fn parse_and_reencode() {
+        let originals = vec![
+            &b"005,199,000,255,073,123,01101001"[..],
+            &b"MIC199,000,255,073,123,01101001"[..],
+            b"MIC,199,000,255,073,123,01101001",
+            &b"123,12.5,-0.1,,,005,00000011 Balloon"[..],
+            &b"1234,001,002"[..],
+        ];
+
+        for o in originals {
+            let result = AprsTelemetry::decode(o, default_callsign()).unwrap();
+
+            let mut buf = vec![];
+            result.encode(&mut buf).unwrap();
+            assert_eq!(&buf[..2], b"T#");
+            assert_eq!(o, &buf[2..], "{}", buf.escape_ascii());
+        }
+    }
+
+    #[test]
+    fn validate() {
+        let result =
+            AprsTelemetry::decode(&b"005,199,000,255,073,123,01101001"[..], default_callsign())
+                .unwrap();
+        assert_eq!(result.validate(), vec![]);
+
+        let result =
+            AprsTelemetry::decode(&b"1234,12.5,0,256,-1,5"[..], default_callsign()).unwrap();
+        assert_eq!(
+            result.validate(),
+            vec![
+                SpecViolation::TelemetrySequenceOutOfRange(1234),
+                SpecViolation::AnalogValueOutOfRange(0),
+                SpecViolation::AnalogValueOutOfRange(2),
+                SpecViolation::AnalogValueOutOfRange(3),
+            ]
+        );
+    }
+}
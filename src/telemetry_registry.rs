@@ -0,0 +1,269 @@
+//! Telemetry reports only contain raw values. The names, units, equations
+//! and bit senses are sent separately as telemetry definition messages.
+//! The [`TelemetryRegistry`] remembers the latest definitions of each
+//! station and applies them to incoming telemetry reports.
+
+use std::collections::HashMap;
+
+use AprsData;
+use AprsPacket;
+use AprsTelemetry;
+use Callsign;
+use TelemetryDefinition;
+use TelemetryEquation;
+use TelemetrySequence;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+struct StationDefinitions {
+    parameters: Vec<Vec<u8>>,
+    units: Vec<Vec<u8>>,
+    equations: Vec<TelemetryEquation>,
+    sense: Option<[bool; 8]>,
+    project_title: Vec<u8>,
+}
+
+impl StationDefinitions {
+    fn update(&mut self, definition: TelemetryDefinition) {
+        match definition {
+            TelemetryDefinition::Parameters(p) => self.parameters = p,
+            TelemetryDefinition::Units(u) => self.units = u,
+            TelemetryDefinition::Equations(e) => self.equations = e,
+            TelemetryDefinition::Bits {
+                sense,
+                project_title,
+            } => {
+                self.sense = Some(sense);
+                self.project_title = project_title;
+            }
+        }
+    }
+
+    fn label(labels: &[Vec<u8>], index: usize) -> Option<Vec<u8>> {
+        labels.get(index).filter(|l| !l.is_empty()).cloned()
+    }
+
+    fn apply(&self, station: Callsign, telemetry: &AprsTelemetry) -> TelemetryReadings {
+        let analog = telemetry
+            .analog
+            .iter()
+            .enumerate()
+            .map(|(i, raw)| {
+                let equation = self.equations.get(i).copied().unwrap_or_default();
+
+                AnalogReading {
+                    name: Self::label(&self.parameters, i),
+                    unit: Self::label(&self.units, i),
+                    value: raw.map(|r| equation.apply(r)),
+                }
+            })
+            .collect();
+
+        let digital = telemetry
+            .digital
+            .iter()
+            .flatten()
+            .enumerate()
+            .map(|(i, value)| {
+                // without a BITS definition, a set bit is considered active
+                let sense = self.sense.map(|s| s[i]).unwrap_or(true);
+
+                DigitalReading {
+                    name: Self::label(&self.parameters, i + 5),
+                    label: Self::label(&self.units, i + 5),
+                    value: *value,
+                    active: *value == sense,
+                }
+            })
+            .collect();
+
+        let project_title = Some(self.project_title.clone()).filter(|t| !t.is_empty());
+
+        TelemetryReadings {
+            station,
+            sequence: telemetry.sequence,
+            project_title,
+            analog,
+            digital,
+        }
+    }
+}
+
+/// A telemetry report with the station's definitions applied
+#[derive(Clone, Debug, PartialEq)]
+pub struct TelemetryReadings {
+    pub station: Callsign,
+    pub sequence: TelemetrySequence,
+    pub project_title: Option<Vec<u8>>,
+    pub analog: Vec<AnalogReading>,
+    pub digital: Vec<DigitalReading>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AnalogReading {
+    pub name: Option<Vec<u8>>,
+    pub unit: Option<Vec<u8>>,
+    /// The value after applying the station's equation,
+    /// or `None` if the report didn't contain a valid value
+    pub value: Option<f64>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DigitalReading {
+    pub name: Option<Vec<u8>>,
+    /// The label that applies when the channel is active
+    pub label: Option<Vec<u8>>,
+    /// The raw value of the bit
+    pub value: bool,
+    /// Whether the bit matches the station's bit sense
+    pub active: bool,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct TelemetryRegistry {
+    stations: HashMap<Callsign, StationDefinitions>,
+}
+
+impl TelemetryRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Processes a packet.
+    /// Telemetry definition messages update the definitions of the station they are addressed to.
+    /// Telemetry reports are returned with the definitions of their source station applied.
+    pub fn ingest(&mut self, packet: &AprsPacket) -> Option<TelemetryReadings> {
+        match &packet.data {
+            AprsData::Message(m) => {
+                let definition = m.telemetry_definition()?;
+                let station = std::str::from_utf8(&m.addressee)
+                    .ok()
+                    .and_then(Callsign::new)?;
+
+                self.stations.entry(station).or_default().update(definition);
+
+                None
+            }
+            AprsData::Telemetry(t) => Some(self.readings(packet.from.clone(), t)),
+            _ => None,
+        }
+    }
+
+    /// Applies the known definitions of `station` to a telemetry report
+    pub fn readings(&self, station: Callsign, telemetry: &AprsTelemetry) -> TelemetryReadings {
+        match self.stations.get(&station) {
+            Some(definitions) => definitions.apply(station, telemetry),
+            None => StationDefinitions::default().apply(station, telemetry),
+        }
+    }
+
+    /// Forgets all definitions of `station`
+    pub fn remove(&mut self, station: &Callsign) {
+        self.stations.remove(station);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ingest(registry: &mut TelemetryRegistry, packet: &str) -> Option<TelemetryReadings> {
+        registry.ingest(&AprsPacket::decode_textual(packet.as_bytes()).unwrap())
+    }
+
+    #[test]
+    fn readings_without_definitions() {
+        let mut registry = TelemetryRegistry::new();
+
+        let readings = ingest(
+            &mut registry,
+            "N0QBF-11>APRS:T#005,199,000,255,073,123,01101001",
+        )
+        .unwrap();
+
+        assert_eq!(readings.station, Callsign::new_with_ssid("N0QBF", "11"));
+        assert_eq!(readings.sequence, TelemetrySequence::Number(5));
+        assert_eq!(readings.project_title, None);
+        assert_eq!(
+            readings.analog[0],
+            AnalogReading {
+                name: None,
+                unit: None,
+                value: Some(199.0),
+            }
+        );
+        assert_eq!(
+            readings.digital[1],
+            DigitalReading {
+                name: None,
+                label: None,
+                value: true,
+                active: true,
+            }
+        );
+    }
+
+    #[test]
+    fn readings_with_definitions() {
+        let mut registry = TelemetryRegistry::new();
+
+        let definitions = vec![
+            "N0QBF-11>APRS::N0QBF-11 :PARM.Battery,Btemp,ATemp,Pres,Alt,Camra,Chut,Sun,10m,ATV",
+            "N0QBF-11>APRS::N0QBF-11 :UNIT.v/100,deg.F,deg.F,Mbar,Kft,Click,OPEN,on,on,hi",
+            "N0QBF-11>APRS::N0QBF-11 :EQNS.0,5.2,0,0,.53,-32,3,4.39,49,-32,3,18,1,2,3",
+            "N0QBF-11>APRS::N0QBF-11 :BITS.10110000,N6VUJ High Altitude Balloon",
+        ];
+        for d in definitions {
+            assert_eq!(ingest(&mut registry, d), None);
+        }
+
+        let readings = ingest(
+            &mut registry,
+            "N0QBF-11>APRS:T#005,199,000,255,073,123,01101001",
+        )
+        .unwrap();
+
+        assert_eq!(
+            readings.project_title,
+            Some(b"N6VUJ High Altitude Balloon".to_vec())
+        );
+        assert_eq!(readings.analog[1].name, Some(b"Btemp".to_vec()));
+        assert_eq!(readings.analog[1].unit, Some(b"deg.F".to_vec()));
+        assert_relative_eq!(readings.analog[1].value.unwrap(), -32.0);
+        assert_relative_eq!(
+            readings.analog[2].value.unwrap(),
+            3.0 * 255.0 * 255.0 + 4.39 * 255.0 + 49.0
+        );
+
+        // B1 is 0 but active high
+        assert_eq!(
+            readings.digital[0],
+            DigitalReading {
+                name: Some(b"Camra".to_vec()),
+                label: Some(b"Click".to_vec()),
+                value: false,
+                active: false,
+            }
+        );
+        // B2 is 1 but active low
+        assert!(readings.digital[1].value);
+        assert!(!readings.digital[1].active);
+        // B8 has no name
+        assert_eq!(readings.digital[7].name, None);
+    }
+
+    #[test]
+    fn definitions_are_per_station() {
+        let mut registry = TelemetryRegistry::new();
+
+        ingest(&mut registry, "N0QBF-11>APRS::N0QBF-11 :PARM.Battery");
+        let readings = ingest(&mut registry, "N0QBF-12>APRS:T#005,199").unwrap();
+        assert_eq!(readings.analog[0].name, None);
+
+        let readings = ingest(&mut registry, "N0QBF-11>APRS:T#005,199").unwrap();
+        assert_eq!(readings.analog[0].name, Some(b"Battery".to_vec()));
+
+        registry.remove(&Callsign::new_with_ssid("N0QBF", "11"));
+        let readings = ingest(&mut registry, "N0QBF-11>APRS:T#005,199").unwrap();
+        assert_eq!(readings.analog[0].name, None);
+    }
+}
@@ -0,0 +1,158 @@
+//! A Third-Party packet starts with the '}' APRS Data Type Identifier.
+//! It encapsulates a complete packet with its own source, destination and path,
+//! e.g. a packet that an IGate forwards from APRS-IS to RF.
+//!
+//! Example:
+//! - "}WB2OSZ-4>APN383,TCPIP,WB2OSZ-3*:!4237.14N/07120.83W#"
+//!
+//! A third-party packet can contain another one, but only up to `MAX_DEPTH`
+//! levels. Anything nested deeper is decoded as `AprsData::Unknown`.
+
+use std::io::Write;
+
+use AprsData;
+use AprsPacket;
+use Callsign;
+use DecodeError;
+use EncodeError;
+use SpecViolation;
+use Via;
+
+use crate::diagnostic::SpannedError;
+
+/// The number of third-party headers that are decoded
+pub(crate) const MAX_DEPTH: usize = 2;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AprsThirdParty {
+    pub to: Callsign,
+
+    pub packet: Box<AprsPacket>,
+}
+
+impl AprsThirdParty {
+    /// Wraps a packet received from APRS-IS for transmission on RF by `igate`.
+    /// As required by the spec, the path of the inner packet is replaced
+    /// with `TCPIP,<igate>*`.
+    pub fn wrap(to: Callsign, mut packet: AprsPacket, igate: Callsign) -> Self {
+        packet.via = vec![
+            Via::Callsign(Callsign::new_no_ssid("TCPIP"), false),
+            Via::Callsign(igate, true),
+        ];
+
+        Self {
+            to,
+            packet: Box::new(packet),
+        }
+    }
+
+    pub fn decode(b: &[u8], to: Callsign) -> Result<Self, DecodeError> {
+        Self::decode_nested(b, to, 1, &mut vec![]).map_err(DecodeError::from)
+    }
+
+    /// Decodes the inner packet of a third-party packet at `depth`,
+    /// starting from 1 for the outermost one
+    pub(crate) fn decode_nested(
+        b: &[u8],
+        to: Callsign,
+        depth: usize,
+        recovered: &mut Vec<SpannedError>,
+    ) -> Result<Self, SpannedError> {
+        let packet = AprsPacket::decode_textual_with(b, recovered, |body, to, recovered| {
+            AprsData::decode_nested(body, to, depth, recovered)
+        })?;
+
+        Ok(Self {
+            to,
+            packet: Box::new(packet),
+        })
+    }
+
+    /// Returns all violations of the APRS spec by the inner packet
+    pub fn validate(&self) -> Vec<SpecViolation> {
+        self.packet.validate()
+    }
+
+    pub fn encode<W: Write>(&self, buf: &mut W) -> Result<(), EncodeError> {
+        buf.write_all(b"}")?;
+        self.packet.encode_textual(buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use AprsData;
+    use QConstruct;
+
+    fn default_callsign() -> Callsign {
+        Callsign::new_no_ssid("VE9")
+    }
+
+    #[test]
+    fn decode_third_party() {
+        let result = AprsThirdParty::decode(
+            b"WB2OSZ-4>APN383,TCPIP,WB2OSZ-3*:!4237.14N/07120.83W#",
+            default_callsign(),
+        )
+        .unwrap();
+
+        assert_eq!(result.packet.from, Callsign::new_with_ssid("WB2OSZ", "4"));
+        assert_eq!(result.packet.to(), Some(&Callsign::new_no_ssid("APN383")));
+        assert_eq!(
+            result.packet.via,
+            vec![
+                Via::Callsign(Callsign::new_no_ssid("TCPIP"), true),
+                Via::Callsign(Callsign::new_with_ssid("WB2OSZ", "3"), true),
+            ]
+        );
+        assert!(matches!(result.packet.data, AprsData::Position(_)));
+    }
+
+    #[test]
+    fn decode_invalid_inner_packet() {
+        assert_eq!(
+            AprsThirdParty::decode(b"no header", default_callsign()),
+            Err(DecodeError::InvalidPacket(b"no header".to_vec()))
+        );
+    }
+
+    #[test]
+    fn decode_nested() {
+        let result = AprsThirdParty::decode(b"A>B:}C>D:}E>F:>Hello", default_callsign()).unwrap();
+        let inner = match result.packet.data {
+            AprsData::ThirdParty(t) => t,
+            d => panic!("Unexpected data type {:?}", d),
+        };
+        assert!(matches!(inner.packet.data, AprsData::Unknown(_)));
+
+        let mut buf = vec![];
+        inner.encode(&mut buf).unwrap();
+        assert_eq!(buf, b"}C>D:}E>F:>Hello");
+    }
+
+    #[test]
+    fn decode_deeply_nested() {
+        let b = b"A>B:}".repeat(10_000);
+        assert!(AprsThirdParty::decode(&b, default_callsign()).is_ok());
+    }
+
+    #[test]
+    fn wrap_and_encode() {
+        let packet = AprsPacket::decode_textual(b"N0CALL>APRS,TCPIP*,qAC,T2TEST:>Hello").unwrap();
+
+        let third_party = AprsThirdParty::wrap(
+            default_callsign(),
+            packet,
+            Callsign::new_with_ssid("VE9", "10"),
+        );
+        assert!(!third_party
+            .packet
+            .via
+            .contains(&Via::QConstruct(QConstruct::AC)));
+
+        let mut buf = vec![];
+        third_party.encode(&mut buf).unwrap();
+        assert_eq!(buf, b"}N0CALL>APRS,TCPIP,VE9-10*:>Hello");
+    }
+}
@@ -0,0 +1,57 @@
+//! Packets with a data type identifier that isn't supported are kept as they are,
+//! so that they can be forwarded without changes.
+
+use std::io::Write;
+
+use Callsign;
+use EncodeError;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AprsUnknown {
+    pub to: Callsign,
+
+    /// The APRS Data Type Identifier, `None` if the information field is empty
+    pub data_type: Option<u8>,
+    /// The information field without the data type identifier
+    pub body: Vec<u8>,
+}
+
+impl AprsUnknown {
+    pub fn decode(b: &[u8], to: Callsign) -> Self {
+        Self {
+            to,
+            data_type: b.first().copied(),
+            body: b.get(1..).unwrap_or(&[]).to_vec(),
+        }
+    }
+
+    pub fn encode<W: Write>(&self, buf: &mut W) -> Result<(), EncodeError> {
+        if let Some(data_type) = self.data_type {
+            buf.write_all(&[data_type])?;
+        }
+        buf.write_all(&self.body)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn default_callsign() -> Callsign {
+        Callsign::new_no_ssid("VE9")
+    }
+
+    #[test]
+    fn decode_and_encode() {
+        for b in [&b""[..], b"#", b"#Hello"] {
+            let unknown = AprsUnknown::decode(b, default_callsign());
+            assert_eq!(unknown.data_type, b.first().copied());
+
+            let mut buf = vec![];
+            unknown.encode(&mut buf).unwrap();
+            assert_eq!(buf, b);
+        }
+    }
+}
@@ -0,0 +1,97 @@
+//! A User-Defined packet starts with the '{' APRS Data Type Identifier,
+//! followed by a one character user ID assigned to the experimenter,
+//! a one character packet type and the user-defined data.
+//!
+//! Example:
+//! - "{Q1qwerty"                       (user ID `Q`, packet type `1`)
+
+use std::io::Write;
+
+use Callsign;
+use DecodeError;
+use EncodeError;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AprsUserDefined {
+    pub to: Callsign,
+
+    pub user_id: u8,
+    pub packet_type: u8,
+    pub data: Vec<u8>,
+}
+
+impl AprsUserDefined {
+    pub fn decode(b: &[u8], to: Callsign) -> Result<Self, DecodeError> {
+        if b.len() < 2 {
+            return Err(DecodeError::InvalidUserDefined(b.to_vec()));
+        }
+
+        Ok(Self {
+            to,
+            user_id: b[0],
+            packet_type: b[1],
+            data: b[2..].to_vec(),
+        })
+    }
+
+    pub fn encode<W: Write>(&self, buf: &mut W) -> Result<(), EncodeError> {
+        buf.write_all(&[b'{', self.user_id, self.packet_type])?;
+        buf.write_all(&self.data)?;
+
+        Ok(())
+    }
+}
+
+/// A packet with invalid or test data, starting with the ',' APRS Data Type Identifier.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AprsTestData {
+    pub to: Callsign,
+
+    pub data: Vec<u8>,
+}
+
+impl AprsTestData {
+    pub fn decode(b: &[u8], to: Callsign) -> Self {
+        Self {
+            to,
+            data: b.to_vec(),
+        }
+    }
+
+    pub fn encode<W: Write>(&self, buf: &mut W) -> Result<(), EncodeError> {
+        buf.write_all(b",")?;
+        buf.write_all(&self.data)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn default_callsign() -> Callsign {
+        Callsign::new_no_ssid("VE9")
+    }
+
+    #[test]
+    fn decode_user_defined() {
+        let result = AprsUserDefined::decode(b"Q1qwerty", default_callsign()).unwrap();
+
+        assert_eq!(result.user_id, b'Q');
+        assert_eq!(result.packet_type, b'1');
+        assert_eq!(result.data, b"qwerty");
+
+        let mut buf = vec![];
+        result.encode(&mut buf).unwrap();
+        assert_eq!(buf, b"{Q1qwerty");
+    }
+
+    #[test]
+    fn decode_user_defined_too_short() {
+        assert_eq!(
+            AprsUserDefined::decode(b"Q", default_callsign()),
+            Err(DecodeError::InvalidUserDefined(b"Q".to_vec()))
+        );
+    }
+}
@@ -0,0 +1,258 @@
+//! Checks for the limits of APRS101 that the decoders and encoders
+//! don't enforce themselves, e.g. the length of comments.
+//! Each data type reports its violations with `validate`.
+
+use Callsign;
+use Field;
+use Timestamp;
+
+// maximum lengths, see APRS101
+pub(crate) const MAX_COMMENT_LEN: usize = 43;
+pub(crate) const MAX_COMMENT_LEN_WITH_EXTENSION: usize = 36;
+pub(crate) const MAX_STATUS_LEN: usize = 62;
+pub(crate) const MAX_STATUS_LEN_WITH_TIMESTAMP: usize = 55;
+pub(crate) const MAX_MESSAGE_LEN: usize = 67;
+pub(crate) const MAX_NAME_LEN: usize = 9;
+pub(crate) const MAX_CALLSIGN_LEN: usize = 6;
+pub(crate) const MAX_SSID: u8 = 15;
+pub(crate) const MAX_TELEMETRY_SEQUENCE: u32 = 999;
+pub(crate) const MIN_ITEM_NAME_LEN: usize = 3;
+
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct EncodeOptions {
+    /// Refuse packets that violate the spec with `EncodeError::NonCompliant`
+    pub strict: bool,
+}
+
+impl EncodeOptions {
+    pub fn strict() -> Self {
+        Self { strict: true }
+    }
+
+    pub fn lenient() -> Self {
+        Self { strict: false }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum SpecViolation {
+    #[error("Lowercase callsign: {0}")]
+    LowercaseCallsign(Callsign),
+    /// AX.25 addresses have at most 6 characters
+    #[error("Callsign is longer than 6 characters: {0}")]
+    CallsignTooLong(Callsign),
+    /// AX.25 SSIDs are numbers from 0 to 15
+    #[error("Invalid SSID: {0}")]
+    InvalidSsid(Callsign),
+    #[error("Timestamp out of range: {0:?}")]
+    TimestampOutOfRange(Timestamp),
+    /// Status reports should only have DDHHMM timestamps
+    #[error("Timestamp not allowed here: {0:?}")]
+    NoncompliantTimestamp(Timestamp),
+    #[error("Comment is {len} bytes long, at most {max} are allowed")]
+    CommentTooLong { len: usize, max: usize },
+    #[error("Message is {len} bytes long, at most {max} are allowed")]
+    MessageTooLong { len: usize, max: usize },
+    /// Message text can't contain '|', '~' or '{'
+    #[error("Message contains {:?}", *.0 as char)]
+    InvalidMessageCharacter(u8),
+    /// Message IDs have 1 to 5 letters or digits
+    #[error("Invalid message ID: {0:?}")]
+    InvalidMessageId(Vec<u8>),
+    /// The checksum of a raw GPS sentence is missing or wrong
+    #[error("Invalid NMEA checksum: {0:?}")]
+    InvalidNmeaChecksum(Vec<u8>),
+    #[error("Message addressee is longer than 9 bytes: {0:?}")]
+    AddresseeTooLong(Vec<u8>),
+    #[error("Object name contains spaces: {0:?}")]
+    SpaceInObjectName(Vec<u8>),
+    /// Object names have 1 to 9 bytes, item names 3 to 9
+    #[error("Invalid name length: {0:?}")]
+    InvalidNameLength(Vec<u8>),
+    /// Item names can't contain '!' or ' '
+    #[error("Invalid item name: {0:?}")]
+    InvalidItemName(Vec<u8>),
+    /// The symbol table is '/', '\\' or an overlay,
+    /// the symbol code a printable character
+    #[error("Invalid symbol: {:?}{:?}", *.table as char, *.code as char)]
+    InvalidSymbol { table: u8, code: u8 },
+    #[error("Telemetry sequence number is larger than 999: {0}")]
+    TelemetrySequenceOutOfRange(u32),
+    /// The index of an analog telemetry value that isn't an integer from 0 to 255
+    #[error("Analog telemetry value {0} is out of range")]
+    AnalogValueOutOfRange(usize),
+}
+
+impl SpecViolation {
+    /// The part of the packet that violates the spec
+    pub fn field(&self) -> Field {
+        match self {
+            Self::LowercaseCallsign(_) | Self::CallsignTooLong(_) | Self::InvalidSsid(_) => {
+                Field::Header
+            }
+            Self::TimestampOutOfRange(_) | Self::NoncompliantTimestamp(_) => Field::Timestamp,
+            Self::CommentTooLong { .. } => Field::Comment,
+            Self::MessageTooLong { .. }
+            | Self::InvalidMessageCharacter(_)
+            | Self::InvalidMessageId(_)
+            | Self::AddresseeTooLong(_) => Field::Message,
+            Self::SpaceInObjectName(_) | Self::InvalidNameLength(_) | Self::InvalidItemName(_) => {
+                Field::Name
+            }
+            Self::InvalidNmeaChecksum(_) => Field::Nmea,
+            Self::InvalidSymbol { .. } => Field::Position,
+            Self::TelemetrySequenceOutOfRange(_) | Self::AnalogValueOutOfRange(_) => {
+                Field::Telemetry
+            }
+        }
+    }
+
+    /// What the spec expects instead
+    pub fn expected(&self) -> &'static str {
+        match self {
+            Self::LowercaseCallsign(_) => "an uppercase callsign",
+            Self::CallsignTooLong(_) => "a callsign of at most 6 characters",
+            Self::InvalidSsid(_) => "an SSID from 0 to 15",
+            Self::TimestampOutOfRange(_) => "a timestamp with a valid day, hour, minute and second",
+            Self::NoncompliantTimestamp(_) => "a timestamp as DDHHMMz",
+            Self::CommentTooLong { .. } => "a shorter comment",
+            Self::MessageTooLong { .. } => "a message of at most 67 characters",
+            Self::InvalidMessageCharacter(_) => "a message without '|', '~' or '{'",
+            Self::InvalidMessageId(_) => "a message ID of 1 to 5 letters or digits",
+            Self::InvalidNmeaChecksum(_) => "a checksum matching the NMEA sentence",
+            Self::AddresseeTooLong(_) => "an addressee of at most 9 characters",
+            Self::SpaceInObjectName(_) => "an object name without spaces",
+            Self::InvalidNameLength(_) => {
+                "an object name of 1 to 9 or an item name of 3 to 9 characters"
+            }
+            Self::InvalidItemName(_) => "an item name without '!' or ' '",
+            Self::InvalidSymbol { .. } => {
+                "a symbol table of '/', '\\', 0-9 or A-Z and a printable symbol code"
+            }
+            Self::TelemetrySequenceOutOfRange(_) => "a telemetry sequence number from 0 to 999",
+            Self::AnalogValueOutOfRange(_) => "analog telemetry values from 0 to 255",
+        }
+    }
+}
+
+pub(crate) fn check_callsign(callsign: &Callsign, violations: &mut Vec<SpecViolation>) {
+    let lowercase = callsign.call().bytes().any(|c| c.is_ascii_lowercase())
+        || callsign
+            .ssid()
+            .map(|s| s.bytes().any(|c| c.is_ascii_lowercase()))
+            .unwrap_or(false);
+
+    if lowercase {
+        violations.push(SpecViolation::LowercaseCallsign(callsign.clone()));
+    }
+
+    // the limits of AX.25 addresses, which APRS-IS doesn't enforce
+    if callsign.call().len() > MAX_CALLSIGN_LEN {
+        violations.push(SpecViolation::CallsignTooLong(callsign.clone()));
+    }
+
+    if let Some(ssid) = callsign.ssid() {
+        let valid = ssid.bytes().all(|c| c.is_ascii_digit())
+            && ssid.parse::<u8>().map_or(false, |s| s <= MAX_SSID);
+        if !valid {
+            violations.push(SpecViolation::InvalidSsid(callsign.clone()));
+        }
+    }
+}
+
+pub(crate) fn check_symbol(table: u8, code: u8, violations: &mut Vec<SpecViolation>) {
+    let valid_table =
+        table == b'/' || table == b'\\' || table.is_ascii_digit() || table.is_ascii_uppercase();
+    let valid_code = (b'!'..=b'~').contains(&code);
+
+    if !valid_table || !valid_code {
+        violations.push(SpecViolation::InvalidSymbol { table, code });
+    }
+}
+
+pub(crate) fn check_timestamp(timestamp: Option<&Timestamp>, violations: &mut Vec<SpecViolation>) {
+    let in_range = match timestamp {
+        Some(Timestamp::DDHHMM(d, h, m)) => (1..=31).contains(d) && *h < 24 && *m < 60,
+        Some(Timestamp::HHMMSS(h, m, s)) => *h < 24 && *m < 60 && *s < 60,
+        Some(Timestamp::MDHM(mo, d, h, m)) => {
+            (1..=12).contains(mo) && (1..=31).contains(d) && *h < 24 && *m < 60
+        }
+        Some(Timestamp::Unsupported(_)) | None => true,
+    };
+
+    if let (false, Some(t)) = (in_range, timestamp) {
+        violations.push(SpecViolation::TimestampOutOfRange(t.clone()));
+    }
+}
+
+pub(crate) fn check_comment(len: usize, max: usize, violations: &mut Vec<SpecViolation>) {
+    if len > max {
+        violations.push(SpecViolation::CommentTooLong { len, max });
+    }
+}
+
+/// The maximum comment length of positions, objects and items
+pub(crate) fn max_comment_len(has_extension: bool) -> usize {
+    if has_extension {
+        MAX_COMMENT_LEN_WITH_EXTENSION
+    } else {
+        MAX_COMMENT_LEN
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamp_ranges() {
+        let valid = [
+            Timestamp::DDHHMM(31, 23, 59),
+            Timestamp::HHMMSS(23, 59, 59),
+            Timestamp::MDHM(12, 1, 0, 0),
+        ];
+        let invalid = [
+            Timestamp::DDHHMM(0, 12, 0),
+            Timestamp::HHMMSS(12, 60, 0),
+            Timestamp::MDHM(13, 1, 0, 0),
+        ];
+
+        for t in valid.iter() {
+            let mut violations = vec![];
+            check_timestamp(Some(t), &mut violations);
+            assert!(violations.is_empty(), "{:?}", t);
+        }
+        for t in invalid.iter() {
+            let mut violations = vec![];
+            check_timestamp(Some(t), &mut violations);
+            assert_eq!(
+                violations,
+                vec![SpecViolation::TimestampOutOfRange(t.clone())]
+            );
+        }
+    }
+
+    #[test]
+    fn callsign_limits() {
+        let check = |callsign: Callsign| {
+            let mut violations = vec![];
+            check_callsign(&callsign, &mut violations);
+            violations
+        };
+
+        assert_eq!(check(Callsign::new_with_ssid("N0CALL", "15")), vec![]);
+        assert_eq!(
+            check(Callsign::new_no_ssid("T2FINLAND")),
+            vec![SpecViolation::CallsignTooLong(Callsign::new_no_ssid(
+                "T2FINLAND"
+            ))]
+        );
+        for ssid in ["16", "A", "-1"] {
+            let callsign = Callsign::new_with_ssid("N0CALL", ssid);
+            assert_eq!(
+                check(callsign.clone()),
+                vec![SpecViolation::InvalidSsid(callsign)]
+            );
+        }
+    }
+}
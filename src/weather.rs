@@ -0,0 +1,134 @@
+//! A positionless Weather Report contains weather data without any position
+//! information. The report starts with the '_' APRS Data Type Identifier,
+//! followed by a MDHM timestamp, the weather data, the software type and the
+//! weather unit type.
+//!
+//! Examples:
+//! - "_10090556c220s004g005t077r000p000P000h50b09900wRSW"
+
+use std::io::Write;
+
+use Callsign;
+use DecodeError;
+use EncodeError;
+use SpecViolation;
+use Timestamp;
+use Weather;
+
+use crate::components::weather::WeatherFormat;
+use crate::validate;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AprsWeather {
+    pub to: Callsign,
+
+    pub timestamp: Timestamp,
+    pub weather: Weather,
+    pub comment: Vec<u8>,
+}
+
+impl AprsWeather {
+    pub fn decode(b: &[u8], to: Callsign) -> Result<Self, DecodeError> {
+        let timestamp = Timestamp::decode_mdhm(
+            b.get(..8)
+                .ok_or_else(|| DecodeError::InvalidTimestamp(b.to_vec()))?,
+        )?;
+
+        let (weather, comment) = Weather::decode(&b[8..], WeatherFormat::Positionless)
+            .ok_or_else(|| DecodeError::InvalidWeather(b.to_vec()))?;
+
+        Ok(Self {
+            to,
+            timestamp,
+            weather,
+            comment: comment.to_vec(),
+        })
+    }
+
+    /// Returns all violations of the APRS spec
+    pub fn validate(&self) -> Vec<SpecViolation> {
+        let mut violations = vec![];
+        validate::check_timestamp(Some(&self.timestamp), &mut violations);
+
+        violations
+    }
+
+    pub fn encode<W: Write>(&self, buf: &mut W) -> Result<(), EncodeError> {
+        write!(buf, "_")?;
+
+        self.timestamp.encode(buf)?;
+        self.weather.encode(buf, WeatherFormat::Positionless)?;
+
+        buf.write_all(&self.comment)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn default_callsign() -> Callsign {
+        Callsign::new_no_ssid("VE9")
+    }
+
+    #[test]
+    fn parse_weather() {
+        let result = AprsWeather::decode(
+            &b"10090556c220s004g005t077r000p000P000h50b09900wRSW"[..],
+            default_callsign(),
+        )
+        .unwrap();
+
+        assert_eq!(result.to, default_callsign());
+        assert_eq!(result.timestamp, Timestamp::MDHM(10, 9, 5, 56));
+        assert_eq!(
+            result.weather,
+            Weather {
+                wind_direction: Some(220),
+                wind_speed: Some(4),
+                wind_gust: Some(5),
+                temperature: Some(77),
+                rain_last_hour: Some(0),
+                rain_last_24_hours: Some(0),
+                rain_since_midnight: Some(0),
+                humidity: Some(50),
+                barometric_pressure: Some(9900),
+                ..Default::default()
+            }
+        );
+        assert_eq!(result.comment, b"wRSW");
+    }
+
+    #[test]
+    fn parse_invalid_timestamp() {
+        let result = AprsWeather::decode(&b"1009c220s004g005t077"[..], default_callsign());
+
+        assert_eq!(
+            result,
+            Err(DecodeError::InvalidTimestamp(b"1009c220".to_vec()))
+        );
+    }
+
+    #[test]
+    fn parse_missing_wind() {
+        let result = AprsWeather::decode(&b"10090556g005t077"[..], default_callsign());
+
+        assert_eq!(
+            result,
+            Err(DecodeError::InvalidWeather(b"10090556g005t077".to_vec()))
+        );
+    }
+
+    #[test]
+    fn parse_and_reencode() {
+        let original = &b"10090556c...s...g...t-05h00wRSW"[..];
+        let result = AprsWeather::decode(original, default_callsign()).unwrap();
+
+        let mut buf = vec![];
+        result.encode(&mut buf).unwrap();
+        assert_eq!(buf[0], b'_');
+        assert_eq!(original, &buf[1..]);
+    }
+}